@@ -1,6 +1,8 @@
 use ash::vk::{AccessFlags, ImageAspectFlags, ImageLayout, PipelineStageFlags};
 use std::sync::{Arc, Mutex};
 use vengine_rs::buffer::buffer::{VEBuffer, VEBufferUsage};
+use vengine_rs::buffer::storage_buffer::{VEDrawIndirect, VEStorageBuffer};
+use vengine_rs::compute::compute_stage::VEComputeStage;
 use vengine_rs::core::command_buffer::VECommandBuffer;
 use vengine_rs::core::descriptor_set::VEDescriptorSet;
 use vengine_rs::core::descriptor_set_layout::{
@@ -48,6 +50,9 @@ struct MeshStage {
 
     vertex_attributes: Vec<VertexAttribFormat>,
     render_stage: Arc<VERenderStage>,
+
+    cull_descriptor_set_layout: VEDescriptorSetLayout,
+    cull_stage: VEComputeStage,
 }
 
 struct Mesh {
@@ -56,6 +61,10 @@ struct Mesh {
     sampler: VESampler,
 
     descriptor_set: VEDescriptorSet,
+
+    // filled by the cull shader, drawn with draw_indirect
+    draw_arguments: VEStorageBuffer<VEDrawIndirect>,
+    cull_descriptor_set: VEDescriptorSet,
 }
 
 #[allow(clippy::unwrap_used)]
@@ -205,6 +214,22 @@ impl DingusApp {
                 .unwrap(),
         );
 
+        let cull_shader = toolkit
+            .create_shader_module("examples/dingus_mesh/cull.spv", VEShaderModuleType::Compute)
+            .unwrap();
+
+        let cull_descriptor_set_layout = toolkit
+            .create_descriptor_set_layout(&[VEDescriptorSetLayoutField {
+                binding: 0,
+                typ: VEDescriptorSetFieldType::StorageBuffer,
+                stage: VEDescriptorSetFieldStage::Compute,
+            }])
+            .unwrap();
+
+        let cull_stage = toolkit
+            .create_compute_stage(&[&cull_descriptor_set_layout], &cull_shader)
+            .unwrap();
+
         MeshStage {
             uniform_buffer,
             depth_buffer,
@@ -218,6 +243,9 @@ impl DingusApp {
             global_descriptor_set,
 
             render_stage,
+
+            cull_descriptor_set_layout,
+            cull_stage,
         }
     }
 
@@ -253,17 +281,63 @@ impl DingusApp {
             .bind_image_sampler(0, &texture, texture_view, &sampler)
             .unwrap();
 
+        // the cull shader sets instance_count, until then the draw would be empty
+        let mut draw_arguments = toolkit
+            .create_storage_buffer::<VEDrawIndirect>(1, Some(VEMemoryProperties::HostCoherent))
+            .unwrap();
+        draw_arguments
+            .write(
+                0,
+                &[VEDrawIndirect {
+                    vertex_count: vertex_buffer.vertex_count,
+                    ..VEDrawIndirect::default()
+                }],
+            )
+            .unwrap();
+
+        let cull_descriptor_set = self
+            .mesh_stage
+            .cull_descriptor_set_layout
+            .create_descriptor_set()
+            .unwrap();
+        cull_descriptor_set
+            .bind_buffer(0, draw_arguments.get_buffer())
+            .unwrap();
+
         Mesh {
             vertex_buffer,
             texture,
             sampler,
 
             descriptor_set,
+
+            draw_arguments,
+            cull_descriptor_set,
         }
     }
 
     fn record(&mut self) {
-        self.command_buffer.begin().unwrap();
+        // recorded once and resubmitted every frame
+        self.command_buffer.begin_with(&[]).unwrap();
+
+        // barriers are not allowed inside the render pass, so culling runs before it
+        self.mesh_stage.cull_stage.bind(&self.command_buffer);
+        for mesh in &mut self.meshes {
+            self.mesh_stage.cull_stage.set_descriptor_set(
+                &self.command_buffer,
+                0,
+                &mesh.cull_descriptor_set,
+            );
+            self.mesh_stage.cull_stage.dispatch(
+                &self.command_buffer,
+                mesh.draw_arguments.len() as u32,
+                1,
+                1,
+            );
+            mesh.draw_arguments
+                .get_buffer_mut()
+                .barrier_compute_write_to_indirect(&self.command_buffer);
+        }
 
         self.mesh_stage.render_stage.bind(&self.command_buffer);
 
@@ -280,7 +354,11 @@ impl DingusApp {
                 &mesh.descriptor_set,
             );
 
-            mesh.vertex_buffer.draw_instanced(&self.command_buffer, 1);
+            mesh.vertex_buffer
+                .buffer
+                .bind_vertex_buffer(&self.command_buffer, 0, 0);
+            mesh.draw_arguments
+                .draw_indirect(&self.toolkit.device, &self.command_buffer);
         }
 
         self.mesh_stage
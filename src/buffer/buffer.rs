@@ -1,8 +1,11 @@
 use crate::core::command_buffer::{VECommandBuffer, VECommandBufferError};
 use crate::core::command_pool::VECommandPool;
 use crate::core::device::VEDevice;
+use crate::core::fence::{VEFence, VEFenceError};
 use crate::core::main_device_queue::{VEMainDeviceQueue, VEMainDeviceQueueError};
+use crate::core::memory_barrier::{submit_barriers, VEBufferMemoryBarrier};
 use crate::core::memory_properties::{get_memory_properties_flags, VEMemoryProperties};
+use crate::core::sync_state::VESyncState;
 use crate::memory::memory_chunk::{VEMemoryChunkError, VESingleAllocation};
 use crate::memory::memory_manager::{VEMemoryManager, VEMemoryManagerError};
 use ash::vk;
@@ -35,6 +38,24 @@ pub enum VEBufferError {
 
     #[error("queue locking failed")]
     QueueLockingFailed,
+
+    #[error("offset is not aligned to the index type size")]
+    MisalignedIndexOffset,
+
+    #[error("fence error")]
+    FenceError(#[from] VEFenceError),
+
+    #[error("element range outside of the storage buffer")]
+    ElementRangeOutOfBounds,
+
+    #[error("storage buffer needs at least one element")]
+    EmptyStorageBuffer,
+
+    #[error("vertex data is empty")]
+    EmptyVertexData,
+
+    #[error("index data is empty")]
+    EmptyIndexData,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -44,6 +65,47 @@ pub enum VEBufferUsage {
     TransferSource,
     TransferDestination,
     Vertex,
+    Index,
+    Indirect,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum VEIndexType {
+    U16,
+    U32,
+}
+
+pub(crate) fn get_index_type(typ: VEIndexType) -> vk::IndexType {
+    match typ {
+        VEIndexType::U16 => vk::IndexType::UINT16,
+        VEIndexType::U32 => vk::IndexType::UINT32,
+    }
+}
+
+pub fn get_index_type_byte_size(typ: VEIndexType) -> u64 {
+    match typ {
+        VEIndexType::U16 => 2,
+        VEIndexType::U32 => 4,
+    }
+}
+
+// index_offset and total size for from_vertex_and_index_data, indices start at the first
+// multiple of the index size after the vertices
+fn get_vertex_and_index_layout(
+    vertex_len: usize,
+    index_len: usize,
+    index_type: VEIndexType,
+) -> Result<(u64, u64), VEBufferError> {
+    // zero sized buffers are invalid in vulkan and a mesh without either part cannot be drawn
+    if vertex_len == 0 {
+        return Err(VEBufferError::EmptyVertexData);
+    }
+    if index_len == 0 {
+        return Err(VEBufferError::EmptyIndexData);
+    }
+    let index_size = get_index_type_byte_size(index_type);
+    let index_offset = (vertex_len as u64).div_ceil(index_size) * index_size;
+    Ok((index_offset, index_offset + index_len as u64))
 }
 
 pub struct VEBuffer {
@@ -52,22 +114,39 @@ pub struct VEBuffer {
     command_pool: Arc<VECommandPool>,
     memory_manager: Arc<Mutex<VEMemoryManager>>,
     allocation: VESingleAllocation,
+    last_use: Option<Arc<VEFence>>,
+    sync_state: VESyncState,
+    sharing_mode: SharingMode,
+    queue_family_indices: Vec<u32>,
     pub buffer: Buffer,
     pub size: u64,
     pub usage: Vec<VEBufferUsage>,
 }
 
+// settings past usage and size, see VEBuffer::new_with_options
+#[derive(Debug, Default)]
+pub struct VEBufferOptions<'a> {
+    pub memory_properties: Option<VEMemoryProperties>,
+    // shared between the given queue families, for example compute writing draw arguments
+    // that graphics reads, without ownership transfers. concurrent access may be slower
+    // than exclusive on some drivers, prefer exclusive with transfers for hot buffers.
+    // needs at least two distinct families, otherwise the buffer stays exclusive
+    pub queue_families: &'a [u32],
+    // bound in the memory manager transient arena, see VEBuffer::new_transient
+    pub transient: bool,
+}
+
 fn get_buffer_usage_flags(usages: &[VEBufferUsage]) -> vk::BufferUsageFlags {
     let mut flags = vk::BufferUsageFlags::empty();
     for usage in usages {
         match usage {
-            VEBufferUsage::Uniform => flags = flags | vk::BufferUsageFlags::UNIFORM_BUFFER,
-            VEBufferUsage::Storage => flags = flags | vk::BufferUsageFlags::STORAGE_BUFFER,
-            VEBufferUsage::TransferSource => flags = flags | vk::BufferUsageFlags::TRANSFER_SRC,
-            VEBufferUsage::TransferDestination => {
-                flags = flags | vk::BufferUsageFlags::TRANSFER_DST
-            }
-            VEBufferUsage::Vertex => flags = flags | vk::BufferUsageFlags::VERTEX_BUFFER,
+            VEBufferUsage::Uniform => flags |= vk::BufferUsageFlags::UNIFORM_BUFFER,
+            VEBufferUsage::Storage => flags |= vk::BufferUsageFlags::STORAGE_BUFFER,
+            VEBufferUsage::TransferSource => flags |= vk::BufferUsageFlags::TRANSFER_SRC,
+            VEBufferUsage::TransferDestination => flags |= vk::BufferUsageFlags::TRANSFER_DST,
+            VEBufferUsage::Vertex => flags |= vk::BufferUsageFlags::VERTEX_BUFFER,
+            VEBufferUsage::Index => flags |= vk::BufferUsageFlags::INDEX_BUFFER,
+            VEBufferUsage::Indirect => flags |= vk::BufferUsageFlags::INDIRECT_BUFFER,
         }
     }
     flags
@@ -83,6 +162,72 @@ impl VEBuffer {
         size: u64,
         memory_properties: Option<VEMemoryProperties>,
     ) -> Result<VEBuffer, VEBufferError> {
+        Self::new_with_options(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            usage,
+            size,
+            VEBufferOptions {
+                memory_properties,
+                ..VEBufferOptions::default()
+            },
+        )
+    }
+
+    // scratch buffer from the memory manager transient arena, its memory is reused after
+    // VEMemoryManager::reset_transient_arena so it must be dropped before the next reset
+    pub fn new_transient(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        usage: &[VEBufferUsage],
+        size: u64,
+        memory_properties: Option<VEMemoryProperties>,
+    ) -> Result<VEBuffer, VEBufferError> {
+        Self::new_with_options(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            usage,
+            size,
+            VEBufferOptions {
+                memory_properties,
+                transient: true,
+                ..VEBufferOptions::default()
+            },
+        )
+    }
+
+    pub fn new_with_options(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        usage: &[VEBufferUsage],
+        size: u64,
+        options: VEBufferOptions,
+    ) -> Result<VEBuffer, VEBufferError> {
+        let VEBufferOptions {
+            memory_properties,
+            queue_families,
+            transient,
+        } = options;
+
+        // concurrent sharing needs at least two distinct families, otherwise stay exclusive
+        let mut queue_family_indices = queue_families.to_vec();
+        queue_family_indices.sort_unstable();
+        queue_family_indices.dedup();
+        let sharing_mode = if queue_family_indices.len() > 1 {
+            SharingMode::CONCURRENT
+        } else {
+            queue_family_indices = vec![device.queue_family_index];
+            SharingMode::EXCLUSIVE
+        };
+
         unsafe {
             let buffer = device
                 .device
@@ -90,7 +235,8 @@ impl VEBuffer {
                     &BufferCreateInfo::default()
                         .size(size)
                         .usage(get_buffer_usage_flags(usage))
-                        .sharing_mode(SharingMode::EXCLUSIVE),
+                        .sharing_mode(sharing_mode)
+                        .queue_family_indices(&queue_family_indices),
                     None,
                 )
                 .map_err(VEBufferError::CreationFailed)?;
@@ -103,10 +249,17 @@ impl VEBuffer {
 
             let allocation = match mem_index {
                 None => return Err(VEBufferError::NoSuitableMemoryTypeFound),
-                Some(mem_index) => memory_manager
-                    .lock()
-                    .map_err(|_| VEBufferError::LockingMemoryManagerFailed)?
-                    .bind_buffer_memory(mem_index, buffer, mem_reqs.size)?,
+                Some(mem_index) => {
+                    let mut memory_manager = memory_manager
+                        .lock()
+                        .map_err(|_| VEBufferError::LockingMemoryManagerFailed)?;
+                    if transient {
+                        memory_manager
+                            .bind_transient_buffer_memory(mem_index, buffer, size, None)?
+                    } else {
+                        memory_manager.bind_buffer_memory(mem_index, buffer, size, None)?
+                    }
+                }
             };
 
             Ok(VEBuffer {
@@ -116,12 +269,66 @@ impl VEBuffer {
                 memory_manager,
                 buffer,
                 allocation,
+                last_use: None,
+                sync_state: VESyncState::default(),
+                sharing_mode,
+                queue_family_indices,
                 size,
                 usage: usage.to_vec(),
             })
         }
     }
 
+    // vertices at offset 0, indices right after, aligned to the index size
+    pub fn from_vertex_and_index_data(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        vertex_data: &[u8],
+        index_data: &[u8],
+        index_type: VEIndexType,
+    ) -> Result<(VEBuffer, u64, u64), VEBufferError> {
+        let vertex_offset = 0;
+        let (index_offset, total_size) =
+            get_vertex_and_index_layout(vertex_data.len(), index_data.len(), index_type)?;
+
+        let mut staging_buffer = VEBuffer::new(
+            device.clone(),
+            queue.clone(),
+            command_pool.clone(),
+            memory_manager.clone(),
+            &[VEBufferUsage::TransferSource],
+            total_size,
+            Some(VEMemoryProperties::HostCoherent),
+        )?;
+
+        let final_buffer = VEBuffer::new(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            &[
+                VEBufferUsage::Vertex,
+                VEBufferUsage::Index,
+                VEBufferUsage::TransferDestination,
+            ],
+            total_size,
+            Some(VEMemoryProperties::DeviceLocal),
+        )?;
+
+        unsafe {
+            let mem = staging_buffer.map()? as *mut u8;
+            let slice = std::slice::from_raw_parts_mut(mem, total_size as usize);
+            slice[..vertex_data.len()].copy_from_slice(vertex_data);
+            slice[index_offset as usize..].copy_from_slice(index_data);
+        }
+
+        staging_buffer.copy_to(&final_buffer, 0, 0, total_size)?;
+
+        Ok((final_buffer, vertex_offset, index_offset))
+    }
+
     pub fn map(&mut self) -> Result<*mut core::ffi::c_void, VEBufferError> {
         self.memory_manager
             .lock()
@@ -138,6 +345,84 @@ impl VEBuffer {
             .map_err(VEBufferError::MemoryManagerError)
     }
 
+    pub fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), VEBufferError> {
+        self.memory_manager
+            .lock()
+            .map_err(|_| VEBufferError::LockingMemoryManagerFailed)?
+            .write_allocation(&self.allocation, offset, data)
+            .map_err(VEBufferError::MemoryManagerError)
+    }
+
+    // caller must flush before GPU access on non coherent memory
+    pub fn write_no_flush(&mut self, offset: u64, data: &[u8]) -> Result<(), VEBufferError> {
+        self.memory_manager
+            .lock()
+            .map_err(|_| VEBufferError::LockingMemoryManagerFailed)?
+            .write_allocation_no_flush(&self.allocation, offset, data)
+            .map_err(VEBufferError::MemoryManagerError)
+    }
+
+    pub fn flush(&mut self, offset: u64, size: u64) -> Result<(), VEBufferError> {
+        self.memory_manager
+            .lock()
+            .map_err(|_| VEBufferError::LockingMemoryManagerFailed)?
+            .flush_allocation(&self.allocation, offset, size)
+            .map_err(VEBufferError::MemoryManagerError)
+    }
+
+    pub(crate) fn get_allocation(&self) -> &VESingleAllocation {
+        &self.allocation
+    }
+
+    // read only, for debugging why a barrier was emitted
+    pub fn get_sync_state(&self) -> &VESyncState {
+        &self.sync_state
+    }
+
+    pub(crate) fn get_sync_state_mut(&mut self) -> &mut VESyncState {
+        &mut self.sync_state
+    }
+
+    pub fn is_concurrent(&self) -> bool {
+        self.sharing_mode == SharingMode::CONCURRENT
+    }
+
+    pub fn get_queue_family_indices(&self) -> &[u32] {
+        &self.queue_family_indices
+    }
+
+    // fence of the last submission that used this buffer, set by the caller after submit
+    pub fn set_last_use(&mut self, fence: Arc<VEFence>) {
+        self.last_use = Some(fence);
+    }
+
+    pub fn wait_until_idle(&self) -> Result<(), VEBufferError> {
+        if let Some(fence) = &self.last_use {
+            fence.wait()?;
+        }
+        Ok(())
+    }
+
+    pub fn read_to_cpu(&mut self) -> Result<Vec<u8>, VEBufferError> {
+        self.wait_until_idle()?;
+        let mut result = vec![0u8; self.size as usize];
+        self.memory_manager
+            .lock()
+            .map_err(|_| VEBufferError::LockingMemoryManagerFailed)?
+            .read_allocation(&self.allocation, 0, &mut result)
+            .map_err(VEBufferError::MemoryManagerError)?;
+        Ok(result)
+    }
+
+    // needed before reading through the pointer returned by map on non coherent memory
+    pub fn invalidate(&mut self, offset: u64, size: u64) -> Result<(), VEBufferError> {
+        self.memory_manager
+            .lock()
+            .map_err(|_| VEBufferError::LockingMemoryManagerFailed)?
+            .invalidate_allocation(&self.allocation, offset, size)
+            .map_err(VEBufferError::MemoryManagerError)
+    }
+
     pub fn copy_to(
         &self,
         target: &VEBuffer,
@@ -185,6 +470,60 @@ impl VEBuffer {
             );
         }
     }
+
+    // compute shader writes of draw or dispatch arguments made visible to the indirect command
+    // read, record it between the dispatch that fills the buffer and the indirect draw
+    pub fn barrier_compute_write_to_indirect(&mut self, command_buffer: &VECommandBuffer) {
+        let barrier = VEBufferMemoryBarrier {
+            buffer: self.buffer,
+            src_access: vk::AccessFlags::SHADER_WRITE,
+            dst_access: vk::AccessFlags::INDIRECT_COMMAND_READ,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+        };
+        submit_barriers(
+            &self.device,
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::DRAW_INDIRECT,
+            &[],
+            &[barrier.build()],
+            &[],
+        );
+        self.sync_state
+            .record_read(vk::PipelineStageFlags::DRAW_INDIRECT);
+    }
+
+    pub fn bind_vertex_buffer(&self, command_buffer: &VECommandBuffer, binding: u32, offset: u64) {
+        unsafe {
+            self.device.device.cmd_bind_vertex_buffers(
+                command_buffer.handle,
+                binding,
+                &[self.buffer],
+                &[offset],
+            );
+        }
+    }
+
+    pub fn bind_index_buffer(
+        &self,
+        command_buffer: &VECommandBuffer,
+        offset: u64,
+        index_type: VEIndexType,
+    ) -> Result<(), VEBufferError> {
+        if !offset.is_multiple_of(get_index_type_byte_size(index_type)) {
+            return Err(VEBufferError::MisalignedIndexOffset);
+        }
+        unsafe {
+            self.device.device.cmd_bind_index_buffer(
+                command_buffer.handle,
+                self.buffer,
+                offset,
+                get_index_type(index_type),
+            );
+        }
+        Ok(())
+    }
 }
 
 impl Drop for VEBuffer {
@@ -193,6 +532,7 @@ impl Drop for VEBuffer {
         match locking_result {
             Ok(mut mem) => match { mem.free_allocation(&self.allocation) } {
                 Ok(_) => (),
+                // also the case after VEMemoryManager::free_all released the allocation
                 Err(_) => {}
             },
             Err(_) => {
@@ -201,3 +541,32 @@ impl Drop for VEBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indices_start_aligned_after_the_vertices() {
+        assert!(matches!(
+            get_vertex_and_index_layout(10, 6, VEIndexType::U32),
+            Ok((12, 18))
+        ));
+        assert!(matches!(
+            get_vertex_and_index_layout(12, 6, VEIndexType::U16),
+            Ok((12, 18))
+        ));
+    }
+
+    #[test]
+    fn empty_vertex_or_index_data_is_rejected() {
+        assert!(matches!(
+            get_vertex_and_index_layout(0, 6, VEIndexType::U16),
+            Err(VEBufferError::EmptyVertexData)
+        ));
+        assert!(matches!(
+            get_vertex_and_index_layout(12, 0, VEIndexType::U16),
+            Err(VEBufferError::EmptyIndexData)
+        ));
+    }
+}
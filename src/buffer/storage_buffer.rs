@@ -0,0 +1,328 @@
+use crate::buffer::buffer::{VEBuffer, VEBufferError, VEBufferUsage};
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::command_pool::VECommandPool;
+use crate::core::device::VEDevice;
+use crate::core::main_device_queue::VEMainDeviceQueue;
+use crate::core::memory_properties::VEMemoryProperties;
+use crate::memory::memory_manager::VEMemoryManager;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+// same layout as VkDrawIndirectCommand
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VEDrawIndirect {
+    pub vertex_count: u32,
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
+}
+
+// same layout as VkDrawIndexedIndirectCommand
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VEDrawIndexedIndirect {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub first_instance: u32,
+}
+
+// same layout as VkDispatchIndirectCommand
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VEDispatchIndirect {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+/// elements are copied to and from the buffer byte for byte
+///
+/// # Safety
+/// implementors must be repr(C) and must not contain padding bytes
+pub unsafe trait VEStorageBufferElement: Copy {}
+
+unsafe impl VEStorageBufferElement for VEDrawIndirect {}
+unsafe impl VEStorageBufferElement for VEDrawIndexedIndirect {}
+unsafe impl VEStorageBufferElement for VEDispatchIndirect {}
+unsafe impl VEStorageBufferElement for u32 {}
+unsafe impl VEStorageBufferElement for f32 {}
+
+fn elements_to_bytes<T: VEStorageBufferElement>(elements: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(elements.as_ptr() as *const u8, size_of_val(elements)) }
+}
+
+fn bytes_to_elements<T: VEStorageBufferElement>(bytes: &[u8]) -> Vec<T> {
+    bytes
+        .chunks_exact(size_of::<T>())
+        .map(|chunk| unsafe { std::ptr::read_unaligned(chunk.as_ptr() as *const T) })
+        .collect()
+}
+
+// typed array of elements, usable as storage buffer so compute can fill indirect arguments
+pub struct VEStorageBuffer<T: VEStorageBufferElement> {
+    buffer: VEBuffer,
+    len: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T: VEStorageBufferElement> VEStorageBuffer<T> {
+    pub fn new(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        len: usize,
+        memory_properties: Option<VEMemoryProperties>,
+    ) -> Result<VEStorageBuffer<T>, VEBufferError> {
+        // zero sized buffers are invalid in vulkan
+        if len == 0 {
+            return Err(VEBufferError::EmptyStorageBuffer);
+        }
+        let buffer = VEBuffer::new(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            &[
+                VEBufferUsage::Storage,
+                VEBufferUsage::Indirect,
+                VEBufferUsage::TransferSource,
+                VEBufferUsage::TransferDestination,
+            ],
+            (len * size_of::<T>()) as u64,
+            memory_properties,
+        )?;
+        Ok(VEStorageBuffer {
+            buffer,
+            len,
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn stride() -> u32 {
+        size_of::<T>() as u32
+    }
+
+    pub fn get_buffer(&self) -> &VEBuffer {
+        &self.buffer
+    }
+
+    pub fn get_buffer_mut(&mut self) -> &mut VEBuffer {
+        &mut self.buffer
+    }
+
+    // needs host visible memory
+    pub fn write(&mut self, first: usize, elements: &[T]) -> Result<(), VEBufferError> {
+        if first + elements.len() > self.len {
+            return Err(VEBufferError::ElementRangeOutOfBounds);
+        }
+        self.buffer
+            .write((first * size_of::<T>()) as u64, elements_to_bytes(elements))
+    }
+
+    pub fn read(&mut self) -> Result<Vec<T>, VEBufferError> {
+        Ok(bytes_to_elements(&self.buffer.read_to_cpu()?))
+    }
+}
+
+impl VEStorageBuffer<VEDrawIndirect> {
+    // one draw per element, the pipeline and vertex buffer must be bound
+    pub fn draw_indirect(&self, device: &VEDevice, command_buffer: &VECommandBuffer) {
+        if device.supports_multi_draw_indirect() {
+            unsafe {
+                device.device.cmd_draw_indirect(
+                    command_buffer.handle,
+                    self.buffer.buffer,
+                    0,
+                    self.len as u32,
+                    Self::stride(),
+                );
+            }
+        } else {
+            for i in 0..self.len {
+                unsafe {
+                    device.device.cmd_draw_indirect(
+                        command_buffer.handle,
+                        self.buffer.buffer,
+                        (i * size_of::<VEDrawIndirect>()) as u64,
+                        1,
+                        Self::stride(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl VEStorageBuffer<VEDrawIndexedIndirect> {
+    // one draw per element, the pipeline, vertex and index buffers must be bound
+    pub fn draw_indexed_indirect(&self, device: &VEDevice, command_buffer: &VECommandBuffer) {
+        if device.supports_multi_draw_indirect() {
+            unsafe {
+                device.device.cmd_draw_indexed_indirect(
+                    command_buffer.handle,
+                    self.buffer.buffer,
+                    0,
+                    self.len as u32,
+                    Self::stride(),
+                );
+            }
+        } else {
+            for i in 0..self.len {
+                unsafe {
+                    device.device.cmd_draw_indexed_indirect(
+                        command_buffer.handle,
+                        self.buffer.buffer,
+                        (i * size_of::<VEDrawIndexedIndirect>()) as u64,
+                        1,
+                        Self::stride(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl VEStorageBuffer<VEDispatchIndirect> {
+    // the compute stage must be bound
+    pub fn dispatch_indirect(
+        &self,
+        device: &VEDevice,
+        command_buffer: &VECommandBuffer,
+        index: usize,
+    ) -> Result<(), VEBufferError> {
+        if index >= self.len {
+            return Err(VEBufferError::ElementRangeOutOfBounds);
+        }
+        unsafe {
+            device.device.cmd_dispatch_indirect(
+                command_buffer.handle,
+                self.buffer.buffer,
+                (index * size_of::<VEDispatchIndirect>()) as u64,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk;
+    use std::mem::offset_of;
+
+    #[test]
+    fn draw_indirect_matches_vulkan_layout() {
+        assert_eq!(
+            size_of::<VEDrawIndirect>(),
+            size_of::<vk::DrawIndirectCommand>()
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndirect, vertex_count),
+            offset_of!(vk::DrawIndirectCommand, vertex_count)
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndirect, instance_count),
+            offset_of!(vk::DrawIndirectCommand, instance_count)
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndirect, first_vertex),
+            offset_of!(vk::DrawIndirectCommand, first_vertex)
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndirect, first_instance),
+            offset_of!(vk::DrawIndirectCommand, first_instance)
+        );
+    }
+
+    #[test]
+    fn draw_indexed_indirect_matches_vulkan_layout() {
+        assert_eq!(
+            size_of::<VEDrawIndexedIndirect>(),
+            size_of::<vk::DrawIndexedIndirectCommand>()
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndexedIndirect, index_count),
+            offset_of!(vk::DrawIndexedIndirectCommand, index_count)
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndexedIndirect, instance_count),
+            offset_of!(vk::DrawIndexedIndirectCommand, instance_count)
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndexedIndirect, first_index),
+            offset_of!(vk::DrawIndexedIndirectCommand, first_index)
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndexedIndirect, vertex_offset),
+            offset_of!(vk::DrawIndexedIndirectCommand, vertex_offset)
+        );
+        assert_eq!(
+            offset_of!(VEDrawIndexedIndirect, first_instance),
+            offset_of!(vk::DrawIndexedIndirectCommand, first_instance)
+        );
+    }
+
+    #[test]
+    fn dispatch_indirect_matches_vulkan_layout() {
+        assert_eq!(
+            size_of::<VEDispatchIndirect>(),
+            size_of::<vk::DispatchIndirectCommand>()
+        );
+        assert_eq!(
+            offset_of!(VEDispatchIndirect, x),
+            offset_of!(vk::DispatchIndirectCommand, x)
+        );
+        assert_eq!(
+            offset_of!(VEDispatchIndirect, y),
+            offset_of!(vk::DispatchIndirectCommand, y)
+        );
+        assert_eq!(
+            offset_of!(VEDispatchIndirect, z),
+            offset_of!(vk::DispatchIndirectCommand, z)
+        );
+    }
+
+    // same byte conversion write and read use, without a device
+    #[test]
+    fn indirect_commands_round_trip_through_bytes() {
+        let commands = [
+            VEDrawIndexedIndirect {
+                index_count: 36,
+                instance_count: 4,
+                first_index: 6,
+                vertex_offset: -3,
+                first_instance: 2,
+            },
+            VEDrawIndexedIndirect {
+                index_count: 3,
+                instance_count: 1,
+                first_index: 0,
+                vertex_offset: 0,
+                first_instance: 0,
+            },
+        ];
+        let bytes = elements_to_bytes(&commands);
+        assert_eq!(bytes.len(), 2 * size_of::<vk::DrawIndexedIndirectCommand>());
+        // vertex_offset is the fourth field, see VkDrawIndexedIndirectCommand
+        assert_eq!(&bytes[12..16], &(-3i32).to_ne_bytes());
+        assert_eq!(bytes_to_elements::<VEDrawIndexedIndirect>(bytes), commands);
+
+        let dispatch = [VEDispatchIndirect { x: 8, y: 4, z: 1 }];
+        assert_eq!(
+            bytes_to_elements::<VEDispatchIndirect>(elements_to_bytes(&dispatch)),
+            dispatch
+        );
+    }
+}
@@ -24,6 +24,7 @@ impl VEComputePipeline {
         device: Arc<VEDevice>,
         set_layouts: &[&VEDescriptorSetLayout],
         shader: &VEShaderModule,
+        push_constant_ranges: &[vk::PushConstantRange],
     ) -> Result<VEComputePipeline, VEComputePipelineError> {
         let shader_stage_info = vk::PipelineShaderStageCreateInfo::default()
             .stage(vk::ShaderStageFlags::COMPUTE)
@@ -31,7 +32,9 @@ impl VEComputePipeline {
             .name(c"main");
         let layouts: Vec<vk::DescriptorSetLayout> = set_layouts.iter().map(|x| x.layout).collect();
 
-        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts);
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default()
+            .set_layouts(&layouts)
+            .push_constant_ranges(push_constant_ranges);
         let pipeline_layout = unsafe {
             device
                 .device
@@ -3,7 +3,7 @@ use crate::core::command_buffer::{VECommandBuffer, VECommandBufferError};
 use crate::core::command_pool::VECommandPool;
 use crate::core::descriptor_set::VEDescriptorSet;
 use crate::core::descriptor_set_layout::VEDescriptorSetLayout;
-use crate::core::device::VEDevice;
+use crate::core::device::{VEDevice, VEDeviceError};
 use crate::core::shader_module::VEShaderModule;
 use ash::vk;
 use ash::vk::CommandBufferUsageFlags;
@@ -33,13 +33,29 @@ impl VEComputeStage {
         set_layouts: &[&VEDescriptorSetLayout],
         shader: &VEShaderModule,
     ) -> Result<VEComputeStage, VEComputeStageError> {
-        let pipeline = VEComputePipeline::new(device.clone(), set_layouts, &shader)?;
+        Self::new_with_push_constants(device, command_pool, set_layouts, shader, &[])
+    }
+
+    // ranges are added to the pipeline layout, fill them with set_push_constants
+    pub fn new_with_push_constants(
+        device: Arc<VEDevice>,
+        command_pool: Arc<VECommandPool>,
+        set_layouts: &[&VEDescriptorSetLayout],
+        shader: &VEShaderModule,
+        push_constant_ranges: &[vk::PushConstantRange],
+    ) -> Result<VEComputeStage, VEComputeStageError> {
+        let pipeline =
+            VEComputePipeline::new(device.clone(), set_layouts, &shader, push_constant_ranges)?;
         Ok(VEComputeStage {
             device: device.clone(),
             pipeline: Arc::new(pipeline),
         })
     }
 
+    pub fn get_pipeline_layout(&self) -> vk::PipelineLayout {
+        self.pipeline.layout
+    }
+
     pub fn set_descriptor_set(
         &self,
         command_buffer: &VECommandBuffer,
@@ -68,6 +84,18 @@ impl VEComputeStage {
         }
     }
 
+    pub fn set_push_constants(&self, command_buffer: &VECommandBuffer, offset: u32, data: &[u8]) {
+        unsafe {
+            self.device.device.cmd_push_constants(
+                command_buffer.handle,
+                self.pipeline.layout,
+                vk::ShaderStageFlags::COMPUTE,
+                offset,
+                data,
+            );
+        }
+    }
+
     pub fn dispatch(
         &self,
         command_buffer: &VECommandBuffer,
@@ -84,4 +112,9 @@ impl VEComputeStage {
             );
         }
     }
+
+    pub fn set_name(&self, name: &str) -> Result<(), VEDeviceError> {
+        self.device.set_debug_name(self.pipeline.pipeline, name)?;
+        self.device.set_debug_name(self.pipeline.layout, name)
+    }
 }
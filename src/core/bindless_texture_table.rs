@@ -0,0 +1,149 @@
+use crate::core::descriptor_set::{VEDescriptorSet, VEDescriptorSetError};
+use crate::core::descriptor_set_layout::{
+    get_field_stage_flags, VEDescriptorSetFieldStage, VEDescriptorSetLayout,
+};
+use crate::core::device::VEDevice;
+use crate::image::sampler::VESampler;
+use ash::vk;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEBindlessTextureTableError {
+    #[error("bindless descriptors not supported by the device")]
+    BindlessNotSupported,
+
+    #[error("table size must be at least 1")]
+    EmptyTable,
+
+    #[error("layout creation failed")]
+    LayoutCreationFailed(#[source] vk::Result),
+
+    #[error("pool creation failed")]
+    PoolCreationFailed(#[source] vk::Result),
+
+    #[error("set allocation failed")]
+    SetAllocationFailed(#[source] VEDescriptorSetError),
+
+    #[error("slot index out of bounds")]
+    SlotOutOfBounds,
+}
+
+// a single array of combined image samplers living in its own update-after-bind
+// pool, slots can be rewritten while the set is bound as long as the slot itself
+// is not used by commands in flight
+pub struct VEBindlessTextureTable {
+    device: Arc<VEDevice>,
+    pool: vk::DescriptorPool,
+    pub layout: VEDescriptorSetLayout,
+    pub set: VEDescriptorSet,
+    pub binding: u32,
+    pub size: u32,
+}
+
+impl VEBindlessTextureTable {
+    pub fn new(
+        device: Arc<VEDevice>,
+        binding: u32,
+        size: u32,
+        stage: VEDescriptorSetFieldStage,
+    ) -> Result<VEBindlessTextureTable, VEBindlessTextureTableError> {
+        if !device.supports_bindless() {
+            return Err(VEBindlessTextureTableError::BindlessNotSupported);
+        }
+        if size == 0 {
+            return Err(VEBindlessTextureTableError::EmptyTable);
+        }
+
+        let bindings = [vk::DescriptorSetLayoutBinding::default()
+            .binding(binding)
+            .descriptor_count(size)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .stage_flags(get_field_stage_flags(&stage))];
+        let binding_flags = [vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING
+            | vk::DescriptorBindingFlags::PARTIALLY_BOUND];
+        let mut binding_flags_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::default().binding_flags(&binding_flags);
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::default()
+            .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+            .bindings(&bindings)
+            .push_next(&mut binding_flags_info);
+        let layout = unsafe {
+            device
+                .device
+                .create_descriptor_set_layout(&layout_info, None)
+                .map_err(VEBindlessTextureTableError::LayoutCreationFailed)?
+        };
+        let layout = VEDescriptorSetLayout::from_handle(device.clone(), layout);
+
+        let pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(size)];
+        let pool_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+            .pool_sizes(&pool_sizes)
+            .max_sets(1);
+        let pool = unsafe {
+            device
+                .device
+                .create_descriptor_pool(&pool_info, None)
+                .map_err(VEBindlessTextureTableError::PoolCreationFailed)?
+        };
+
+        let set = match VEDescriptorSet::new(device.clone(), layout.layout, &pool) {
+            Ok(set) => set,
+            Err(e) => {
+                unsafe { device.device.destroy_descriptor_pool(pool, None) };
+                return Err(VEBindlessTextureTableError::SetAllocationFailed(e));
+            }
+        };
+
+        Ok(VEBindlessTextureTable {
+            device,
+            pool,
+            layout,
+            set,
+            binding,
+            size,
+        })
+    }
+
+    // writes only the one array element, the rest of the table is left untouched. layout is
+    // the one the image will be in when shaders sample it, usually SHADER_READ_ONLY_OPTIMAL,
+    // not necessarily the one it is in now
+    pub fn update_slot(
+        &self,
+        index: u32,
+        view: vk::ImageView,
+        sampler: &VESampler,
+        layout: vk::ImageLayout,
+    ) -> Result<(), VEBindlessTextureTableError> {
+        if index >= self.size {
+            return Err(VEBindlessTextureTableError::SlotOutOfBounds);
+        }
+        let infos = [vk::DescriptorImageInfo::default()
+            .image_view(view)
+            .sampler(sampler.handle)
+            .image_layout(layout)];
+        let writes = [vk::WriteDescriptorSet::default()
+            .dst_set(self.set.set)
+            .dst_binding(self.binding)
+            .dst_array_element(index)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&infos)];
+        unsafe {
+            self.device.device.update_descriptor_sets(&writes, &[]);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VEBindlessTextureTable {
+    fn drop(&mut self) {
+        // the layout field destroys its own handle afterwards
+        unsafe {
+            self.device.device.destroy_descriptor_pool(self.pool, None);
+        }
+    }
+}
@@ -1,7 +1,11 @@
 use crate::core::command_pool::VECommandPool;
-use crate::core::device::VEDevice;
+use crate::core::device::{VEDevice, VEDeviceError};
+use crate::core::fence::VEFence;
 use crate::core::main_device_queue::VEMainDeviceQueue;
 use crate::core::semaphore::{SemaphoreState, VESemaphore};
+use crate::graphics::render_stage::{
+    get_cull_flags, get_primitive_topology, VECullMode, VEPrimitiveTopology,
+};
 use ash::vk;
 use ash::vk::{
     CommandBuffer, CommandBufferAllocateInfo, CommandBufferLevel, CommandBufferUsageFlags,
@@ -29,11 +33,186 @@ pub enum VECommandBufferError {
 
     #[error("waiting for awaited semaphore")]
     WaitingForAwaitedSemaphore,
+
+    #[error("extended dynamic state not supported")]
+    ExtendedDynamicStateNotSupported,
+
+    #[error("dynamic rendering local read not supported")]
+    DynamicRenderingLocalReadNotSupported,
+
+    #[error("render pass continue is only valid for secondary command buffers")]
+    RenderPassContinueRequiresSecondary,
+
+    #[error("secondary command buffers must be begun with inheritance info")]
+    SecondaryRequiresInheritance,
+
+    #[error("only primary command buffers can execute secondary command buffers")]
+    ExecuteRequiresPrimary,
+
+    #[error("only secondary command buffers can be executed")]
+    ExecutedBufferNotSecondary,
+
+    #[error("depth bounds test not supported")]
+    DepthBoundsNotSupported,
+
+    #[error("submission has neither command buffers nor semaphores")]
+    EmptySubmit,
+}
+
+/// One batch on the queue. Command buffers may be empty for a sync point that only waits on
+/// and signals semaphores, for example handing a queue's results over to another queue, or
+/// a frame that records nothing but still has to pass the swapchain's blit semaphore on:
+///
+/// ```no_run
+/// # use std::sync::{Arc, Mutex};
+/// # use vengine_rs::core::command_buffer::{submit_batch, VECommandBufferError};
+/// # use vengine_rs::core::device::VEDevice;
+/// # use vengine_rs::core::main_device_queue::VEMainDeviceQueue;
+/// # use vengine_rs::core::semaphore::VESemaphore;
+/// fn skip_frame(
+///     device: &VEDevice,
+///     queue: &VEMainDeviceQueue,
+///     blit_done: Arc<Mutex<VESemaphore>>,
+///     frame_done: Arc<Mutex<VESemaphore>>,
+/// ) -> Result<(), VECommandBufferError> {
+///     // nothing to draw, the next blit waits on frame_done as if a frame was rendered
+///     submit_batch(device, queue, &[], vec![blit_done], vec![frame_done], None)
+/// }
+/// ```
+pub fn submit_batch(
+    device: &VEDevice,
+    queue: &VEMainDeviceQueue,
+    command_buffers: &[&VECommandBuffer],
+    wait_for_semaphores: Vec<Arc<Mutex<VESemaphore>>>,
+    signal_semaphores: Vec<Arc<Mutex<VESemaphore>>>,
+    fence: Option<&VEFence>,
+) -> Result<(), VECommandBufferError> {
+    // fresh semaphores were never signaled and are skipped, states only change once the
+    // submit went through
+    let mut awaited: Vec<&Arc<Mutex<VESemaphore>>> = vec![];
+    let mut wait_handles: Vec<vk::Semaphore> = vec![];
+    let mut wait_masks: Vec<PipelineStageFlags> = vec![];
+    for x in &wait_for_semaphores {
+        let semaphore = x
+            .lock()
+            .map_err(|_| VECommandBufferError::SemaphoreLockingFailed)?;
+        match semaphore.state {
+            SemaphoreState::Fresh => continue,
+            SemaphoreState::Pending => (),
+            SemaphoreState::Awaited => {
+                return Err(VECommandBufferError::WaitingForAwaitedSemaphore)
+            }
+        }
+        wait_handles.push(semaphore.handle);
+        wait_masks.push(
+            PipelineStageFlags::ALL_COMMANDS
+                | PipelineStageFlags::ALL_GRAPHICS
+                | PipelineStageFlags::COMPUTE_SHADER,
+        );
+        awaited.push(x);
+    }
+
+    let mut signal_handles: Vec<vk::Semaphore> = vec![];
+    for x in &signal_semaphores {
+        signal_handles.push(
+            x.lock()
+                .map_err(|_| VECommandBufferError::SemaphoreLockingFailed)?
+                .handle,
+        );
+    }
+
+    if command_buffers.is_empty() && wait_handles.is_empty() && signal_handles.is_empty() {
+        return Err(VECommandBufferError::EmptySubmit);
+    }
+
+    let command_buffer_handles: Vec<vk::CommandBuffer> =
+        command_buffers.iter().map(|c| c.handle).collect();
+
+    let submit_info = vk::SubmitInfo::default()
+        .signal_semaphores(&signal_handles)
+        .wait_semaphores(&wait_handles)
+        .wait_dst_stage_mask(&wait_masks)
+        .command_buffers(&command_buffer_handles);
+
+    unsafe {
+        device
+            .device
+            .queue_submit(
+                queue.main_queue,
+                &[submit_info],
+                fence.map_or(vk::Fence::null(), |f| f.handle),
+            )
+            .map_err(VECommandBufferError::SubmitFailed)?;
+    }
+
+    for x in awaited {
+        x.lock()
+            .map_err(|_| VECommandBufferError::SemaphoreLockingFailed)?
+            .state = SemaphoreState::Awaited;
+    }
+    for x in &signal_semaphores {
+        x.lock()
+            .map_err(|_| VECommandBufferError::SemaphoreLockingFailed)?
+            .state = SemaphoreState::Pending;
+    }
+    Ok(())
+}
+
+// OneTimeSubmit: recorded, submitted once and re-recorded, lets the driver optimize
+// SimultaneousUse: resubmitted while a previous submission may still be pending
+// RenderPassContinue: secondary buffers executed entirely inside a render pass, added by
+// begin_secondary
+// no usages at all means the buffer can be resubmitted once the previous submission finished
+#[derive(Debug, Clone, PartialEq)]
+pub enum VECommandBufferUsage {
+    OneTimeSubmit,
+    SimultaneousUse,
+    RenderPassContinue,
+}
+
+fn get_command_buffer_usage_flags(usages: &[VECommandBufferUsage]) -> CommandBufferUsageFlags {
+    let mut flags = CommandBufferUsageFlags::empty();
+    for usage in usages {
+        flags |= match usage {
+            VECommandBufferUsage::OneTimeSubmit => CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            VECommandBufferUsage::SimultaneousUse => CommandBufferUsageFlags::SIMULTANEOUS_USE,
+            VECommandBufferUsage::RenderPassContinue => {
+                CommandBufferUsageFlags::RENDER_PASS_CONTINUE
+            }
+        };
+    }
+    flags
+}
+
+// what a secondary buffer recorded with RenderPassContinue runs inside of, the primary
+// must begin the same render pass with SECONDARY_COMMAND_BUFFERS contents
+#[derive(Debug, Clone, Copy)]
+pub struct VECommandBufferInheritance {
+    pub render_pass: vk::RenderPass,
+    pub subpass: u32,
+    pub framebuffer: vk::Framebuffer,
+}
+
+fn validate_begin(
+    level: CommandBufferLevel,
+    flags: CommandBufferUsageFlags,
+    has_inheritance: bool,
+) -> Result<(), VECommandBufferError> {
+    if flags.contains(CommandBufferUsageFlags::RENDER_PASS_CONTINUE)
+        && level != CommandBufferLevel::SECONDARY
+    {
+        return Err(VECommandBufferError::RenderPassContinueRequiresSecondary);
+    }
+    if level == CommandBufferLevel::SECONDARY && !has_inheritance {
+        return Err(VECommandBufferError::SecondaryRequiresInheritance);
+    }
+    Ok(())
 }
 
 pub struct VECommandBuffer {
     device: Arc<VEDevice>,
     command_pool: Arc<VECommandPool>,
+    level: CommandBufferLevel,
     pub handle: CommandBuffer,
 }
 
@@ -41,13 +220,29 @@ impl VECommandBuffer {
     pub fn new(
         device: Arc<VEDevice>,
         command_pool: Arc<VECommandPool>,
+    ) -> Result<VECommandBuffer, VECommandBufferError> {
+        Self::new_with_level(device, command_pool, CommandBufferLevel::PRIMARY)
+    }
+
+    // recorded with begin_secondary and run from a primary with execute_commands
+    pub fn new_secondary(
+        device: Arc<VEDevice>,
+        command_pool: Arc<VECommandPool>,
+    ) -> Result<VECommandBuffer, VECommandBufferError> {
+        Self::new_with_level(device, command_pool, CommandBufferLevel::SECONDARY)
+    }
+
+    fn new_with_level(
+        device: Arc<VEDevice>,
+        command_pool: Arc<VECommandPool>,
+        level: CommandBufferLevel,
     ) -> Result<VECommandBuffer, VECommandBufferError> {
         let handle = unsafe {
             device
                 .device
                 .allocate_command_buffers(
                     &CommandBufferAllocateInfo::default()
-                        .level(CommandBufferLevel::PRIMARY)
+                        .level(level)
                         .command_buffer_count(1)
                         .command_pool(command_pool.handle),
                 )
@@ -56,37 +251,84 @@ impl VECommandBuffer {
         Ok(VECommandBuffer {
             device: device.clone(),
             command_pool: command_pool.clone(),
+            level,
             handle,
         })
     }
 
+    pub fn begin_with(&self, usages: &[VECommandBufferUsage]) -> Result<(), VECommandBufferError> {
+        self.begin_with_flags(get_command_buffer_usage_flags(usages))
+    }
+
     pub fn begin_with_flags(
         &self,
         flags: CommandBufferUsageFlags,
     ) -> Result<(), VECommandBufferError> {
-        let begin_info = vk::CommandBufferBeginInfo::default().flags(flags);
+        validate_begin(self.level, flags, false)?;
+        self.begin_raw(&vk::CommandBufferBeginInfo::default().flags(flags))
+    }
+
+    // RenderPassContinue gets added, the buffer may then only record commands valid
+    // inside the inherited subpass
+    pub fn begin_secondary(
+        &self,
+        usages: &[VECommandBufferUsage],
+        inheritance: VECommandBufferInheritance,
+    ) -> Result<(), VECommandBufferError> {
+        let flags =
+            get_command_buffer_usage_flags(usages) | CommandBufferUsageFlags::RENDER_PASS_CONTINUE;
+        validate_begin(self.level, flags, true)?;
+        let inheritance_info = vk::CommandBufferInheritanceInfo::default()
+            .render_pass(inheritance.render_pass)
+            .subpass(inheritance.subpass)
+            .framebuffer(inheritance.framebuffer);
+        self.begin_raw(
+            &vk::CommandBufferBeginInfo::default()
+                .flags(flags)
+                .inheritance_info(&inheritance_info),
+        )
+    }
 
+    fn begin_raw(
+        &self,
+        begin_info: &vk::CommandBufferBeginInfo,
+    ) -> Result<(), VECommandBufferError> {
         unsafe {
             self.device
                 .device
-                .begin_command_buffer(self.handle, &begin_info)
+                .begin_command_buffer(self.handle, begin_info)
                 .map_err(VECommandBufferError::BeginFailed)?;
         }
 
         Ok(())
     }
 
+    // one time submit, use begin_with for buffers that are submitted more than once
     pub fn begin(&self) -> Result<(), VECommandBufferError> {
-        let begin_info =
-            vk::CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::empty());
+        self.begin_with(&[VECommandBufferUsage::OneTimeSubmit])
+    }
 
+    // the secondaries must have ended and must have been begun for the render pass
+    // instance this primary is currently in
+    pub fn execute_commands(
+        &self,
+        secondaries: &[&VECommandBuffer],
+    ) -> Result<(), VECommandBufferError> {
+        if self.level != CommandBufferLevel::PRIMARY {
+            return Err(VECommandBufferError::ExecuteRequiresPrimary);
+        }
+        if secondaries
+            .iter()
+            .any(|x| x.level != CommandBufferLevel::SECONDARY)
+        {
+            return Err(VECommandBufferError::ExecutedBufferNotSecondary);
+        }
+        let handles: Vec<vk::CommandBuffer> = secondaries.iter().map(|x| x.handle).collect();
         unsafe {
             self.device
                 .device
-                .begin_command_buffer(self.handle, &begin_info)
-                .map_err(VECommandBufferError::BeginFailed)?;
-        }
-
+                .cmd_execute_commands(self.handle, &handles)
+        };
         Ok(())
     }
 
@@ -106,56 +348,191 @@ impl VECommandBuffer {
         wait_for_semaphores: Vec<Arc<Mutex<VESemaphore>>>,
         signal_semaphores: Vec<Arc<Mutex<VESemaphore>>>,
     ) -> Result<(), VECommandBufferError> {
-        let mut wait_handles: Vec<vk::Semaphore> = vec![];
-        let mut wait_masks: Vec<PipelineStageFlags> = vec![];
-        for x in wait_for_semaphores {
-            let mut x = x
-                .lock()
-                .map_err(|_| VECommandBufferError::SemaphoreLockingFailed)?;
-            let should = match x.state {
-                SemaphoreState::Fresh => Ok(false),
-                SemaphoreState::Pending => Ok(true),
-                SemaphoreState::Awaited => Err(VECommandBufferError::WaitingForAwaitedSemaphore),
-            }?;
-            if should {
-                wait_handles.push(x.handle);
-                wait_masks.push(
-                    PipelineStageFlags::ALL_COMMANDS
-                        | PipelineStageFlags::ALL_GRAPHICS
-                        | PipelineStageFlags::COMPUTE_SHADER,
-                );
-                if x.state == SemaphoreState::Pending {
-                    x.state = SemaphoreState::Awaited;
-                }
-            }
-        }
+        submit_batch(
+            &self.device,
+            queue,
+            &[self],
+            wait_for_semaphores,
+            signal_semaphores,
+            None,
+        )
+    }
 
-        let mut signal_handles: Vec<vk::Semaphore> = vec![];
+    // fence gets signaled once the work completes, used to track resource last use
+    pub fn submit_with_fence(
+        &self,
+        queue: &VEMainDeviceQueue,
+        wait_for_semaphores: Vec<Arc<Mutex<VESemaphore>>>,
+        signal_semaphores: Vec<Arc<Mutex<VESemaphore>>>,
+        fence: &VEFence,
+    ) -> Result<(), VECommandBufferError> {
+        submit_batch(
+            &self.device,
+            queue,
+            &[self],
+            wait_for_semaphores,
+            signal_semaphores,
+            Some(fence),
+        )
+    }
 
-        for x in signal_semaphores {
-            let mut x = x
-                .lock()
-                .map_err(|_| VECommandBufferError::SemaphoreLockingFailed)?;
-            signal_handles.push(x.handle);
-            x.state = SemaphoreState::Pending;
-        }
+    fn get_extended_dynamic_state(
+        &self,
+    ) -> Result<&ash::ext::extended_dynamic_state::Device, VECommandBufferError> {
+        self.device
+            .extended_dynamic_state
+            .as_ref()
+            .ok_or(VECommandBufferError::ExtendedDynamicStateNotSupported)
+    }
+
+    pub fn set_cull_mode(&self, cull_mode: VECullMode) -> Result<(), VECommandBufferError> {
+        let loader = self.get_extended_dynamic_state()?;
+        unsafe { loader.cmd_set_cull_mode(self.handle, get_cull_flags(cull_mode)) };
+        Ok(())
+    }
 
-        let command_buffer_handles = [self.handle];
+    pub fn set_front_face(&self, front_face: vk::FrontFace) -> Result<(), VECommandBufferError> {
+        let loader = self.get_extended_dynamic_state()?;
+        unsafe { loader.cmd_set_front_face(self.handle, front_face) };
+        Ok(())
+    }
 
-        let submit_info = vk::SubmitInfo::default()
-            .signal_semaphores(&signal_handles)
-            .wait_semaphores(&wait_handles)
-            .wait_dst_stage_mask(&wait_masks)
-            .command_buffers(&command_buffer_handles);
+    pub fn set_primitive_topology(
+        &self,
+        primitive_topology: VEPrimitiveTopology,
+    ) -> Result<(), VECommandBufferError> {
+        let loader = self.get_extended_dynamic_state()?;
+        unsafe {
+            loader
+                .cmd_set_primitive_topology(self.handle, get_primitive_topology(primitive_topology))
+        };
+        Ok(())
+    }
 
+    // only valid for pipelines created with depth_bounds_test_enable, depths in 0..=1
+    pub fn set_depth_bounds(&self, min: f32, max: f32) -> Result<(), VECommandBufferError> {
+        if !self.device.supports_depth_bounds() {
+            return Err(VECommandBufferError::DepthBoundsNotSupported);
+        }
         unsafe {
             self.device
                 .device
-                .queue_submit(queue.main_queue, &[submit_info], vk::Fence::null())
-                .map_err(VECommandBufferError::SubmitFailed)?;
+                .cmd_set_depth_bounds(self.handle, min, max)
+        };
+        Ok(())
+    }
+
+    pub fn set_depth_test_enable(&self, enable: bool) -> Result<(), VECommandBufferError> {
+        let loader = self.get_extended_dynamic_state()?;
+        unsafe { loader.cmd_set_depth_test_enable(self.handle, enable) };
+        Ok(())
+    }
+
+    pub fn set_depth_write_enable(&self, enable: bool) -> Result<(), VECommandBufferError> {
+        let loader = self.get_extended_dynamic_state()?;
+        unsafe { loader.cmd_set_depth_write_enable(self.handle, enable) };
+        Ok(())
+    }
+
+    pub fn set_depth_compare_op(
+        &self,
+        compare_op: vk::CompareOp,
+    ) -> Result<(), VECommandBufferError> {
+        let loader = self.get_extended_dynamic_state()?;
+        unsafe { loader.cmd_set_depth_compare_op(self.handle, compare_op) };
+        Ok(())
+    }
+
+    fn get_dynamic_rendering_local_read(
+        &self,
+    ) -> Result<&ash::khr::dynamic_rendering_local_read::Device, VECommandBufferError> {
+        self.device
+            .dynamic_rendering_local_read
+            .as_ref()
+            .ok_or(VECommandBufferError::DynamicRenderingLocalReadNotSupported)
+    }
+
+    // remaps color attachments of the current dynamic rendering instance to shader output locations
+    pub fn set_rendering_attachment_locations(
+        &self,
+        locations: &[u32],
+    ) -> Result<(), VECommandBufferError> {
+        let loader = self.get_dynamic_rendering_local_read()?;
+        let info =
+            vk::RenderingAttachmentLocationInfoKHR::default().color_attachment_locations(locations);
+        unsafe { loader.cmd_set_rendering_attachment_locations(self.handle, &info) };
+        Ok(())
+    }
+
+    // input_attachment_index values the attachments are read through with subpassLoad
+    pub fn set_rendering_input_attachment_indices(
+        &self,
+        color_indices: &[u32],
+        depth_index: Option<u32>,
+        stencil_index: Option<u32>,
+    ) -> Result<(), VECommandBufferError> {
+        let loader = self.get_dynamic_rendering_local_read()?;
+        let mut info = vk::RenderingInputAttachmentIndexInfoKHR::default()
+            .color_attachment_input_indices(color_indices);
+        if let Some(index) = &depth_index {
+            info = info.depth_input_attachment_index(index);
         }
+        if let Some(index) = &stencil_index {
+            info = info.stencil_input_attachment_index(index);
+        }
+        unsafe { loader.cmd_set_rendering_input_attachment_indices(self.handle, &info) };
         Ok(())
     }
+
+    // makes attachment writes of earlier draws visible to input attachment reads of
+    // later draws in the same dynamic rendering instance, attachments must be in
+    // RENDERING_LOCAL_READ_KHR or GENERAL
+    pub fn local_read_barrier(&self) -> Result<(), VECommandBufferError> {
+        self.get_dynamic_rendering_local_read()?;
+        let barrier = vk::MemoryBarrier::default()
+            .src_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            )
+            .dst_access_mask(vk::AccessFlags::INPUT_ATTACHMENT_READ);
+        unsafe {
+            self.device.device.cmd_pipeline_barrier(
+                self.handle,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::BY_REGION,
+                &[barrier],
+                &[],
+                &[],
+            );
+        }
+        Ok(())
+    }
+
+    // inside a render pass created with input attachments, makes color writes of earlier
+    // draws visible to input attachment reads of later draws in the same subpass
+    pub fn subpass_self_barrier(&self) {
+        let barrier = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::INPUT_ATTACHMENT_READ);
+        unsafe {
+            self.device.device.cmd_pipeline_barrier(
+                self.handle,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::BY_REGION,
+                &[barrier],
+                &[],
+                &[],
+            );
+        }
+    }
+
+    pub fn set_name(&self, name: &str) -> Result<(), VEDeviceError> {
+        self.device.set_debug_name(self.handle, name)
+    }
 }
 
 impl Drop for VECommandBuffer {
@@ -167,3 +544,48 @@ impl Drop for VECommandBuffer {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_pass_continue_needs_secondary() {
+        assert!(matches!(
+            validate_begin(
+                CommandBufferLevel::PRIMARY,
+                CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+                true
+            ),
+            Err(VECommandBufferError::RenderPassContinueRequiresSecondary)
+        ));
+    }
+
+    #[test]
+    fn secondary_needs_inheritance() {
+        assert!(matches!(
+            validate_begin(
+                CommandBufferLevel::SECONDARY,
+                CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+                false
+            ),
+            Err(VECommandBufferError::SecondaryRequiresInheritance)
+        ));
+        assert!(validate_begin(
+            CommandBufferLevel::SECONDARY,
+            CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+            true
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn primary_begins_without_inheritance() {
+        assert!(validate_begin(
+            CommandBufferLevel::PRIMARY,
+            CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            false
+        )
+        .is_ok());
+    }
+}
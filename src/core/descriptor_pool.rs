@@ -0,0 +1,111 @@
+use crate::core::descriptor_set::{VEDescriptorSet, VEDescriptorSetError};
+use crate::core::descriptor_set_layout::VEDescriptorSetLayout;
+use crate::core::device::VEDevice;
+use ash::vk;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEDescriptorPoolError {
+    #[error("pool creation failed")]
+    PoolCreationFailed(#[source] vk::Result),
+
+    #[error(
+        "layout bindings are unknown, the layout was not created by VEDescriptorSetLayout::new"
+    )]
+    UnknownLayoutBindings,
+
+    #[error("no layouts or sets to size the pool for")]
+    EmptyPool,
+
+    #[error("descriptor set creation failed")]
+    DescriptorSetCreationFailed(#[source] VEDescriptorSetError),
+
+    #[error("no descriptor sets can be allocated from a push descriptor layout")]
+    PushDescriptorLayout,
+}
+
+// sums the descriptors per type over every set that will be allocated, each binding holds
+// one descriptor. (layout, number of sets) pairs, types that do not occur are left out
+fn get_pool_sizes(
+    layouts: &[(&VEDescriptorSetLayout, u32)],
+) -> Result<(Vec<vk::DescriptorPoolSize>, u32), VEDescriptorPoolError> {
+    let mut pool_sizes: Vec<vk::DescriptorPoolSize> = vec![];
+    let mut max_sets = 0;
+    for (layout, count) in layouts {
+        if layout.is_push() {
+            return Err(VEDescriptorPoolError::PushDescriptorLayout);
+        }
+        let binding_types = layout
+            .get_binding_types()
+            .ok_or(VEDescriptorPoolError::UnknownLayoutBindings)?;
+        for (_, typ) in binding_types {
+            match pool_sizes.iter_mut().find(|size| size.ty == *typ) {
+                Some(size) => size.descriptor_count += count,
+                None => pool_sizes.push(
+                    vk::DescriptorPoolSize::default()
+                        .ty(*typ)
+                        .descriptor_count(*count),
+                ),
+            }
+        }
+        max_sets += count;
+    }
+    if max_sets == 0 {
+        return Err(VEDescriptorPoolError::EmptyPool);
+    }
+    Ok((pool_sizes, max_sets))
+}
+
+// exactly sized for a known set of layouts, unlike the pools a layout grows on its own
+pub struct VEDescriptorPool {
+    device: Arc<VEDevice>,
+    pub pool: vk::DescriptorPool,
+    pub max_sets: u32,
+    pub pool_sizes: Vec<vk::DescriptorPoolSize>,
+}
+
+impl VEDescriptorPool {
+    pub fn from_layouts(
+        device: Arc<VEDevice>,
+        layouts: &[(&VEDescriptorSetLayout, u32)],
+    ) -> Result<VEDescriptorPool, VEDescriptorPoolError> {
+        let (pool_sizes, max_sets) = get_pool_sizes(layouts)?;
+        let info = vk::DescriptorPoolCreateInfo::default()
+            .pool_sizes(&pool_sizes)
+            .max_sets(max_sets);
+        let pool = unsafe {
+            device
+                .device
+                .create_descriptor_pool(&info, None)
+                .map_err(VEDescriptorPoolError::PoolCreationFailed)?
+        };
+        Ok(VEDescriptorPool {
+            device,
+            pool,
+            max_sets,
+            pool_sizes,
+        })
+    }
+
+    pub fn create_descriptor_set(
+        &self,
+        layout: &VEDescriptorSetLayout,
+    ) -> Result<VEDescriptorSet, VEDescriptorPoolError> {
+        if layout.is_push() {
+            return Err(VEDescriptorPoolError::PushDescriptorLayout);
+        }
+        let set = VEDescriptorSet::new(self.device.clone(), layout.layout, &self.pool)
+            .map_err(VEDescriptorPoolError::DescriptorSetCreationFailed)?;
+        Ok(match layout.get_binding_types() {
+            Some(binding_types) => set.with_binding_types(binding_types.to_vec()),
+            None => set,
+        })
+    }
+}
+
+impl Drop for VEDescriptorPool {
+    fn drop(&mut self) {
+        unsafe { self.device.device.destroy_descriptor_pool(self.pool, None) }
+    }
+}
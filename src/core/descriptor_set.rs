@@ -1,5 +1,5 @@
 use crate::buffer::buffer::{VEBuffer, VEBufferUsage};
-use crate::core::device::VEDevice;
+use crate::core::device::{VEDevice, VEDeviceError};
 use crate::image::image::VEImage;
 use crate::image::sampler::VESampler;
 use ash::vk;
@@ -16,11 +16,31 @@ pub enum VEDescriptorSetError {
 
     #[error("invalid buffer type for descriptor set")]
     InvalidBufferType,
+
+    #[error("image was not created with storage usage, add VEImageUsage::Storage")]
+    ImageMissingStorageUsage,
+
+    #[error(
+        "image format {0:?} does not support storage images on this device, pick another format"
+    )]
+    FormatNotStorageCapable(vk::Format),
+
+    #[error("image was not created with sampled usage, add VEImageUsage::Sampled")]
+    ImageMissingSampledUsage,
+
+    #[error("binding {binding} is declared as {expected:?} in the layout, not {found:?}")]
+    DescriptorTypeMismatch {
+        binding: u32,
+        expected: Option<vk::DescriptorType>,
+        found: vk::DescriptorType,
+    },
 }
 
 pub struct VEDescriptorSet {
     device: Arc<VEDevice>,
     pub set: vk::DescriptorSet,
+    // binding to descriptor type from the layout, when known writes are checked against it
+    binding_types: Option<Vec<(u32, vk::DescriptorType)>>,
 }
 
 impl VEDescriptorSet {
@@ -34,7 +54,82 @@ impl VEDescriptorSet {
             .descriptor_pool(*pool)
             .set_layouts(&layouts);
         let set = unsafe { device.device.allocate_descriptor_sets(&info)?[0] };
-        Ok(VEDescriptorSet { device, set })
+        Ok(VEDescriptorSet {
+            device,
+            set,
+            binding_types: None,
+        })
+    }
+
+    pub(crate) fn with_binding_types(
+        mut self,
+        binding_types: Vec<(u32, vk::DescriptorType)>,
+    ) -> VEDescriptorSet {
+        self.binding_types = Some(binding_types);
+        self
+    }
+
+    fn check_binding_type(
+        &self,
+        binding: u32,
+        found: vk::DescriptorType,
+    ) -> Result<(), VEDescriptorSetError> {
+        let Some(binding_types) = &self.binding_types else {
+            return Ok(());
+        };
+        let expected = binding_types
+            .iter()
+            .find(|(b, _)| *b == binding)
+            .map(|(_, typ)| *typ);
+        if expected != Some(found) {
+            return Err(VEDescriptorSetError::DescriptorTypeMismatch {
+                binding,
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    // separate sampler for a SeparateSampler binding, pairs with write_sampled_image
+    pub fn write_sampler(
+        &self,
+        binding: u32,
+        sampler: &VESampler,
+    ) -> Result<(), VEDescriptorSetError> {
+        self.check_binding_type(binding, vk::DescriptorType::SAMPLER)?;
+        let infos = [vk::DescriptorImageInfo::default().sampler(sampler.handle)];
+        self.write(
+            vk::WriteDescriptorSet::default()
+                .dst_binding(binding)
+                .descriptor_type(vk::DescriptorType::SAMPLER)
+                .image_info(&infos),
+        );
+        Ok(())
+    }
+
+    // image without a sampler for a SampledImage binding, layout is the one it has when sampled
+    pub fn write_sampled_image(
+        &self,
+        binding: u32,
+        image: &VEImage,
+        view: vk::ImageView,
+        layout: vk::ImageLayout,
+    ) -> Result<(), VEDescriptorSetError> {
+        self.check_binding_type(binding, vk::DescriptorType::SAMPLED_IMAGE)?;
+        if !image.usage.contains(vk::ImageUsageFlags::SAMPLED) {
+            return Err(VEDescriptorSetError::ImageMissingSampledUsage);
+        }
+        let infos = [vk::DescriptorImageInfo::default()
+            .image_view(view)
+            .image_layout(layout)];
+        self.write(
+            vk::WriteDescriptorSet::default()
+                .dst_binding(binding)
+                .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                .image_info(&infos),
+        );
+        Ok(())
     }
 
     pub fn bind_image_sampler(
@@ -73,6 +168,49 @@ impl VEDescriptorSet {
         ))
     }
 
+    // for attachments read in the subpass that writes them, see VERenderStageOptions::input_attachments
+    pub fn bind_input_attachment(
+        &self,
+        binding: u32,
+        view: vk::ImageView,
+    ) -> Result<(), VEDescriptorSetError> {
+        let infos = [vk::DescriptorImageInfo::default()
+            .image_view(view)
+            .image_layout(vk::ImageLayout::GENERAL)];
+        self.write(
+            vk::WriteDescriptorSet::default()
+                .dst_binding(binding)
+                .descriptor_type(vk::DescriptorType::INPUT_ATTACHMENT)
+                .image_info(&infos),
+        );
+        Ok(())
+    }
+
+    // validated variant of bind_image_storage, the image has to be in GENERAL when used
+    pub fn write_storage_image(
+        &self,
+        binding: u32,
+        image: &VEImage,
+        view: vk::ImageView,
+    ) -> Result<(), VEDescriptorSetError> {
+        if !image.usage.contains(vk::ImageUsageFlags::STORAGE) {
+            return Err(VEDescriptorSetError::ImageMissingStorageUsage);
+        }
+        if !self.device.supports_storage_image(image.format) {
+            return Err(VEDescriptorSetError::FormatNotStorageCapable(image.format));
+        }
+        let infos = [vk::DescriptorImageInfo::default()
+            .image_view(view)
+            .image_layout(vk::ImageLayout::GENERAL)];
+        self.write(
+            vk::WriteDescriptorSet::default()
+                .dst_binding(binding)
+                .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                .image_info(&infos),
+        );
+        Ok(())
+    }
+
     pub fn bind_buffer(&self, binding: u32, buffer: &VEBuffer) -> Result<(), VEDescriptorSetError> {
         let infos = [vk::DescriptorBufferInfo::default()
             .buffer(buffer.buffer)
@@ -102,4 +240,8 @@ impl VEDescriptorSet {
             self.device.device.update_descriptor_sets(&writes, &copies);
         }
     }
+
+    pub fn set_name(&self, name: &str) -> Result<(), VEDeviceError> {
+        self.device.set_debug_name(self.set, name)
+    }
 }
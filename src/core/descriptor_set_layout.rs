@@ -1,5 +1,6 @@
 use crate::core::descriptor_set::{VEDescriptorSet, VEDescriptorSetError};
 use crate::core::device::VEDevice;
+use crate::core::per_frame_descriptor_set::VEPerFrameDescriptorSet;
 use ash::vk;
 use std::sync::Arc;
 use thiserror::Error;
@@ -13,10 +14,15 @@ pub enum VEDescriptorSetFieldStage {
 }
 
 pub enum VEDescriptorSetFieldType {
+    // combined image sampler
     Sampler,
+    // sampler and image bound separately, so shaders can pair any sampler with any texture
+    SeparateSampler,
+    SampledImage,
     UniformBuffer,
     StorageBuffer,
     StorageImage,
+    InputAttachment,
 }
 
 pub struct VEDescriptorSetLayout {
@@ -24,6 +30,10 @@ pub struct VEDescriptorSetLayout {
     allocation_counter: u32,
     pub layout: vk::DescriptorSetLayout,
     pools: Vec<Arc<vk::DescriptorPool>>,
+    // None for layouts created elsewhere
+    binding_types: Option<Vec<(u32, vk::DescriptorType)>>,
+    // created with new_push, descriptors are pushed and no sets can be allocated
+    push: bool,
 }
 
 pub struct VEDescriptorSetLayoutField {
@@ -45,6 +55,25 @@ pub enum VEDescriptorSetLayoutError {
 
     #[error("descriptor set creation failed")]
     DescriptorSetCreationFailed(#[source] VEDescriptorSetError),
+
+    #[error("at least one frame in flight is required")]
+    NoFramesInFlight,
+
+    #[error("push descriptors need VK_KHR_push_descriptor")]
+    PushDescriptorNotSupported,
+
+    #[error("no descriptor sets can be allocated from a push descriptor layout")]
+    PushDescriptorLayout,
+}
+
+pub(crate) fn get_field_stage_flags(stage: &VEDescriptorSetFieldStage) -> vk::ShaderStageFlags {
+    match stage {
+        VEDescriptorSetFieldStage::All => vk::ShaderStageFlags::ALL,
+        VEDescriptorSetFieldStage::AllGraphics => vk::ShaderStageFlags::ALL_GRAPHICS,
+        VEDescriptorSetFieldStage::Compute => vk::ShaderStageFlags::COMPUTE,
+        VEDescriptorSetFieldStage::Vertex => vk::ShaderStageFlags::VERTEX,
+        VEDescriptorSetFieldStage::Fragment => vk::ShaderStageFlags::FRAGMENT,
+    }
 }
 
 static DEFAULT_POOL_SIZE: u32 = 256;
@@ -53,22 +82,42 @@ impl VEDescriptorSetLayout {
     pub fn new(
         device: Arc<VEDevice>,
         fields: &[VEDescriptorSetLayoutField],
+    ) -> Result<VEDescriptorSetLayout, VEDescriptorSetLayoutError> {
+        Self::new_with_flags(device, fields, vk::DescriptorSetLayoutCreateFlags::empty())
+    }
+
+    // for VEPushDescriptorTemplate, no descriptor sets can be allocated from it
+    pub fn new_push(
+        device: Arc<VEDevice>,
+        fields: &[VEDescriptorSetLayoutField],
+    ) -> Result<VEDescriptorSetLayout, VEDescriptorSetLayoutError> {
+        if device.push_descriptor.is_none() {
+            return Err(VEDescriptorSetLayoutError::PushDescriptorNotSupported);
+        }
+        Self::new_with_flags(
+            device,
+            fields,
+            vk::DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR,
+        )
+    }
+
+    fn new_with_flags(
+        device: Arc<VEDevice>,
+        fields: &[VEDescriptorSetLayoutField],
+        flags: vk::DescriptorSetLayoutCreateFlags,
     ) -> Result<VEDescriptorSetLayout, VEDescriptorSetLayoutError> {
         let mut bindings = vec![];
         for field in fields {
             let typ = match field.typ {
                 VEDescriptorSetFieldType::Sampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                VEDescriptorSetFieldType::SeparateSampler => vk::DescriptorType::SAMPLER,
+                VEDescriptorSetFieldType::SampledImage => vk::DescriptorType::SAMPLED_IMAGE,
                 VEDescriptorSetFieldType::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
                 VEDescriptorSetFieldType::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
                 VEDescriptorSetFieldType::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+                VEDescriptorSetFieldType::InputAttachment => vk::DescriptorType::INPUT_ATTACHMENT,
             };
-            let stage = match field.stage {
-                VEDescriptorSetFieldStage::All => vk::ShaderStageFlags::ALL,
-                VEDescriptorSetFieldStage::AllGraphics => vk::ShaderStageFlags::ALL_GRAPHICS,
-                VEDescriptorSetFieldStage::Compute => vk::ShaderStageFlags::COMPUTE,
-                VEDescriptorSetFieldStage::Vertex => vk::ShaderStageFlags::VERTEX,
-                VEDescriptorSetFieldStage::Fragment => vk::ShaderStageFlags::FRAGMENT,
-            };
+            let stage = get_field_stage_flags(&field.stage);
             bindings.push(
                 vk::DescriptorSetLayoutBinding::default()
                     .binding(field.binding)
@@ -78,7 +127,13 @@ impl VEDescriptorSetLayout {
             )
         }
 
-        let info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+        let binding_types = bindings
+            .iter()
+            .map(|b| (b.binding, b.descriptor_type))
+            .collect();
+        let info = vk::DescriptorSetLayoutCreateInfo::default()
+            .flags(flags)
+            .bindings(&bindings);
 
         let layout = unsafe {
             device
@@ -92,10 +147,38 @@ impl VEDescriptorSetLayout {
             layout,
             pools: vec![],
             allocation_counter: 0,
+            binding_types: Some(binding_types),
+            push: flags.contains(vk::DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR),
         })
     }
 
+    // wraps a layout created elsewhere, such as the bindless table
+    pub(crate) fn from_handle(
+        device: Arc<VEDevice>,
+        layout: vk::DescriptorSetLayout,
+    ) -> VEDescriptorSetLayout {
+        VEDescriptorSetLayout {
+            device,
+            layout,
+            pools: vec![],
+            allocation_counter: 0,
+            binding_types: None,
+            push: false,
+        }
+    }
+
+    pub(crate) fn get_binding_types(&self) -> Option<&[(u32, vk::DescriptorType)]> {
+        self.binding_types.as_deref()
+    }
+
+    pub fn is_push(&self) -> bool {
+        self.push
+    }
+
     pub fn create_descriptor_set(&mut self) -> Result<VEDescriptorSet, VEDescriptorSetLayoutError> {
+        if self.push {
+            return Err(VEDescriptorSetLayoutError::PushDescriptorLayout);
+        }
         if self.pools.len() == 0 {
             self.generate_new_set_pool()?;
         } else {
@@ -109,8 +192,23 @@ impl VEDescriptorSetLayout {
             .pools
             .last()
             .ok_or_else(|| VEDescriptorSetLayoutError::NoPoolFound)?;
-        VEDescriptorSet::new(self.device.clone(), self.layout, pool)
-            .map_err(VEDescriptorSetLayoutError::DescriptorSetCreationFailed)
+        let set = VEDescriptorSet::new(self.device.clone(), self.layout, pool)
+            .map_err(VEDescriptorSetLayoutError::DescriptorSetCreationFailed)?;
+        Ok(match &self.binding_types {
+            Some(binding_types) => set.with_binding_types(binding_types.clone()),
+            None => set,
+        })
+    }
+
+    pub fn create_per_frame_descriptor_set(
+        &mut self,
+        frames_in_flight: u32,
+    ) -> Result<VEPerFrameDescriptorSet, VEDescriptorSetLayoutError> {
+        let mut sets = vec![];
+        for _ in 0..frames_in_flight {
+            sets.push(self.create_descriptor_set()?);
+        }
+        VEPerFrameDescriptorSet::new(sets)
     }
 
     pub fn generate_new_set_pool(&mut self) -> Result<(), VEDescriptorSetLayoutError> {
@@ -124,9 +222,18 @@ impl VEDescriptorSetLayout {
             vk::DescriptorPoolSize::default()
                 .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
                 .descriptor_count(DEFAULT_POOL_SIZE),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::SAMPLER)
+                .descriptor_count(DEFAULT_POOL_SIZE),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::SAMPLED_IMAGE)
+                .descriptor_count(DEFAULT_POOL_SIZE),
             vk::DescriptorPoolSize::default()
                 .ty(vk::DescriptorType::STORAGE_IMAGE)
                 .descriptor_count(DEFAULT_POOL_SIZE),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::INPUT_ATTACHMENT)
+                .descriptor_count(DEFAULT_POOL_SIZE),
         ];
         let info = vk::DescriptorPoolCreateInfo::default()
             .pool_sizes(&pool_sizes)
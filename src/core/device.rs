@@ -1,13 +1,18 @@
+use crate::core::feature_report::VEFeatureReport;
+use crate::core::fence::VEFence;
 use crate::window::window::VEWindow;
-use ash::ext::debug_utils;
-use ash::khr::{surface, swapchain};
+use ash::ext::{
+    custom_border_color, debug_utils, depth_clip_enable, extended_dynamic_state,
+    fragment_shader_interlock, memory_priority, pageable_device_local_memory,
+};
+use ash::khr::{dynamic_rendering_local_read, push_descriptor, surface, swapchain};
 use ash::vk::{
     make_api_version, ApplicationInfo, DebugUtilsMessageSeverityFlagsEXT,
     DebugUtilsMessageTypeFlagsEXT, DebugUtilsMessengerCreateInfoEXT, InstanceCreateFlags,
     InstanceCreateInfo, MemoryPropertyFlags, PhysicalDevice, PhysicalDeviceMemoryProperties,
-    SurfaceKHR,
+    PhysicalDeviceProperties, SurfaceKHR,
 };
-use ash::{vk, Device, Instance};
+use ash::{vk, Device, Entry, Instance};
 use std::borrow::Cow;
 use std::ffi;
 use std::fmt::{Debug, Formatter};
@@ -46,13 +51,45 @@ pub enum VEDeviceError {
     #[error("cannot create surface")]
     CannotCreateSurface(#[source] vk::Result),
 
+    #[error("cannot enumerate device extensions")]
+    CannotEnumerateDeviceExtensions(#[source] vk::Result),
+
     #[error("cannot create device")]
     CannotCreateDevice(#[source] vk::Result),
 
+    #[error("cannot enumerate instance extensions")]
+    CannotEnumerateInstanceExtensions(#[source] vk::Result),
+
+    #[error("debug name contains a nul byte")]
+    InvalidDebugName(#[source] ffi::NulError),
+
+    #[error("cannot set debug name")]
+    CannotSetDebugName(#[source] vk::Result),
+
+    #[error("device lost")]
+    DeviceLost,
+
+    #[error("fence wait failed")]
+    FenceWaitFailed(#[source] vk::Result),
+
     #[error("device wait idle failed")]
     DeviceWaitIdleFailed(#[source] vk::Result),
 }
 
+// which fragment shader interlock modes the device enabled. in glsl, with
+// GL_ARB_fragment_shader_interlock, declare e.g. `layout(pixel_interlock_ordered) in;` and put
+// the read-modify-write of the attachment or storage image between beginInvocationInterlockARB()
+// and endInvocationInterlockARB(). each is called exactly once, in uniform control flow of main.
+// overlapping fragments then run the section one after another in primitive order, so no
+// barriers are needed inside the pass. sample interlock orders per sample, shading rate
+// interlock per fragment shading rate region
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VEFragmentShaderInterlockGranularity {
+    pub pixel: bool,
+    pub sample: bool,
+    pub shading_rate: bool,
+}
+
 pub struct VEDevice {
     pub instance: Instance,
     pub device: Device,
@@ -60,7 +97,25 @@ pub struct VEDevice {
     pub surface_loader: surface::Instance,
     pub surface: SurfaceKHR,
     pub queue_family_index: u32,
+    pub extended_dynamic_state: Option<extended_dynamic_state::Device>,
+    pub dynamic_rendering_local_read: Option<dynamic_rendering_local_read::Device>,
+    pub debug_utils: Option<debug_utils::Device>,
+    pub pageable_device_local_memory: Option<pageable_device_local_memory::Device>,
+    pub push_descriptor: Option<push_descriptor::Device>,
+    // core since 1.1
+    supports_descriptor_update_templates: bool,
+    supports_bindless: bool,
+    supports_multi_draw_indirect: bool,
+    supports_depth_clamp: bool,
+    supports_depth_bounds: bool,
+    supports_depth_clip_enable: bool,
+    supports_custom_border_color: bool,
+    supports_sampler_anisotropy: bool,
+    supports_fill_mode_non_solid: bool,
+    feature_report: VEFeatureReport,
+    fragment_shader_interlock: Option<VEFragmentShaderInterlockGranularity>,
     device_memory_properties: PhysicalDeviceMemoryProperties,
+    device_properties: PhysicalDeviceProperties,
 }
 
 impl Debug for VEDevice {
@@ -126,7 +181,12 @@ impl VEDevice {
         let mut extension_names = ash_window::enumerate_required_extensions(display_handle)
             .map_err(VEDeviceError::CannotEnumerateRequiredWindowExtensions)?
             .to_vec();
-        extension_names.push(debug_utils::NAME.as_ptr());
+        let supports_debug_utils = Self::available_instance_extensions(&window.entry)?
+            .iter()
+            .any(|ext| debug_utils::NAME.to_str() == Ok(ext.as_str()));
+        if supports_debug_utils {
+            extension_names.push(debug_utils::NAME.as_ptr());
+        }
         #[cfg(any(target_os = "macos", target_os = "ios"))]
         {
             extension_names.push(ash::khr::portability_enumeration::NAME.as_ptr());
@@ -173,12 +233,14 @@ impl VEDevice {
             )
             .pfn_user_callback(Some(vulkan_debug_callback));
 
-        let debug_utils_loader = debug_utils::Instance::new(&window.entry, &instance);
+        if supports_debug_utils {
+            let debug_utils_loader = debug_utils::Instance::new(&window.entry, &instance);
 
-        unsafe {
-            debug_utils_loader
-                .create_debug_utils_messenger(&debug_info, None)
-                .map_err(VEDeviceError::CannotCreateDebugUtilsMessenger)?;
+            unsafe {
+                debug_utils_loader
+                    .create_debug_utils_messenger(&debug_info, None)
+                    .map_err(VEDeviceError::CannotCreateDebugUtilsMessenger)?;
+            }
         }
 
         let surface = unsafe {
@@ -223,14 +285,149 @@ impl VEDevice {
             .ok_or(VEDeviceError::NoSuitablePhysicalDeviceFound)?;
 
         let queue_family_index = queue_family_index as u32;
-        let device_extension_names_raw = [
+
+        let supported_device_extensions = Self::available_device_extensions(&instance, pdevice)?;
+        let is_device_extension_supported = |name: &ffi::CStr| {
+            supported_device_extensions
+                .iter()
+                .any(|ext| name.to_str() == Ok(ext.as_str()))
+        };
+        let extended_dynamic_state_extension_present =
+            is_device_extension_supported(extended_dynamic_state::NAME);
+        let local_read_extension_present =
+            is_device_extension_supported(dynamic_rendering_local_read::NAME);
+        let interlock_extension_present =
+            is_device_extension_supported(fragment_shader_interlock::NAME);
+        let pageable_extension_present =
+            is_device_extension_supported(pageable_device_local_memory::NAME)
+                && is_device_extension_supported(memory_priority::NAME);
+        let depth_clip_extension_present = is_device_extension_supported(depth_clip_enable::NAME);
+        let custom_border_color_extension_present =
+            is_device_extension_supported(custom_border_color::NAME);
+        let supports_push_descriptor = is_device_extension_supported(push_descriptor::NAME);
+
+        let mut device_extension_names_raw = [
             swapchain::NAME.as_ptr(),
             #[cfg(any(target_os = "macos", target_os = "ios"))]
             ash::khr::portability_subset::NAME.as_ptr(),
-        ];
+        ]
+        .to_vec();
+        if supports_push_descriptor {
+            device_extension_names_raw.push(push_descriptor::NAME.as_ptr());
+        }
+
+        let supported_features = unsafe { instance.get_physical_device_features(pdevice) };
+
+        // descriptor indexing is core since 1.2, everything needed by the bindless table
+        let device_api_version =
+            unsafe { instance.get_physical_device_properties(pdevice) }.api_version;
+        let mut supported_indexing_features =
+            vk::PhysicalDeviceDescriptorIndexingFeatures::default();
+        let mut supported_dynamic_rendering_features =
+            vk::PhysicalDeviceDynamicRenderingFeatures::default();
+        let mut supported_local_read_features =
+            vk::PhysicalDeviceDynamicRenderingLocalReadFeaturesKHR::default();
+        let mut supported_interlock_features =
+            vk::PhysicalDeviceFragmentShaderInterlockFeaturesEXT::default();
+        let mut supported_pageable_features =
+            vk::PhysicalDevicePageableDeviceLocalMemoryFeaturesEXT::default();
+        let mut supported_depth_clip_features =
+            vk::PhysicalDeviceDepthClipEnableFeaturesEXT::default();
+        let mut supported_custom_border_color_features =
+            vk::PhysicalDeviceCustomBorderColorFeaturesEXT::default();
+        let mut supported_extended_dynamic_state_features =
+            vk::PhysicalDeviceExtendedDynamicStateFeaturesEXT::default();
+        if device_api_version >= make_api_version(0, 1, 2, 0) {
+            let mut supported_features2 =
+                vk::PhysicalDeviceFeatures2::default().push_next(&mut supported_indexing_features);
+            // dynamic rendering is core since 1.3, local read still needs the extension
+            if device_api_version >= make_api_version(0, 1, 3, 0) && local_read_extension_present {
+                supported_features2 = supported_features2
+                    .push_next(&mut supported_dynamic_rendering_features)
+                    .push_next(&mut supported_local_read_features);
+            }
+            if interlock_extension_present {
+                supported_features2 =
+                    supported_features2.push_next(&mut supported_interlock_features);
+            }
+            if pageable_extension_present {
+                supported_features2 =
+                    supported_features2.push_next(&mut supported_pageable_features);
+            }
+            if depth_clip_extension_present {
+                supported_features2 =
+                    supported_features2.push_next(&mut supported_depth_clip_features);
+            }
+            if custom_border_color_extension_present {
+                supported_features2 =
+                    supported_features2.push_next(&mut supported_custom_border_color_features);
+            }
+            if extended_dynamic_state_extension_present {
+                supported_features2 =
+                    supported_features2.push_next(&mut supported_extended_dynamic_state_features);
+            }
+            unsafe { instance.get_physical_device_features2(pdevice, &mut supported_features2) };
+        }
+        // the extension can be listed while the feature itself is not supported
+        let supports_extended_dynamic_state =
+            supported_extended_dynamic_state_features.extended_dynamic_state == vk::TRUE;
+        if supports_extended_dynamic_state {
+            device_extension_names_raw.push(extended_dynamic_state::NAME.as_ptr());
+        }
+        let supports_dynamic_rendering_local_read =
+            supported_dynamic_rendering_features.dynamic_rendering == vk::TRUE
+                && supported_local_read_features.dynamic_rendering_local_read == vk::TRUE;
+        if supports_dynamic_rendering_local_read {
+            device_extension_names_raw.push(dynamic_rendering_local_read::NAME.as_ptr());
+        }
+        let fragment_shader_interlock = Some(VEFragmentShaderInterlockGranularity {
+            pixel: supported_interlock_features.fragment_shader_pixel_interlock == vk::TRUE,
+            sample: supported_interlock_features.fragment_shader_sample_interlock == vk::TRUE,
+            shading_rate: supported_interlock_features.fragment_shader_shading_rate_interlock
+                == vk::TRUE,
+        })
+        .filter(|granularity| granularity.pixel || granularity.sample || granularity.shading_rate);
+        if fragment_shader_interlock.is_some() {
+            device_extension_names_raw.push(fragment_shader_interlock::NAME.as_ptr());
+        }
+        let supports_pageable_device_local_memory =
+            supported_pageable_features.pageable_device_local_memory == vk::TRUE;
+        if supports_pageable_device_local_memory {
+            device_extension_names_raw.push(memory_priority::NAME.as_ptr());
+            device_extension_names_raw.push(pageable_device_local_memory::NAME.as_ptr());
+        }
+        let supports_depth_clip_enable =
+            supported_depth_clip_features.depth_clip_enable == vk::TRUE;
+        if supports_depth_clip_enable {
+            device_extension_names_raw.push(depth_clip_enable::NAME.as_ptr());
+        }
+        // samplers pass no format with the color, so both features are needed
+        let supports_custom_border_color =
+            supported_custom_border_color_features.custom_border_colors == vk::TRUE
+                && supported_custom_border_color_features.custom_border_color_without_format
+                    == vk::TRUE;
+        if supports_custom_border_color {
+            device_extension_names_raw.push(custom_border_color::NAME.as_ptr());
+        }
+        let supports_bindless =
+            supported_indexing_features.shader_sampled_image_array_non_uniform_indexing == vk::TRUE
+                && supported_indexing_features.descriptor_binding_sampled_image_update_after_bind
+                    == vk::TRUE
+                && supported_indexing_features.descriptor_binding_update_unused_while_pending
+                    == vk::TRUE
+                && supported_indexing_features.descriptor_binding_partially_bound == vk::TRUE;
         let features = vk::PhysicalDeviceFeatures {
             shader_clip_distance: 1,
-            depth_clamp: 1,
+            depth_clamp: supported_features.depth_clamp,
+            depth_bounds: supported_features.depth_bounds,
+            sampler_anisotropy: supported_features.sampler_anisotropy,
+            // line and point polygon modes for wireframe debug views
+            fill_mode_non_solid: supported_features.fill_mode_non_solid,
+            // needed by the compute mipmap downsampler for r8, rg8, rg16 and friends
+            shader_storage_image_extended_formats: supported_features
+                .shader_storage_image_extended_formats,
+            // indirect draws fall back to one call per command without it
+            multi_draw_indirect: supported_features.multi_draw_indirect,
             ..Default::default()
         };
         let priorities = [1.0];
@@ -239,10 +436,69 @@ impl VEDevice {
             .queue_family_index(queue_family_index)
             .queue_priorities(&priorities);
 
-        let device_create_info = vk::DeviceCreateInfo::default()
+        let mut extended_dynamic_state_features =
+            vk::PhysicalDeviceExtendedDynamicStateFeaturesEXT::default()
+                .extended_dynamic_state(true);
+
+        let mut indexing_features = vk::PhysicalDeviceDescriptorIndexingFeatures::default()
+            .shader_sampled_image_array_non_uniform_indexing(true)
+            .descriptor_binding_sampled_image_update_after_bind(true)
+            .descriptor_binding_update_unused_while_pending(true)
+            .descriptor_binding_partially_bound(true)
+            .runtime_descriptor_array(
+                supported_indexing_features.runtime_descriptor_array == vk::TRUE,
+            );
+
+        let mut dynamic_rendering_features =
+            vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
+        let mut local_read_features =
+            vk::PhysicalDeviceDynamicRenderingLocalReadFeaturesKHR::default()
+                .dynamic_rendering_local_read(true);
+
+        let interlock_granularity = fragment_shader_interlock.unwrap_or_default();
+        let mut interlock_features =
+            vk::PhysicalDeviceFragmentShaderInterlockFeaturesEXT::default()
+                .fragment_shader_pixel_interlock(interlock_granularity.pixel)
+                .fragment_shader_sample_interlock(interlock_granularity.sample)
+                .fragment_shader_shading_rate_interlock(interlock_granularity.shading_rate);
+        let mut pageable_features =
+            vk::PhysicalDevicePageableDeviceLocalMemoryFeaturesEXT::default()
+                .pageable_device_local_memory(true);
+
+        let mut depth_clip_features =
+            vk::PhysicalDeviceDepthClipEnableFeaturesEXT::default().depth_clip_enable(true);
+        let mut custom_border_color_features =
+            vk::PhysicalDeviceCustomBorderColorFeaturesEXT::default()
+                .custom_border_colors(true)
+                .custom_border_color_without_format(true);
+
+        let mut device_create_info = vk::DeviceCreateInfo::default()
             .queue_create_infos(std::slice::from_ref(&queue_info))
             .enabled_extension_names(&device_extension_names_raw)
             .enabled_features(&features);
+        if supports_extended_dynamic_state {
+            device_create_info = device_create_info.push_next(&mut extended_dynamic_state_features);
+        }
+        if supports_bindless {
+            device_create_info = device_create_info.push_next(&mut indexing_features);
+        }
+        if supports_dynamic_rendering_local_read {
+            device_create_info = device_create_info
+                .push_next(&mut dynamic_rendering_features)
+                .push_next(&mut local_read_features);
+        }
+        if fragment_shader_interlock.is_some() {
+            device_create_info = device_create_info.push_next(&mut interlock_features);
+        }
+        if supports_pageable_device_local_memory {
+            device_create_info = device_create_info.push_next(&mut pageable_features);
+        }
+        if supports_depth_clip_enable {
+            device_create_info = device_create_info.push_next(&mut depth_clip_features);
+        }
+        if supports_custom_border_color {
+            device_create_info = device_create_info.push_next(&mut custom_border_color_features);
+        }
 
         let device: Device = unsafe {
             instance
@@ -250,8 +506,89 @@ impl VEDevice {
                 .map_err(VEDeviceError::CannotCreateDevice)?
         };
 
+        let extended_dynamic_state = if supports_extended_dynamic_state {
+            Some(extended_dynamic_state::Device::new(&instance, &device))
+        } else {
+            None
+        };
+
+        let dynamic_rendering_local_read = if supports_dynamic_rendering_local_read {
+            Some(dynamic_rendering_local_read::Device::new(
+                &instance, &device,
+            ))
+        } else {
+            None
+        };
+
+        let push_descriptor = if supports_push_descriptor {
+            Some(push_descriptor::Device::new(&instance, &device))
+        } else {
+            None
+        };
+
+        let debug_utils = if supports_debug_utils {
+            Some(debug_utils::Device::new(&instance, &device))
+        } else {
+            None
+        };
+
+        let pageable_device_local_memory = if supports_pageable_device_local_memory {
+            Some(pageable_device_local_memory::Device::new(
+                &instance, &device,
+            ))
+        } else {
+            None
+        };
+
+        let supports_descriptor_update_templates =
+            device_api_version >= make_api_version(0, 1, 1, 0);
+        let mut feature_report = VEFeatureReport::default();
+        feature_report.required("swapchain");
+        feature_report.required("shader clip distance");
+        feature_report.optional("extended dynamic state", supports_extended_dynamic_state);
+        feature_report.optional(
+            "dynamic rendering local read",
+            supports_dynamic_rendering_local_read,
+        );
+        feature_report.optional(
+            "fragment shader interlock",
+            fragment_shader_interlock.is_some(),
+        );
+        feature_report.optional(
+            "pageable device local memory",
+            supports_pageable_device_local_memory,
+        );
+        feature_report.optional("push descriptor", supports_push_descriptor);
+        feature_report.optional(
+            "descriptor update templates",
+            supports_descriptor_update_templates,
+        );
+        feature_report.optional("bindless", supports_bindless);
+        feature_report.optional(
+            "multi draw indirect",
+            supported_features.multi_draw_indirect == vk::TRUE,
+        );
+        feature_report.optional("depth clamp", supported_features.depth_clamp == vk::TRUE);
+        feature_report.optional("depth bounds", supported_features.depth_bounds == vk::TRUE);
+        feature_report.optional("depth clip enable", supports_depth_clip_enable);
+        feature_report.optional("custom border color", supports_custom_border_color);
+        feature_report.optional(
+            "sampler anisotropy",
+            supported_features.sampler_anisotropy == vk::TRUE,
+        );
+        feature_report.optional(
+            "fill mode non solid",
+            supported_features.fill_mode_non_solid == vk::TRUE,
+        );
+        feature_report.optional(
+            "shader storage image extended formats",
+            supported_features.shader_storage_image_extended_formats == vk::TRUE,
+        );
+        feature_report.optional("debug utils", supports_debug_utils);
+
         let device_memory_properties =
             unsafe { instance.get_physical_device_memory_properties(pdevice) };
+        let device_properties = unsafe { instance.get_physical_device_properties(pdevice) };
 
         Ok(VEDevice {
             instance,
@@ -260,7 +597,24 @@ impl VEDevice {
             surface_loader,
             surface,
             queue_family_index,
+            extended_dynamic_state,
+            dynamic_rendering_local_read,
+            debug_utils,
+            pageable_device_local_memory,
+            push_descriptor,
+            supports_descriptor_update_templates,
+            supports_bindless,
+            supports_multi_draw_indirect: supported_features.multi_draw_indirect == vk::TRUE,
+            supports_depth_clamp: supported_features.depth_clamp == vk::TRUE,
+            supports_depth_bounds: supported_features.depth_bounds == vk::TRUE,
+            supports_depth_clip_enable,
+            supports_custom_border_color,
+            supports_sampler_anisotropy: supported_features.sampler_anisotropy == vk::TRUE,
+            supports_fill_mode_non_solid: supported_features.fill_mode_non_solid == vk::TRUE,
+            feature_report,
+            fragment_shader_interlock,
             device_memory_properties,
+            device_properties,
         })
     }
 
@@ -280,6 +634,227 @@ impl VEDevice {
         None
     }
 
+    // usable before any instance exists, for example to decide which window features to use
+    pub fn available_instance_extensions(entry: &Entry) -> Result<Vec<String>, VEDeviceError> {
+        let properties = unsafe {
+            entry
+                .enumerate_instance_extension_properties(None)
+                .map_err(VEDeviceError::CannotEnumerateInstanceExtensions)?
+        };
+        Ok(properties
+            .iter()
+            .filter_map(|ext| ext.extension_name_as_c_str().ok())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    pub fn available_device_extensions(
+        instance: &Instance,
+        physical_device: PhysicalDevice,
+    ) -> Result<Vec<String>, VEDeviceError> {
+        let properties = unsafe {
+            instance
+                .enumerate_device_extension_properties(physical_device)
+                .map_err(VEDeviceError::CannotEnumerateDeviceExtensions)?
+        };
+        Ok(properties
+            .iter()
+            .filter_map(|ext| ext.extension_name_as_c_str().ok())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    // supported by the physical device, not necessarily enabled on this device
+    pub fn is_device_extension_available(&self, name: &str) -> bool {
+        Self::available_device_extensions(&self.instance, self.physical_device)
+            .is_ok_and(|extensions| extensions.iter().any(|ext| ext == name))
+    }
+
+    pub fn get_max_sampler_lod_bias(&self) -> f32 {
+        self.device_properties.limits.max_sampler_lod_bias
+    }
+
+    // nanoseconds per timestamp tick
+    pub fn timestamp_period(&self) -> f32 {
+        self.device_properties.limits.timestamp_period
+    }
+
+    pub fn get_format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        unsafe {
+            self.instance
+                .get_physical_device_format_properties(self.physical_device, format)
+        }
+    }
+
+    // limits for this exact 2D format, usage and tiling combination, including max extent,
+    // mip levels and sample counts, None when the combination is not supported at all
+    pub fn get_image_format_properties(
+        &self,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        tiling: vk::ImageTiling,
+    ) -> Option<vk::ImageFormatProperties> {
+        unsafe {
+            self.instance
+                .get_physical_device_image_format_properties(
+                    self.physical_device,
+                    format,
+                    vk::ImageType::TYPE_2D,
+                    tiling,
+                    usage,
+                    vk::ImageCreateFlags::empty(),
+                )
+                .ok()
+        }
+    }
+
+    pub fn supports_image_usage(
+        &self,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        tiling: vk::ImageTiling,
+    ) -> bool {
+        self.get_image_format_properties(format, usage, tiling)
+            .is_some()
+    }
+
+    pub fn supports_storage_image(&self, format: vk::Format) -> bool {
+        self.get_format_properties(format)
+            .optimal_tiling_features
+            .contains(vk::FormatFeatureFlags::STORAGE_IMAGE)
+    }
+
+    pub fn supports_extended_dynamic_state(&self) -> bool {
+        self.extended_dynamic_state.is_some()
+    }
+
+    pub fn supports_dynamic_rendering_local_read(&self) -> bool {
+        self.dynamic_rendering_local_read.is_some()
+    }
+
+    pub fn supports_fragment_shader_interlock(&self) -> bool {
+        self.fragment_shader_interlock.is_some()
+    }
+
+    pub fn get_fragment_shader_interlock_granularity(
+        &self,
+    ) -> Option<VEFragmentShaderInterlockGranularity> {
+        self.fragment_shader_interlock
+    }
+
+    pub fn supports_pageable_device_local_memory(&self) -> bool {
+        self.pageable_device_local_memory.is_some()
+    }
+
+    pub fn supports_bindless(&self) -> bool {
+        self.supports_bindless
+    }
+
+    pub fn supports_multi_draw_indirect(&self) -> bool {
+        self.supports_multi_draw_indirect
+    }
+
+    pub fn supports_depth_clamp(&self) -> bool {
+        self.supports_depth_clamp
+    }
+
+    pub fn supports_descriptor_update_templates(&self) -> bool {
+        self.supports_descriptor_update_templates
+    }
+
+    pub fn supports_depth_bounds(&self) -> bool {
+        self.supports_depth_bounds
+    }
+
+    pub fn supports_depth_clip_enable(&self) -> bool {
+        self.supports_depth_clip_enable
+    }
+
+    pub fn supports_custom_border_color(&self) -> bool {
+        self.supports_custom_border_color
+    }
+
+    pub fn supports_sampler_anisotropy(&self) -> bool {
+        self.supports_sampler_anisotropy
+    }
+
+    pub fn supports_fill_mode_non_solid(&self) -> bool {
+        self.supports_fill_mode_non_solid
+    }
+
+    pub fn get_feature_report(&self) -> &VEFeatureReport {
+        &self.feature_report
+    }
+
+    // both return None when the index is past memory_type_count
+    pub fn memory_type_properties(&self, memory_type_index: u32) -> Option<MemoryPropertyFlags> {
+        if memory_type_index >= self.device_memory_properties.memory_type_count {
+            return None;
+        }
+        Some(self.device_memory_properties.memory_types[memory_type_index as usize].property_flags)
+    }
+
+    pub fn get_device_local_heap_size(&self) -> u64 {
+        let heap_count = self.device_memory_properties.memory_heap_count as usize;
+        self.device_memory_properties.memory_heaps[..heap_count]
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .sum()
+    }
+
+    pub fn memory_heap_for_type(&self, memory_type_index: u32) -> Option<u32> {
+        if memory_type_index >= self.device_memory_properties.memory_type_count {
+            return None;
+        }
+        Some(self.device_memory_properties.memory_types[memory_type_index as usize].heap_index)
+    }
+
+    pub(crate) fn get_non_coherent_atom_size(&self) -> u64 {
+        self.device_properties.limits.non_coherent_atom_size
+    }
+
+    // object type comes from the handle type, does nothing without VK_EXT_debug_utils
+    pub fn set_debug_name<H: vk::Handle>(
+        &self,
+        handle: H,
+        name: &str,
+    ) -> Result<(), VEDeviceError> {
+        let Some(debug_utils) = &self.debug_utils else {
+            return Ok(());
+        };
+        let name = ffi::CString::new(name).map_err(VEDeviceError::InvalidDebugName)?;
+        let info = vk::DebugUtilsObjectNameInfoEXT::default()
+            .object_handle(handle)
+            .object_name(&name);
+        unsafe {
+            debug_utils
+                .set_debug_utils_object_name(&info)
+                .map_err(VEDeviceError::CannotSetDebugName)?;
+        }
+        Ok(())
+    }
+
+    // wait_all false returns once any of the fences is signaled, timeout is in nanoseconds.
+    // returns true if the timeout expired first
+    pub fn wait_for_fences(
+        &self,
+        fences: &[&VEFence],
+        wait_all: bool,
+        timeout: u64,
+    ) -> Result<bool, VEDeviceError> {
+        if fences.is_empty() {
+            return Ok(false);
+        }
+        let handles: Vec<vk::Fence> = fences.iter().map(|fence| fence.handle).collect();
+        match unsafe { self.device.wait_for_fences(&handles, wait_all, timeout) } {
+            Ok(()) => Ok(false),
+            Err(vk::Result::TIMEOUT) => Ok(true),
+            Err(vk::Result::ERROR_DEVICE_LOST) => Err(VEDeviceError::DeviceLost),
+            Err(e) => Err(VEDeviceError::FenceWaitFailed(e)),
+        }
+    }
+
     pub fn wait_idle(&self) -> Result<(), VEDeviceError> {
         unsafe {
             self.device
@@ -0,0 +1,62 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VEFeatureReportEntry {
+    pub name: &'static str,
+    // required entries are always enabled, device creation fails without them
+    pub required: bool,
+    pub enabled: bool,
+}
+
+// what the device enabled at creation, one entry per required and optional feature or
+// extension. displays as e.g. "sampler anisotropy: off; push descriptor: on" for logging
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VEFeatureReport {
+    pub entries: Vec<VEFeatureReportEntry>,
+}
+
+impl VEFeatureReport {
+    pub(crate) fn required(&mut self, name: &'static str) {
+        self.entries.push(VEFeatureReportEntry {
+            name,
+            required: true,
+            enabled: true,
+        });
+    }
+
+    pub(crate) fn optional(&mut self, name: &'static str, enabled: bool) {
+        self.entries.push(VEFeatureReportEntry {
+            name,
+            required: false,
+            enabled,
+        });
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.name == name && entry.enabled)
+    }
+
+    // optional features the device could not enable
+    pub fn disabled(&self) -> impl Iterator<Item = &VEFeatureReportEntry> {
+        self.entries.iter().filter(|entry| !entry.enabled)
+    }
+}
+
+impl Display for VEFeatureReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(
+                f,
+                "{}: {}",
+                entry.name,
+                if entry.enabled { "on" } else { "off" }
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::core::device::VEDevice;
+use ash::vk;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEFenceError {
+    #[error("creation failed")]
+    CreationFailed(#[source] vk::Result),
+
+    #[error("wait failed")]
+    WaitFailed(#[source] vk::Result),
+
+    #[error("reset failed")]
+    ResetFailed(#[source] vk::Result),
+
+    #[error("status query failed")]
+    StatusQueryFailed(#[source] vk::Result),
+}
+
+#[derive(Debug)]
+pub struct VEFence {
+    device: Arc<VEDevice>,
+    pub handle: vk::Fence,
+}
+
+impl VEFence {
+    pub fn new(device: Arc<VEDevice>, signaled: bool) -> Result<VEFence, VEFenceError> {
+        let info = vk::FenceCreateInfo::default().flags(if signaled {
+            vk::FenceCreateFlags::SIGNALED
+        } else {
+            vk::FenceCreateFlags::empty()
+        });
+        let handle = unsafe {
+            device
+                .device
+                .create_fence(&info, None)
+                .map_err(VEFenceError::CreationFailed)?
+        };
+
+        Ok(VEFence { device, handle })
+    }
+
+    pub fn wait(&self) -> Result<(), VEFenceError> {
+        unsafe {
+            self.device
+                .device
+                .wait_for_fences(&[self.handle], true, u64::MAX)
+                .map_err(VEFenceError::WaitFailed)
+        }
+    }
+
+    pub fn reset(&self) -> Result<(), VEFenceError> {
+        unsafe {
+            self.device
+                .device
+                .reset_fences(&[self.handle])
+                .map_err(VEFenceError::ResetFailed)
+        }
+    }
+
+    pub fn is_signaled(&self) -> Result<bool, VEFenceError> {
+        unsafe {
+            self.device
+                .device
+                .get_fence_status(self.handle)
+                .map_err(VEFenceError::StatusQueryFailed)
+        }
+    }
+}
+
+impl Drop for VEFence {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device.destroy_fence(self.handle, None);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::image::image_format::to_linear_format;
 use ash::vk;
 
 pub fn clear_color_f32(values: [f32; 4]) -> vk::ClearValue {
@@ -6,6 +7,35 @@ pub fn clear_color_f32(values: [f32; 4]) -> vk::ClearValue {
     }
 }
 
+// sRGB attachments encode the clear value on write, so clearing to 0.5 with an
+// sRGB target shows up brighter than the same clear on an UNORM target.
+// for sRGB formats the rgb channels are passed through the inverse transfer
+// (alpha is left alone) so the stored value matches what was asked for; other
+// formats get the values unchanged, same as clear_color_f32
+pub fn clear_color_linear(values: [f32; 4], target_format: vk::Format) -> vk::ClearValue {
+    if !is_srgb_format(target_format) {
+        return clear_color_f32(values);
+    }
+    clear_color_f32([
+        srgb_to_linear(values[0]),
+        srgb_to_linear(values[1]),
+        srgb_to_linear(values[2]),
+        values[3],
+    ])
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn is_srgb_format(format: vk::Format) -> bool {
+    to_linear_format(format).is_some()
+}
+
 pub fn clear_color_i32(values: [i32; 4]) -> vk::ClearValue {
     vk::ClearValue {
         color: vk::ClearColorValue { int32: values },
@@ -26,3 +56,9 @@ pub fn clear_depth(value: f32) -> vk::ClearValue {
         },
     }
 }
+
+pub fn clear_depth_stencil(value: vk::ClearDepthStencilValue) -> vk::ClearValue {
+    vk::ClearValue {
+        depth_stencil: value,
+    }
+}
@@ -7,22 +7,55 @@ use thiserror::Error;
 pub enum VEMainDeviceQueueError {
     #[error("queue wait idle failed")]
     QueueWaitIdleFailed(#[source] vk::Result),
+
+    #[error("the queue family does not support timestamps")]
+    TimestampsNotSupported,
 }
 
 #[derive(Debug)]
 pub struct VEMainDeviceQueue {
     device: Arc<VEDevice>,
     pub main_queue: vk::Queue,
+    timestamp_valid_bits: u32,
 }
 
 impl VEMainDeviceQueue {
     pub fn new(device: Arc<VEDevice>) -> VEMainDeviceQueue {
+        let timestamp_valid_bits = unsafe {
+            device
+                .instance
+                .get_physical_device_queue_family_properties(device.physical_device)
+        }
+        .get(device.queue_family_index as usize)
+        .map_or(0, |properties| properties.timestamp_valid_bits);
         VEMainDeviceQueue {
             device: device.clone(),
             main_queue: unsafe { device.device.get_device_queue(device.queue_family_index, 0) },
+            timestamp_valid_bits,
         }
     }
 
+    // 0 means timestamps written on this queue are meaningless
+    pub fn timestamp_valid_bits(&self) -> u32 {
+        self.timestamp_valid_bits
+    }
+
+    // mask for raw timestamp query results, the upper bits are undefined
+    pub fn timestamp_mask(&self) -> Result<u64, VEMainDeviceQueueError> {
+        match self.timestamp_valid_bits {
+            0 => Err(VEMainDeviceQueueError::TimestampsNotSupported),
+            64.. => Ok(u64::MAX),
+            bits => Ok((1u64 << bits) - 1),
+        }
+    }
+
+    // nanoseconds between two raw timestamps written on this queue
+    pub fn timestamp_delta_ns(&self, start: u64, end: u64) -> Result<f64, VEMainDeviceQueueError> {
+        let mask = self.timestamp_mask()?;
+        let ticks = (end & mask).wrapping_sub(start & mask) & mask;
+        Ok(ticks as f64 * self.device.timestamp_period() as f64)
+    }
+
     pub fn wait_idle(&self) -> Result<(), VEMainDeviceQueueError> {
         unsafe {
             self.device
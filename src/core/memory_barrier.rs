@@ -1,6 +1,10 @@
+use crate::buffer::buffer::VEBuffer;
 use crate::core::command_buffer::VECommandBuffer;
 use crate::core::device::VEDevice;
+use crate::core::sync_state::VESyncState;
+use crate::image::image::VEImage;
 use ash::vk;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 pub struct VEMemoryBarrier {
@@ -27,11 +31,33 @@ pub struct VEImageMemoryBarrier {
 
 impl VEImageMemoryBarrier {
     pub fn build(&self) -> vk::ImageMemoryBarrier {
+        self.build_with_queue_families(vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+    }
+
+    // ownership transfer between queue families, skipped for concurrent images
+    pub fn build_ownership_transfer(
+        &self,
+        image: &VEImage,
+        src_queue_family: u32,
+        dst_queue_family: u32,
+    ) -> vk::ImageMemoryBarrier<'static> {
+        if image.is_concurrent() || src_queue_family == dst_queue_family {
+            self.build_with_queue_families(vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+        } else {
+            self.build_with_queue_families(src_queue_family, dst_queue_family)
+        }
+    }
+
+    fn build_with_queue_families(
+        &self,
+        src_queue_family: u32,
+        dst_queue_family: u32,
+    ) -> vk::ImageMemoryBarrier<'static> {
         vk::ImageMemoryBarrier::default()
             .old_layout(self.old_layout)
             .new_layout(self.new_layout)
-            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .src_queue_family_index(src_queue_family)
+            .dst_queue_family_index(dst_queue_family)
             .image(self.image)
             .subresource_range(
                 vk::ImageSubresourceRange::default()
@@ -56,9 +82,31 @@ pub struct VEBufferMemoryBarrier {
 
 impl VEBufferMemoryBarrier {
     pub fn build(&self) -> vk::BufferMemoryBarrier {
+        self.build_with_queue_families(vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+    }
+
+    // ownership transfer between queue families, skipped for concurrent buffers
+    pub fn build_ownership_transfer(
+        &self,
+        buffer: &VEBuffer,
+        src_queue_family: u32,
+        dst_queue_family: u32,
+    ) -> vk::BufferMemoryBarrier<'static> {
+        if buffer.is_concurrent() || src_queue_family == dst_queue_family {
+            self.build_with_queue_families(vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+        } else {
+            self.build_with_queue_families(src_queue_family, dst_queue_family)
+        }
+    }
+
+    fn build_with_queue_families(
+        &self,
+        src_queue_family: u32,
+        dst_queue_family: u32,
+    ) -> vk::BufferMemoryBarrier<'static> {
         vk::BufferMemoryBarrier::default()
-            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .src_queue_family_index(src_queue_family)
+            .dst_queue_family_index(dst_queue_family)
             .buffer(self.buffer)
             .src_access_mask(self.src_access)
             .dst_access_mask(self.dst_access)
@@ -88,3 +136,268 @@ pub fn submit_barriers(
         )
     }
 }
+
+struct VEBarrierGroup {
+    source_stage: vk::PipelineStageFlags,
+    destination_stage: vk::PipelineStageFlags,
+    memory_barriers: Vec<vk::MemoryBarrier<'static>>,
+    buffer_memory_barriers: Vec<vk::BufferMemoryBarrier<'static>>,
+    image_memory_barriers: Vec<vk::ImageMemoryBarrier<'static>>,
+}
+
+// collects barriers and records one vkCmdPipelineBarrier per stage pair on flush.
+// a second barrier for an image or buffer already in the batch starts a new segment,
+// so transitions of the same resource keep their order
+#[derive(Default)]
+pub struct VEBarrierBatch {
+    segments: Vec<Vec<VEBarrierGroup>>,
+    queued_images: HashSet<vk::Image>,
+    queued_buffers: HashSet<vk::Buffer>,
+    barrier_count: usize,
+}
+
+impl VEBarrierBatch {
+    pub fn new() -> VEBarrierBatch {
+        VEBarrierBatch::default()
+    }
+
+    pub fn add_memory_barrier(
+        &mut self,
+        source_stage: vk::PipelineStageFlags,
+        destination_stage: vk::PipelineStageFlags,
+        barrier: vk::MemoryBarrier<'static>,
+    ) {
+        self.get_group(source_stage, destination_stage)
+            .memory_barriers
+            .push(barrier);
+    }
+
+    pub fn add_buffer_barrier(
+        &mut self,
+        source_stage: vk::PipelineStageFlags,
+        destination_stage: vk::PipelineStageFlags,
+        barrier: vk::BufferMemoryBarrier<'static>,
+    ) {
+        if !self.queued_buffers.insert(barrier.buffer) {
+            self.start_segment();
+            self.queued_buffers.insert(barrier.buffer);
+        }
+        self.get_group(source_stage, destination_stage)
+            .buffer_memory_barriers
+            .push(barrier);
+    }
+
+    pub fn add_image_barrier(
+        &mut self,
+        source_stage: vk::PipelineStageFlags,
+        destination_stage: vk::PipelineStageFlags,
+        barrier: vk::ImageMemoryBarrier<'static>,
+    ) {
+        if !self.queued_images.insert(barrier.image) {
+            self.start_segment();
+            self.queued_images.insert(barrier.image);
+        }
+        self.get_group(source_stage, destination_stage)
+            .image_memory_barriers
+            .push(barrier);
+    }
+
+    // queues the barrier the image sync state requires before this use, if any, and records
+    // the use. the layout is kept, returns whether a barrier was queued
+    pub fn add_image_use(
+        &mut self,
+        image: &mut VEImage,
+        pass: Option<u32>,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+        is_write: bool,
+    ) -> bool {
+        let required = image.get_sync_state().get_required_barrier(stage, is_write);
+        if let Some((source_stage, source_access)) = required {
+            let barrier = VEImageMemoryBarrier {
+                image: image.handle,
+                aspect: image.get_aspect(),
+                old_layout: image.current_layout,
+                new_layout: image.current_layout,
+                src_access: source_access,
+                dst_access: access,
+            }
+            .build_with_queue_families(vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED);
+            self.add_image_barrier(source_stage, stage, barrier);
+        }
+        Self::record_use(image.get_sync_state_mut(), pass, stage, access, is_write);
+        required.is_some()
+    }
+
+    // same as add_image_use, the barrier covers the whole buffer
+    pub fn add_buffer_use(
+        &mut self,
+        buffer: &mut VEBuffer,
+        pass: Option<u32>,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+        is_write: bool,
+    ) -> bool {
+        let required = buffer
+            .get_sync_state()
+            .get_required_barrier(stage, is_write);
+        if let Some((source_stage, source_access)) = required {
+            let barrier = vk::BufferMemoryBarrier::default()
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .buffer(buffer.buffer)
+                .src_access_mask(source_access)
+                .dst_access_mask(access)
+                .offset(0)
+                .size(vk::WHOLE_SIZE);
+            self.add_buffer_barrier(source_stage, stage, barrier);
+        }
+        Self::record_use(buffer.get_sync_state_mut(), pass, stage, access, is_write);
+        required.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.barrier_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.barrier_count == 0
+    }
+
+    // returns the number of vkCmdPipelineBarrier calls recorded, the batch is empty afterwards
+    pub fn flush(&mut self, device: &VEDevice, command_buffer: &VECommandBuffer) -> usize {
+        let mut calls = 0;
+        for segment in self.segments.drain(..) {
+            for group in segment {
+                submit_barriers(
+                    device,
+                    command_buffer,
+                    group.source_stage,
+                    group.destination_stage,
+                    &group.memory_barriers,
+                    &group.buffer_memory_barriers,
+                    &group.image_memory_barriers,
+                );
+                calls += 1;
+            }
+        }
+        self.queued_images.clear();
+        self.queued_buffers.clear();
+        self.barrier_count = 0;
+        calls
+    }
+
+    fn record_use(
+        sync_state: &mut VESyncState,
+        pass: Option<u32>,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+        is_write: bool,
+    ) {
+        if is_write {
+            sync_state.record_write(pass, stage, access);
+        } else {
+            sync_state.record_read(stage);
+        }
+    }
+
+    fn start_segment(&mut self) {
+        self.segments.push(vec![]);
+        self.queued_images.clear();
+        self.queued_buffers.clear();
+    }
+
+    fn get_group(
+        &mut self,
+        source_stage: vk::PipelineStageFlags,
+        destination_stage: vk::PipelineStageFlags,
+    ) -> &mut VEBarrierGroup {
+        self.barrier_count += 1;
+        if self.segments.is_empty() {
+            self.segments.push(vec![]);
+        }
+        let last_segment = self.segments.len() - 1;
+        let segment = &mut self.segments[last_segment];
+        let index = match segment.iter().position(|group| {
+            group.source_stage == source_stage && group.destination_stage == destination_stage
+        }) {
+            Some(index) => index,
+            None => {
+                segment.push(VEBarrierGroup {
+                    source_stage,
+                    destination_stage,
+                    memory_barriers: vec![],
+                    buffer_memory_barriers: vec![],
+                    image_memory_barriers: vec![],
+                });
+                segment.len() - 1
+            }
+        };
+        &mut segment[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    fn get_barrier(image: u64) -> vk::ImageMemoryBarrier<'static> {
+        vk::ImageMemoryBarrier::default()
+            .image(vk::Image::from_raw(image))
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+    }
+
+    // what flush would record, one call per group
+    fn get_call_count(batch: &VEBarrierBatch) -> usize {
+        batch.segments.iter().map(|segment| segment.len()).sum()
+    }
+
+    #[test]
+    fn distinct_images_with_one_stage_pair_share_a_call() {
+        let mut batch = VEBarrierBatch::new();
+        for image in 1..=3 {
+            batch.add_image_barrier(
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                get_barrier(image),
+            );
+        }
+        assert_eq!(batch.len(), 3);
+        assert_eq!(get_call_count(&batch), 1);
+    }
+
+    #[test]
+    fn different_stage_pairs_get_their_own_call() {
+        let mut batch = VEBarrierBatch::new();
+        batch.add_image_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            get_barrier(1),
+        );
+        batch.add_image_barrier(
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            get_barrier(2),
+        );
+        assert_eq!(get_call_count(&batch), 2);
+    }
+
+    #[test]
+    fn same_image_twice_keeps_its_order() {
+        let mut batch = VEBarrierBatch::new();
+        batch.add_image_barrier(
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            get_barrier(1),
+        );
+        batch.add_image_barrier(
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            get_barrier(1),
+        );
+        assert_eq!(batch.segments.len(), 2);
+        assert_eq!(get_call_count(&batch), 2);
+    }
+}
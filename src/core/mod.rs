@@ -1,12 +1,19 @@
+pub mod bindless_texture_table;
 pub mod command_buffer;
 pub mod command_pool;
+pub mod descriptor_pool;
 pub mod descriptor_set;
 pub mod descriptor_set_layout;
 pub mod device;
+pub mod feature_report;
+pub mod fence;
 pub mod helpers;
 pub mod main_device_queue;
 pub mod memory_barrier;
 pub mod memory_properties;
+pub mod per_frame_descriptor_set;
+pub mod push_descriptor_template;
 pub mod semaphore;
 pub mod shader_module;
+pub mod sync_state;
 pub mod toolkit;
@@ -0,0 +1,45 @@
+use crate::core::descriptor_set::{VEDescriptorSet, VEDescriptorSetError};
+use crate::core::descriptor_set_layout::VEDescriptorSetLayoutError;
+
+// one set per frame in flight, so the one the GPU still reads is never updated
+pub struct VEPerFrameDescriptorSet {
+    sets: Vec<VEDescriptorSet>,
+    current_frame: usize,
+}
+
+impl VEPerFrameDescriptorSet {
+    pub fn new(
+        sets: Vec<VEDescriptorSet>,
+    ) -> Result<VEPerFrameDescriptorSet, VEDescriptorSetLayoutError> {
+        if sets.is_empty() {
+            return Err(VEDescriptorSetLayoutError::NoFramesInFlight);
+        }
+        Ok(VEPerFrameDescriptorSet {
+            sets,
+            current_frame: 0,
+        })
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.sets.len()
+    }
+
+    pub fn set_frame_index(&mut self, frame_index: usize) {
+        self.current_frame = frame_index % self.sets.len();
+    }
+
+    pub fn advance(&mut self) {
+        self.current_frame = (self.current_frame + 1) % self.sets.len();
+    }
+
+    pub fn current(&self) -> &VEDescriptorSet {
+        &self.sets[self.current_frame]
+    }
+
+    pub fn write_current<F>(&self, writer: F) -> Result<(), VEDescriptorSetError>
+    where
+        F: FnOnce(&VEDescriptorSet) -> Result<(), VEDescriptorSetError>,
+    {
+        writer(self.current())
+    }
+}
@@ -0,0 +1,318 @@
+use crate::buffer::buffer::VEBuffer;
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::descriptor_set_layout::VEDescriptorSetLayout;
+use crate::core::device::VEDevice;
+use crate::image::sampler::VESampler;
+use ash::vk;
+use std::ffi::c_void;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEPushDescriptorTemplateError {
+    #[error("push descriptors need VK_KHR_push_descriptor")]
+    PushDescriptorNotSupported,
+
+    #[error("layout bindings are unknown, create the layout with VEDescriptorSetLayout::new_push")]
+    UnknownLayoutBindings,
+
+    #[error("expected {expected} descriptors, one per layout binding, got {found}")]
+    DescriptorCountMismatch { expected: usize, found: usize },
+
+    #[error("template creation failed")]
+    TemplateCreationFailed(#[source] vk::Result),
+}
+
+// one entry of the flat array pushed per draw, image info for samplers and images,
+// buffer info for uniform and storage buffers
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union VEPushDescriptorInfo {
+    pub image: vk::DescriptorImageInfo,
+    pub buffer: vk::DescriptorBufferInfo,
+}
+
+impl VEPushDescriptorInfo {
+    pub fn buffer(buffer: &VEBuffer) -> VEPushDescriptorInfo {
+        VEPushDescriptorInfo {
+            buffer: vk::DescriptorBufferInfo::default()
+                .buffer(buffer.buffer)
+                .offset(0)
+                .range(buffer.size),
+        }
+    }
+
+    // sampler is ignored for sampled and storage image bindings
+    pub fn image(
+        view: vk::ImageView,
+        sampler: Option<&VESampler>,
+        layout: vk::ImageLayout,
+    ) -> VEPushDescriptorInfo {
+        VEPushDescriptorInfo {
+            image: vk::DescriptorImageInfo::default()
+                .image_view(view)
+                .sampler(sampler.map_or(vk::Sampler::null(), |s| s.handle))
+                .image_layout(layout),
+        }
+    }
+
+    pub fn sampler(sampler: &VESampler) -> VEPushDescriptorInfo {
+        VEPushDescriptorInfo {
+            image: vk::DescriptorImageInfo::default().sampler(sampler.handle),
+        }
+    }
+}
+
+fn is_buffer_type(typ: vk::DescriptorType) -> bool {
+    matches!(
+        typ,
+        vk::DescriptorType::UNIFORM_BUFFER | vk::DescriptorType::STORAGE_BUFFER
+    )
+}
+
+// the writes the fallback path pushes, one per binding, pointing into infos
+fn get_push_writes<'a>(
+    binding_types: &[(u32, vk::DescriptorType)],
+    infos: &'a [VEPushDescriptorInfo],
+) -> Vec<vk::WriteDescriptorSet<'a>> {
+    binding_types
+        .iter()
+        .zip(infos)
+        .map(|((binding, typ), info)| {
+            let write = vk::WriteDescriptorSet::default()
+                .dst_binding(*binding)
+                .descriptor_type(*typ);
+            if is_buffer_type(*typ) {
+                write.buffer_info(std::slice::from_ref(unsafe { &info.buffer }))
+            } else {
+                write.image_info(std::slice::from_ref(unsafe { &info.image }))
+            }
+        })
+        .collect()
+}
+
+// pushes every binding of a push layout from one flat array instead of building writes per
+// draw. uses a descriptor update template when the device has them (core since 1.1) and
+// falls back to vkCmdPushDescriptorSetKHR with writes otherwise
+pub struct VEPushDescriptorTemplate {
+    device: Arc<VEDevice>,
+    template: Option<vk::DescriptorUpdateTemplate>,
+    pipeline_layout: vk::PipelineLayout,
+    bind_point: vk::PipelineBindPoint,
+    set: u32,
+    binding_types: Vec<(u32, vk::DescriptorType)>,
+}
+
+impl VEPushDescriptorTemplate {
+    // set is the index of the push layout in the pipeline layout, see get_pipeline_layout
+    // on VERenderStage and VEComputeStage
+    pub fn new(
+        device: Arc<VEDevice>,
+        layout: &VEDescriptorSetLayout,
+        pipeline_layout: vk::PipelineLayout,
+        bind_point: vk::PipelineBindPoint,
+        set: u32,
+    ) -> Result<VEPushDescriptorTemplate, VEPushDescriptorTemplateError> {
+        if device.push_descriptor.is_none() {
+            return Err(VEPushDescriptorTemplateError::PushDescriptorNotSupported);
+        }
+        let binding_types = layout
+            .get_binding_types()
+            .ok_or(VEPushDescriptorTemplateError::UnknownLayoutBindings)?
+            .to_vec();
+
+        let template = if device.supports_descriptor_update_templates() {
+            let stride = size_of::<VEPushDescriptorInfo>();
+            let entries: Vec<vk::DescriptorUpdateTemplateEntry> = binding_types
+                .iter()
+                .enumerate()
+                .map(|(i, (binding, typ))| {
+                    vk::DescriptorUpdateTemplateEntry::default()
+                        .dst_binding(*binding)
+                        .dst_array_element(0)
+                        .descriptor_count(1)
+                        .descriptor_type(*typ)
+                        .offset(i * stride)
+                        .stride(stride)
+                })
+                .collect();
+            let info = vk::DescriptorUpdateTemplateCreateInfo::default()
+                .descriptor_update_entries(&entries)
+                .template_type(vk::DescriptorUpdateTemplateType::PUSH_DESCRIPTORS_KHR)
+                .descriptor_set_layout(layout.layout)
+                .pipeline_bind_point(bind_point)
+                .pipeline_layout(pipeline_layout)
+                .set(set);
+            Some(unsafe {
+                device
+                    .device
+                    .create_descriptor_update_template(&info, None)
+                    .map_err(VEPushDescriptorTemplateError::TemplateCreationFailed)?
+            })
+        } else {
+            None
+        };
+
+        Ok(VEPushDescriptorTemplate {
+            device,
+            template,
+            pipeline_layout,
+            bind_point,
+            set,
+            binding_types,
+        })
+    }
+
+    pub fn uses_template(&self) -> bool {
+        self.template.is_some()
+    }
+
+    // one info per binding, in the order the layout fields were declared
+    pub fn push(
+        &self,
+        command_buffer: &VECommandBuffer,
+        infos: &[VEPushDescriptorInfo],
+    ) -> Result<(), VEPushDescriptorTemplateError> {
+        if infos.len() != self.binding_types.len() {
+            return Err(VEPushDescriptorTemplateError::DescriptorCountMismatch {
+                expected: self.binding_types.len(),
+                found: infos.len(),
+            });
+        }
+        let loader = self
+            .device
+            .push_descriptor
+            .as_ref()
+            .ok_or(VEPushDescriptorTemplateError::PushDescriptorNotSupported)?;
+
+        if let Some(template) = self.template {
+            unsafe {
+                loader.cmd_push_descriptor_set_with_template(
+                    command_buffer.handle,
+                    template,
+                    self.pipeline_layout,
+                    self.set,
+                    infos.as_ptr() as *const c_void,
+                );
+            }
+            return Ok(());
+        }
+
+        let writes = get_push_writes(&self.binding_types, infos);
+        unsafe {
+            loader.cmd_push_descriptor_set(
+                command_buffer.handle,
+                self.bind_point,
+                self.pipeline_layout,
+                self.set,
+                &writes,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VEPushDescriptorTemplate {
+    fn drop(&mut self) {
+        if let Some(template) = self.template {
+            unsafe {
+                self.device
+                    .device
+                    .destroy_descriptor_update_template(template, None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    fn get_binding_types() -> Vec<(u32, vk::DescriptorType)> {
+        vec![
+            (0, vk::DescriptorType::UNIFORM_BUFFER),
+            (1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+            (2, vk::DescriptorType::STORAGE_BUFFER),
+            (3, vk::DescriptorType::SAMPLED_IMAGE),
+        ]
+    }
+
+    fn get_infos(draw: u64) -> Vec<VEPushDescriptorInfo> {
+        get_binding_types()
+            .iter()
+            .map(|(_, typ)| {
+                if is_buffer_type(*typ) {
+                    VEPushDescriptorInfo {
+                        buffer: vk::DescriptorBufferInfo::default().offset(draw).range(256),
+                    }
+                } else {
+                    VEPushDescriptorInfo {
+                        image: vk::DescriptorImageInfo::default()
+                            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn template_stride_matches_largest_info() {
+        assert_eq!(
+            size_of::<VEPushDescriptorInfo>(),
+            size_of::<vk::DescriptorImageInfo>().max(size_of::<vk::DescriptorBufferInfo>())
+        );
+    }
+
+    #[test]
+    fn push_writes_point_into_infos() {
+        let binding_types = get_binding_types();
+        let infos = get_infos(7);
+        let writes = get_push_writes(&binding_types, &infos);
+        assert_eq!(writes.len(), binding_types.len());
+        for ((write, (binding, typ)), info) in writes.iter().zip(&binding_types).zip(&infos) {
+            assert_eq!(write.dst_binding, *binding);
+            assert_eq!(write.descriptor_type, *typ);
+            assert_eq!(write.descriptor_count, 1);
+            if is_buffer_type(*typ) {
+                assert_eq!(write.p_buffer_info, unsafe { &info.buffer } as *const _);
+                assert!(write.p_image_info.is_null());
+            } else {
+                assert_eq!(write.p_image_info, unsafe { &info.image } as *const _);
+                assert!(write.p_buffer_info.is_null());
+            }
+        }
+    }
+
+    // cargo test --release push_descriptor_cpu_cost -- --ignored --nocapture
+    // compares the per-draw cpu work of building writes with filling the flat template array
+    #[test]
+    #[ignore]
+    fn push_descriptor_cpu_cost() {
+        const DRAWS: u64 = 1_000_000;
+        let binding_types = get_binding_types();
+
+        let start = Instant::now();
+        for draw in 0..DRAWS {
+            let infos = black_box(get_infos(draw));
+            black_box(get_push_writes(&binding_types, &infos));
+        }
+        let writes = start.elapsed();
+
+        let start = Instant::now();
+        for draw in 0..DRAWS {
+            let infos = black_box(get_infos(draw));
+            black_box(infos.as_ptr() as *const c_void);
+        }
+        let template = start.elapsed();
+
+        println!(
+            "{DRAWS} draws, writes: {:?} ({:?}/draw), template: {:?} ({:?}/draw)",
+            writes,
+            writes / DRAWS as u32,
+            template,
+            template / DRAWS as u32
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::core::device::VEDevice;
+use crate::core::device::{VEDevice, VEDeviceError};
 use ash::vk;
 use std::sync::Arc;
 use thiserror::Error;
@@ -45,6 +45,10 @@ impl VESemaphore {
 
         Ok(())
     }
+
+    pub fn set_name(&self, name: &str) -> Result<(), VEDeviceError> {
+        self.device.set_debug_name(self.handle, name)
+    }
 }
 
 impl Drop for VESemaphore {
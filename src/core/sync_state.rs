@@ -0,0 +1,68 @@
+use ash::vk;
+
+// last write and the reads since then for one resource, the render graph updates it while
+// scheduling passes and uses it to emit only the barriers that are actually needed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VESyncState {
+    last_writer: Option<u32>,
+    last_write_stage: vk::PipelineStageFlags,
+    last_write_access: vk::AccessFlags,
+    last_read_stages: vk::PipelineStageFlags,
+}
+
+impl VESyncState {
+    // index of the pass that wrote the resource last, None for writes outside the graph
+    pub fn get_last_writer(&self) -> Option<u32> {
+        self.last_writer
+    }
+
+    pub fn get_last_write_stage(&self) -> vk::PipelineStageFlags {
+        self.last_write_stage
+    }
+
+    pub fn get_last_write_access(&self) -> vk::AccessFlags {
+        self.last_write_access
+    }
+
+    pub fn get_last_read_stages(&self) -> vk::PipelineStageFlags {
+        self.last_read_stages
+    }
+
+    pub(crate) fn record_write(
+        &mut self,
+        pass: Option<u32>,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+    ) {
+        self.last_writer = pass;
+        self.last_write_stage = stage;
+        self.last_write_access = access;
+        self.last_read_stages = vk::PipelineStageFlags::empty();
+    }
+
+    pub(crate) fn record_read(&mut self, stage: vk::PipelineStageFlags) {
+        self.last_read_stages |= stage;
+    }
+
+    // source stage and access a barrier before the given use has to wait on, None if no barrier is needed.
+    // reads after reads need nothing, a read in a stage that already waited on the last write is covered,
+    // writes after reads only need an execution dependency
+    pub(crate) fn get_required_barrier(
+        &self,
+        stage: vk::PipelineStageFlags,
+        is_write: bool,
+    ) -> Option<(vk::PipelineStageFlags, vk::AccessFlags)> {
+        if is_write {
+            let source_stage = self.last_write_stage | self.last_read_stages;
+            if source_stage.is_empty() {
+                None
+            } else {
+                Some((source_stage, self.last_write_access))
+            }
+        } else if self.last_write_stage.is_empty() || self.last_read_stages.contains(stage) {
+            None
+        } else {
+            Some((self.last_write_stage, self.last_write_access))
+        }
+    }
+}
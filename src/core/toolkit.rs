@@ -1,26 +1,39 @@
-use crate::buffer::buffer::{VEBuffer, VEBufferError, VEBufferUsage};
+use crate::buffer::buffer::{VEBuffer, VEBufferError, VEBufferOptions, VEBufferUsage, VEIndexType};
+use crate::buffer::storage_buffer::{VEStorageBuffer, VEStorageBufferElement};
 use crate::compute::compute_stage::{VEComputeStage, VEComputeStageError};
+use crate::core::bindless_texture_table::{VEBindlessTextureTable, VEBindlessTextureTableError};
 use crate::core::command_buffer::{VECommandBuffer, VECommandBufferError};
 use crate::core::command_pool::{VECommandPool, VECommandPoolError};
+use crate::core::descriptor_pool::{VEDescriptorPool, VEDescriptorPoolError};
 use crate::core::descriptor_set_layout::{
-    VEDescriptorSetLayout, VEDescriptorSetLayoutError, VEDescriptorSetLayoutField,
+    VEDescriptorSetFieldStage, VEDescriptorSetLayout, VEDescriptorSetLayoutError,
+    VEDescriptorSetLayoutField,
 };
 use crate::core::device::{VEDevice, VEDeviceError};
+use crate::core::fence::{VEFence, VEFenceError};
 use crate::core::main_device_queue::VEMainDeviceQueue;
 use crate::core::memory_properties::VEMemoryProperties;
+use crate::core::push_descriptor_template::{
+    VEPushDescriptorTemplate, VEPushDescriptorTemplateError,
+};
 use crate::core::semaphore::{VESemaphore, VESemaphoreError};
 use crate::core::shader_module::{VEShaderModule, VEShaderModuleError, VEShaderModuleType};
 use crate::graphics::attachment::VEAttachment;
+use crate::graphics::depth_target::{VEDepthTarget, VEDepthTargetError};
+use crate::graphics::draw_batcher::VEDrawBatcher;
+use crate::graphics::fullscreen_pass::{VEFullscreenPass, VEFullscreenPassError};
 use crate::graphics::render_stage::{
-    VECullMode, VEPrimitiveTopology, VERenderStage, VERenderStageError,
+    VECullMode, VEPrimitiveTopology, VERenderStage, VERenderStageError, VERenderStageOptions,
 };
 use crate::graphics::vertex_attributes::VertexAttribFormat;
 use crate::graphics::vertex_buffer::{VEVertexBuffer, VEVertexBufferError};
 use crate::image::filtering::VEFiltering;
-use crate::image::image::{VEImage, VEImageError, VEImageUsage};
+use crate::image::image::{VEImage, VEImageError, VEImageOptions, VEImageUsage};
 use crate::image::image_format::VEImageFormat;
-use crate::image::sampler::{VESampler, VESamplerAddressMode, VESamplerError};
-use crate::memory::memory_manager::VEMemoryManager;
+use crate::image::sampler::{VESampler, VESamplerAddressMode, VESamplerDesc, VESamplerError};
+use crate::memory::memory_manager::{
+    VEAllocationInfo, VEMemoryCategoryStats, VEMemoryManager, VEMemoryManagerError, VEMemoryStats,
+};
 use crate::window::swapchain::{VESwapchain, VESwapchainError};
 use crate::window::window::{AppCallback, VEWindow, VEWindowError};
 use ash::vk;
@@ -46,6 +59,12 @@ pub enum VEToolkitError {
 
     #[error("callbacks locking failed")]
     CallbacksLockingFailed,
+
+    #[error("memory manager locking failed")]
+    MemoryManagerLockingFailed,
+
+    #[error("memory manager error")]
+    MemoryManagerError(#[from] VEMemoryManagerError),
 }
 
 pub trait App {
@@ -190,6 +209,133 @@ impl VEToolkit {
         })
     }
 
+    pub fn get_memory_stats(&self, verbose: bool) -> Result<VEMemoryStats, VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .get_stats(verbose))
+    }
+
+    // call once the frame fence signaled and every transient buffer of that frame is dropped
+    pub fn reset_transient_memory(&self) -> Result<(), VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .reset_transient_arena()?)
+    }
+
+    pub fn get_device_local_usage(&self) -> Result<(u64, u64), VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .device_local_usage())
+    }
+
+    pub fn get_memory_category_stats(&self) -> Result<Vec<VEMemoryCategoryStats>, VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .get_category_stats())
+    }
+
+    pub fn set_buffer_memory_category(
+        &self,
+        buffer: &VEBuffer,
+        category: &'static str,
+    ) -> Result<bool, VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .set_allocation_category(buffer.get_allocation(), category))
+    }
+
+    // false for images without an own allocation, such as swapchain images
+    pub fn set_image_memory_category(
+        &self,
+        image: &VEImage,
+        category: &'static str,
+    ) -> Result<bool, VEToolkitError> {
+        let Some(allocation) = image.get_allocation() else {
+            return Ok(false);
+        };
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .set_allocation_category(allocation, category))
+    }
+
+    // see VEImage::update_region
+    pub fn update_image_region(
+        &self,
+        image: &mut VEImage,
+        offset: vk::Offset3D,
+        extent: vk::Extent3D,
+        mip: u32,
+        layer: u32,
+        data: &[u8],
+    ) -> Result<(), VEImageError> {
+        image.update_region(
+            self.memory_manager.clone(),
+            offset,
+            extent,
+            mip,
+            layer,
+            data,
+        )
+    }
+
+    // see VEMemoryManager::set_priority
+    pub fn set_buffer_memory_priority(
+        &self,
+        buffer: &VEBuffer,
+        priority: f32,
+    ) -> Result<(), VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .set_priority(buffer.get_allocation(), priority)?)
+    }
+
+    pub fn set_image_memory_priority(
+        &self,
+        image: &VEImage,
+        priority: f32,
+    ) -> Result<(), VEToolkitError> {
+        let Some(allocation) = image.get_allocation() else {
+            return Ok(());
+        };
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .set_priority(allocation, priority)?)
+    }
+
+    pub fn get_live_allocations(&self) -> Result<Vec<VEAllocationInfo>, VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .iter_allocations()
+            .collect())
+    }
+
+    // see VEMemoryManager::free_all, resources bound to the type may only be dropped afterwards
+    pub fn free_all_memory(&self, memory_type_index: u32) -> Result<usize, VEToolkitError> {
+        Ok(self
+            .memory_manager
+            .lock()
+            .map_err(|_| VEToolkitError::MemoryManagerLockingFailed)?
+            .free_all(memory_type_index))
+    }
+
     pub fn create_command_buffer(&self) -> Result<VECommandBuffer, VECommandBufferError> {
         VECommandBuffer::new(self.device.clone(), self.command_pool.clone())
     }
@@ -209,6 +355,45 @@ impl VEToolkit {
         VEDescriptorSetLayout::new(self.device.clone(), fields)
     }
 
+    pub fn create_push_descriptor_set_layout(
+        &self,
+        fields: &[VEDescriptorSetLayoutField],
+    ) -> Result<VEDescriptorSetLayout, VEDescriptorSetLayoutError> {
+        VEDescriptorSetLayout::new_push(self.device.clone(), fields)
+    }
+
+    pub fn create_push_descriptor_template(
+        &self,
+        layout: &VEDescriptorSetLayout,
+        pipeline_layout: vk::PipelineLayout,
+        bind_point: vk::PipelineBindPoint,
+        set: u32,
+    ) -> Result<VEPushDescriptorTemplate, VEPushDescriptorTemplateError> {
+        VEPushDescriptorTemplate::new(
+            self.device.clone(),
+            layout,
+            pipeline_layout,
+            bind_point,
+            set,
+        )
+    }
+
+    pub fn create_descriptor_pool(
+        &self,
+        layouts: &[(&VEDescriptorSetLayout, u32)],
+    ) -> Result<VEDescriptorPool, VEDescriptorPoolError> {
+        VEDescriptorPool::from_layouts(self.device.clone(), layouts)
+    }
+
+    pub fn create_bindless_texture_table(
+        &self,
+        binding: u32,
+        size: u32,
+        stage: VEDescriptorSetFieldStage,
+    ) -> Result<VEBindlessTextureTable, VEBindlessTextureTableError> {
+        VEBindlessTextureTable::new(self.device.clone(), binding, size, stage)
+    }
+
     pub fn create_image_full(
         &self,
         width: u32,
@@ -232,6 +417,60 @@ impl VEToolkit {
         )
     }
 
+    pub fn create_image_with_options(
+        &self,
+        extent: vk::Extent3D,
+        format: vk::Format,
+        options: VEImageOptions,
+    ) -> Result<VEImage, VEImageError> {
+        VEImage::new_with_options(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            extent,
+            format,
+            options,
+        )
+    }
+
+    pub fn read_image_pixel(
+        &self,
+        image: &mut VEImage,
+        x: u32,
+        y: u32,
+        mip: u32,
+        layer: u32,
+    ) -> Result<Vec<u8>, VEImageError> {
+        image.read_pixel(self.memory_manager.clone(), x, y, mip, layer)
+    }
+
+    pub fn recreate_image(
+        &self,
+        image: &mut VEImage,
+        width: u32,
+        height: u32,
+    ) -> Result<(), VEImageError> {
+        image.recreate(self.memory_manager.clone(), width, height)
+    }
+
+    // every holder of the Arc sees the new image once the lock is released
+    pub fn recreate_shared_image(
+        &self,
+        image: &Arc<Mutex<VEImage>>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), VEImageError> {
+        image
+            .lock()
+            .map_err(|_| VEImageError::ImageLockingFailed)?
+            .recreate(self.memory_manager.clone(), width, height)
+    }
+
+    pub fn read_linear_image_to_cpu(&self, image: &VEImage) -> Result<Vec<u8>, VEImageError> {
+        image.read_linear_to_cpu(self.memory_manager.clone())
+    }
+
     pub fn create_image_from_data(
         &self,
         data: &[u8],
@@ -273,6 +512,38 @@ impl VEToolkit {
         )
     }
 
+    #[cfg(feature = "hdr")]
+    pub fn create_image_from_hdr(
+        &self,
+        path: &str,
+        usages: &[VEImageUsage],
+    ) -> Result<VEImage, VEImageError> {
+        VEImage::from_hdr(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            path,
+            usages,
+        )
+    }
+
+    #[cfg(feature = "exr")]
+    pub fn create_image_from_exr(
+        &self,
+        path: &str,
+        usages: &[VEImageUsage],
+    ) -> Result<VEImage, VEImageError> {
+        VEImage::from_exr(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            path,
+            usages,
+        )
+    }
+
     pub fn create_sampler(
         &self,
         sampler_address_mode: VESamplerAddressMode,
@@ -291,10 +562,46 @@ impl VEToolkit {
         )
     }
 
+    pub fn create_sampler_with_lod_bias(
+        &self,
+        sampler_address_mode: VESamplerAddressMode,
+
+        min_filter: VEFiltering,
+        mag_filter: VEFiltering,
+
+        anisotropy: bool,
+
+        mip_lod_bias: f32,
+    ) -> Result<VESampler, VESamplerError> {
+        VESampler::new_with_lod_bias(
+            self.device.clone(),
+            sampler_address_mode,
+            min_filter,
+            mag_filter,
+            anisotropy,
+            mip_lod_bias,
+        )
+    }
+
+    pub fn create_sampler_from_desc(
+        &self,
+        desc: &VESamplerDesc,
+    ) -> Result<VESampler, VESamplerError> {
+        VESampler::from_desc(self.device.clone(), desc)
+    }
+
+    pub fn create_upscaling_sampler(&self, render_scale: f32) -> Result<VESampler, VESamplerError> {
+        VESampler::upscaling(self.device.clone(), render_scale)
+    }
+
     pub fn create_semaphore(&self) -> Result<VESemaphore, VESemaphoreError> {
         VESemaphore::new(self.device.clone())
     }
 
+    pub fn create_fence(&self, signaled: bool) -> Result<VEFence, VEFenceError> {
+        VEFence::new(self.device.clone(), signaled)
+    }
+
     pub fn create_buffer(
         &self,
         usage: &[VEBufferUsage],
@@ -312,6 +619,77 @@ impl VEToolkit {
         )
     }
 
+    pub fn create_transient_buffer(
+        &self,
+        usage: &[VEBufferUsage],
+        size: vk::DeviceSize,
+        memory_properties: Option<VEMemoryProperties>,
+    ) -> Result<VEBuffer, VEBufferError> {
+        VEBuffer::new_transient(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            usage,
+            size,
+            memory_properties,
+        )
+    }
+
+    pub fn create_buffer_concurrent(
+        &self,
+        usage: &[VEBufferUsage],
+        size: vk::DeviceSize,
+        memory_properties: Option<VEMemoryProperties>,
+        queue_families: &[u32],
+    ) -> Result<VEBuffer, VEBufferError> {
+        VEBuffer::new_with_options(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            usage,
+            size,
+            VEBufferOptions {
+                memory_properties,
+                queue_families,
+                ..VEBufferOptions::default()
+            },
+        )
+    }
+
+    pub fn create_storage_buffer<T: VEStorageBufferElement>(
+        &self,
+        len: usize,
+        memory_properties: Option<VEMemoryProperties>,
+    ) -> Result<VEStorageBuffer<T>, VEBufferError> {
+        VEStorageBuffer::new(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            len,
+            memory_properties,
+        )
+    }
+
+    pub fn create_buffer_from_vertex_and_index_data(
+        &self,
+        vertex_data: &[u8],
+        index_data: &[u8],
+        index_type: VEIndexType,
+    ) -> Result<(VEBuffer, u64, u64), VEBufferError> {
+        VEBuffer::from_vertex_and_index_data(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            vertex_data,
+            index_data,
+            index_type,
+        )
+    }
+
     pub fn create_vertex_buffer(&self, buffer: VEBuffer, vertex_count: u32) -> VEVertexBuffer {
         VEVertexBuffer::new(self.device.clone(), buffer, vertex_count)
     }
@@ -346,6 +724,47 @@ impl VEToolkit {
         )
     }
 
+    pub fn create_draw_batcher(&self) -> VEDrawBatcher {
+        VEDrawBatcher::new(self.device.clone())
+    }
+
+    pub fn create_depth_target(
+        &self,
+        width: u32,
+        height: u32,
+        for_sampling: bool,
+    ) -> Result<VEDepthTarget, VEDepthTargetError> {
+        VEDepthTarget::new(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            self.memory_manager.clone(),
+            width,
+            height,
+            for_sampling,
+        )
+    }
+
+    pub fn create_fullscreen_pass(
+        &self,
+        viewport_width: u32,
+        viewport_height: u32,
+        attachments: &[&VEAttachment],
+        set_layouts: &[&VEDescriptorSetLayout],
+        fragment_shader: &VEShaderModule,
+        options: &VERenderStageOptions,
+    ) -> Result<VEFullscreenPass, VEFullscreenPassError> {
+        VEFullscreenPass::new(
+            self.device.clone(),
+            viewport_width,
+            viewport_height,
+            attachments,
+            set_layouts,
+            fragment_shader,
+            options,
+        )
+    }
+
     pub fn create_compute_stage(
         &self,
         set_layouts: &[&VEDescriptorSetLayout],
@@ -384,4 +803,24 @@ impl VEToolkit {
             cull_mode,
         )
     }
+
+    pub fn create_render_stage_with_options(
+        &self,
+        viewport: vk::Extent2D,
+        attachments: &[&VEAttachment],
+        set_layouts: &[&VEDescriptorSetLayout],
+        vertex_shader: &VEShaderModule,
+        fragment_shader: &VEShaderModule,
+        options: &VERenderStageOptions,
+    ) -> Result<VERenderStage, VERenderStageError> {
+        VERenderStage::new_with_options(
+            self.device.clone(),
+            viewport,
+            attachments,
+            set_layouts,
+            vertex_shader,
+            fragment_shader,
+            options,
+        )
+    }
 }
@@ -6,18 +6,64 @@ use thiserror::Error;
 pub enum VEAttachmentError {
     #[error("image view not found")]
     ImageViewNotFound,
+
+    #[error("stencil requested for an image without a stencil aspect")]
+    NoStencilAspect,
+
+    #[error("attachment aspect is not part of the image")]
+    AspectNotInImage,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AttachmentBlending {
+    // blending disabled, same as no blending on the attachment
+    Opaque,
     Additive,
     Alpha,
 }
 
+impl AttachmentBlending {
+    pub fn to_blend_state(&self) -> vk::PipelineColorBlendAttachmentState {
+        let state = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .alpha_blend_op(vk::BlendOp::ADD);
+        match self {
+            AttachmentBlending::Opaque => state
+                .src_color_blend_factor(vk::BlendFactor::ONE)
+                .dst_color_blend_factor(vk::BlendFactor::ZERO)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+                .blend_enable(false),
+            AttachmentBlending::Additive => state
+                .src_color_blend_factor(vk::BlendFactor::ONE)
+                .dst_color_blend_factor(vk::BlendFactor::ONE)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+                .blend_enable(true),
+            AttachmentBlending::Alpha => state
+                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .src_alpha_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .blend_enable(true),
+        }
+    }
+}
+
+// holds only the raw view handle, never a reference to the image, so it cannot keep the image
+// alive. the view belongs to the image, whoever owns the VEImage (for example VEDepthTarget)
+// decides its lifetime, and framebuffers built from the attachment must be dropped first.
+// transient msaa or depth images are released by dropping their owner once the command
+// buffers using the render pass finished, dropping the attachment alone releases nothing
 pub struct VEAttachment {
     pub image_view: vk::ImageView,
     pub is_depth: bool,
+    pub aspect: vk::ImageAspectFlags,
     pub description: vk::AttachmentDescription,
     pub blending: Option<AttachmentBlending>,
+    // channels the pipeline writes, empty keeps the attachment bound but untouched
+    pub color_write_mask: vk::ColorComponentFlags,
     pub clear: Option<vk::ClearValue>,
 }
 
@@ -28,17 +74,64 @@ impl VEAttachment {
         blending: Option<AttachmentBlending>,
         clear: Option<vk::ClearValue>,
     ) -> Result<VEAttachment, VEAttachmentError> {
+        // depth and stencil share the load op of the combined clear value
+        if let Some(value) = clear {
+            let stencil = unsafe { value.depth_stencil.stencil };
+            if image.is_depth() && stencil != 0 && !image.has_stencil() {
+                return Err(VEAttachmentError::NoStencilAspect);
+            }
+        }
+        Self::from_image_with_aspect(image, view, image.get_aspect(), blending, clear)
+    }
+
+    // for depth-stencil images, aspects left out are neither loaded nor stored,
+    // a stencil-only pass keeps depth DONT_CARE
+    pub fn from_image_with_aspect(
+        image: &VEImage,
+        view: vk::ImageView,
+        aspect: vk::ImageAspectFlags,
+        blending: Option<AttachmentBlending>,
+        clear: Option<vk::ClearValue>,
+    ) -> Result<VEAttachment, VEAttachmentError> {
+        if aspect.contains(vk::ImageAspectFlags::STENCIL) && !image.has_stencil() {
+            return Err(VEAttachmentError::NoStencilAspect);
+        }
+        if aspect.is_empty() || !image.get_aspect().contains(aspect) {
+            return Err(VEAttachmentError::AspectNotInImage);
+        }
+
+        let load_op = if clear.is_some() {
+            vk::AttachmentLoadOp::CLEAR
+        } else {
+            vk::AttachmentLoadOp::LOAD
+        };
+        let targets_main =
+            aspect.intersects(vk::ImageAspectFlags::COLOR | vk::ImageAspectFlags::DEPTH);
+        let targets_stencil = aspect.contains(vk::ImageAspectFlags::STENCIL);
+
         let description = vk::AttachmentDescription::default()
             .format(image.format)
             .samples(vk::SampleCountFlags::TYPE_1)
-            .load_op(if clear.is_some() {
-                vk::AttachmentLoadOp::CLEAR
+            .load_op(if targets_main {
+                load_op
+            } else {
+                vk::AttachmentLoadOp::DONT_CARE
+            })
+            .store_op(if targets_main {
+                vk::AttachmentStoreOp::STORE
+            } else {
+                vk::AttachmentStoreOp::DONT_CARE
+            })
+            .stencil_load_op(if targets_stencil {
+                load_op
+            } else {
+                vk::AttachmentLoadOp::DONT_CARE
+            })
+            .stencil_store_op(if targets_stencil {
+                vk::AttachmentStoreOp::STORE
             } else {
-                vk::AttachmentLoadOp::LOAD
+                vk::AttachmentStoreOp::DONT_CARE
             })
-            .store_op(vk::AttachmentStoreOp::STORE)
-            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
             .initial_layout(image.current_layout)
             .final_layout(if image.is_depth() {
                 vk::ImageLayout::GENERAL // TODO verify, its the final layout
@@ -46,12 +139,133 @@ impl VEAttachment {
                 vk::ImageLayout::GENERAL
             });
 
-        Ok(VEAttachment {
+        let attachment = VEAttachment {
             image_view: view,
             is_depth: image.is_depth(),
+            aspect,
             description,
             blending,
+            color_write_mask: vk::ColorComponentFlags::RGBA,
             clear,
-        })
+        };
+        attachment.warn_on_undefined_load();
+        Ok(attachment)
+    }
+
+    // replaces image.current_layout as the layout the render pass starts from, UNDEFINED lets
+    // the driver discard the previous contents of targets that are fully overwritten
+    pub fn with_initial_layout(mut self, layout: vk::ImageLayout) -> VEAttachment {
+        self.description.initial_layout = layout;
+        self.warn_on_undefined_load();
+        self
+    }
+
+    fn warn_on_undefined_load(&self) {
+        let loads = self.description.load_op == vk::AttachmentLoadOp::LOAD
+            || self.description.stencil_load_op == vk::AttachmentLoadOp::LOAD;
+        if self.description.initial_layout == vk::ImageLayout::UNDEFINED && loads {
+            tracing::warn!(
+                format = ?self.description.format,
+                "attachment loads from an UNDEFINED initial layout, its contents are garbage"
+            );
+        }
+    }
+
+    // DONT_CARE skips writing the results back to memory, for attachments only read inside
+    // the pass such as depth that is never sampled. stencil keeps its own store op
+    pub fn with_store_op(mut self, store_op: vk::AttachmentStoreOp) -> VEAttachment {
+        if self
+            .aspect
+            .intersects(vk::ImageAspectFlags::COLOR | vk::ImageAspectFlags::DEPTH)
+        {
+            self.description.store_op = store_op;
+        }
+        self
+    }
+
+    // for example RG only for a motion vector target, ignored for depth attachments
+    pub fn with_color_write_mask(mut self, mask: vk::ColorComponentFlags) -> VEAttachment {
+        self.color_write_mask = mask;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factors(
+        state: &vk::PipelineColorBlendAttachmentState,
+    ) -> (
+        vk::BlendFactor,
+        vk::BlendFactor,
+        vk::BlendFactor,
+        vk::BlendFactor,
+    ) {
+        (
+            state.src_color_blend_factor,
+            state.dst_color_blend_factor,
+            state.src_alpha_blend_factor,
+            state.dst_alpha_blend_factor,
+        )
+    }
+
+    #[test]
+    fn opaque_disables_blending() {
+        let state = AttachmentBlending::Opaque.to_blend_state();
+        assert_eq!(state.blend_enable, vk::FALSE);
+        assert_eq!(
+            factors(&state),
+            (
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ZERO,
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ZERO
+            )
+        );
+    }
+
+    #[test]
+    fn additive_adds_source_to_destination() {
+        let state = AttachmentBlending::Additive.to_blend_state();
+        assert_eq!(state.blend_enable, vk::TRUE);
+        assert_eq!(
+            factors(&state),
+            (
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE
+            )
+        );
+    }
+
+    #[test]
+    fn alpha_blends_by_source_alpha() {
+        let state = AttachmentBlending::Alpha.to_blend_state();
+        assert_eq!(state.blend_enable, vk::TRUE);
+        assert_eq!(
+            factors(&state),
+            (
+                vk::BlendFactor::SRC_ALPHA,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                vk::BlendFactor::SRC_ALPHA,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA
+            )
+        );
+    }
+
+    #[test]
+    fn every_variant_adds_and_writes_rgba() {
+        for blending in [
+            AttachmentBlending::Opaque,
+            AttachmentBlending::Additive,
+            AttachmentBlending::Alpha,
+        ] {
+            let state = blending.to_blend_state();
+            assert_eq!(state.color_blend_op, vk::BlendOp::ADD);
+            assert_eq!(state.alpha_blend_op, vk::BlendOp::ADD);
+            assert_eq!(state.color_write_mask, vk::ColorComponentFlags::RGBA);
+        }
     }
 }
@@ -0,0 +1,94 @@
+use crate::core::command_pool::VECommandPool;
+use crate::core::device::VEDevice;
+use crate::core::main_device_queue::VEMainDeviceQueue;
+use crate::graphics::attachment::{VEAttachment, VEAttachmentError};
+use crate::image::image::{
+    VEImage, VEImageError, VEImageOptions, VEImageUsage, VEImageViewCreateInfo,
+};
+use crate::memory::memory_manager::VEMemoryManager;
+use ash::vk;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEDepthTargetError {
+    #[error("no supported depth format found")]
+    NoSupportedDepthFormat,
+
+    #[error("image error")]
+    ImageError(#[from] VEImageError),
+
+    #[error("attachment error")]
+    AttachmentError(#[from] VEAttachmentError),
+}
+
+// depth image plus an attachment that clears it to 1.0 every pass, the old contents are discarded,
+// this struct is the only owner of the image, dropping it frees the image and its memory
+pub struct VEDepthTarget {
+    pub image: VEImage,
+    pub attachment: VEAttachment,
+    // depth aspect only, set when created for sampling. the attachment view covers stencil too
+    // on depth stencil formats and is not valid in a sampled descriptor
+    pub sampled_view: Option<vk::ImageView>,
+}
+
+impl VEDepthTarget {
+    pub fn new(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        width: u32,
+        height: u32,
+        for_sampling: bool,
+    ) -> Result<VEDepthTarget, VEDepthTargetError> {
+        let mut usages = vec![VEImageUsage::DepthAttachment];
+        if for_sampling {
+            usages.push(VEImageUsage::Sampled);
+        }
+        let format = VEImage::find_supported_depth_format(&device, &usages)
+            .ok_or(VEDepthTargetError::NoSupportedDepthFormat)?;
+
+        let mut image = VEImage::new_with_options(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            },
+            format,
+            VEImageOptions {
+                usages: &usages,
+                ..VEImageOptions::default()
+            },
+        )?;
+        let view = image.get_view(VEImageViewCreateInfo::simple_2d())?;
+        let attachment = VEAttachment::from_image(
+            &image,
+            view,
+            None,
+            Some(vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            }),
+        )?
+        .with_initial_layout(vk::ImageLayout::UNDEFINED);
+
+        let sampled_view = if for_sampling {
+            Some(image.get_view(VEImageViewCreateInfo::depth_only_2d())?)
+        } else {
+            None
+        };
+
+        Ok(VEDepthTarget {
+            image,
+            attachment,
+            sampled_view,
+        })
+    }
+}
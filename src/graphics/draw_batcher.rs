@@ -0,0 +1,355 @@
+use crate::buffer::buffer::{get_index_type, VEBuffer, VEIndexType};
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::descriptor_set::VEDescriptorSet;
+use crate::core::device::VEDevice;
+use crate::graphics::render_stage::VERenderStage;
+use ash::vk;
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub enum VEDrawParams {
+    Draw {
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    },
+    DrawIndexed {
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    },
+}
+
+// handles are copied out so items do not borrow the stage or the buffers,
+// everything referenced must stay alive until the command buffer finished executing
+#[derive(Clone)]
+pub struct VEDrawItem {
+    pub pipeline: vk::Pipeline,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub descriptor_sets: Vec<vk::DescriptorSet>,
+    pub vertex_buffer: Option<(vk::Buffer, u64)>,
+    pub index_buffer: Option<(vk::Buffer, u64, vk::IndexType)>,
+    pub push_constants: Option<(vk::ShaderStageFlags, Vec<u8>)>,
+    pub draw: VEDrawParams,
+}
+
+impl VEDrawItem {
+    pub fn new(stage: &VERenderStage, draw: VEDrawParams) -> VEDrawItem {
+        let pipeline = stage.get_pipeline();
+        VEDrawItem {
+            pipeline: pipeline.pipeline,
+            pipeline_layout: pipeline.layout,
+            descriptor_sets: vec![],
+            vertex_buffer: None,
+            index_buffer: None,
+            push_constants: None,
+            draw,
+        }
+    }
+
+    pub fn set_descriptor_sets(&mut self, sets: &[&VEDescriptorSet]) {
+        self.descriptor_sets = sets.iter().map(|set| set.set).collect();
+    }
+
+    pub fn set_vertex_buffer(&mut self, buffer: &VEBuffer, offset: u64) {
+        self.vertex_buffer = Some((buffer.buffer, offset));
+    }
+
+    pub fn set_index_buffer(&mut self, buffer: &VEBuffer, offset: u64, index_type: VEIndexType) {
+        self.index_buffer = Some((buffer.buffer, offset, get_index_type(index_type)));
+    }
+
+    pub fn set_push_constants(&mut self, stages: vk::ShaderStageFlags, data: &[u8]) {
+        self.push_constants = Some((stages, data.to_vec()));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum VEBindCommand {
+    Pipeline(vk::Pipeline),
+    DescriptorSets {
+        pipeline_layout: vk::PipelineLayout,
+        first_set: u32,
+        sets: Vec<vk::DescriptorSet>,
+    },
+    PushConstants {
+        pipeline_layout: vk::PipelineLayout,
+        stages: vk::ShaderStageFlags,
+        data: Vec<u8>,
+    },
+    VertexBuffer(vk::Buffer, u64),
+    IndexBuffer(vk::Buffer, u64, vk::IndexType),
+}
+
+#[derive(Default)]
+struct VEBoundState {
+    pipeline: Option<vk::Pipeline>,
+    pipeline_layout: Option<vk::PipelineLayout>,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    vertex_buffer: Option<(vk::Buffer, u64)>,
+    index_buffer: Option<(vk::Buffer, u64, vk::IndexType)>,
+    push_constants: Option<(vk::ShaderStageFlags, Vec<u8>)>,
+}
+
+impl VEBoundState {
+    // binds the item needs on top of the bound state, the state is updated as if they were
+    // recorded
+    fn get_bind_commands(&mut self, item: &VEDrawItem) -> Vec<VEBindCommand> {
+        let mut commands = vec![];
+        if self.pipeline != Some(item.pipeline) {
+            commands.push(VEBindCommand::Pipeline(item.pipeline));
+            self.pipeline = Some(item.pipeline);
+        }
+
+        // sets and push constants are only known to stay valid for the same layout
+        if self.pipeline_layout != Some(item.pipeline_layout) {
+            self.pipeline_layout = Some(item.pipeline_layout);
+            self.descriptor_sets.clear();
+            self.push_constants = None;
+        }
+
+        let first_changed = item
+            .descriptor_sets
+            .iter()
+            .zip(self.descriptor_sets.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if first_changed < item.descriptor_sets.len() {
+            commands.push(VEBindCommand::DescriptorSets {
+                pipeline_layout: item.pipeline_layout,
+                first_set: first_changed as u32,
+                sets: item.descriptor_sets[first_changed..].to_vec(),
+            });
+            self.descriptor_sets = item.descriptor_sets.clone();
+        }
+
+        if let Some(push_constants) = &item.push_constants {
+            if self.push_constants.as_ref() != Some(push_constants) {
+                commands.push(VEBindCommand::PushConstants {
+                    pipeline_layout: item.pipeline_layout,
+                    stages: push_constants.0,
+                    data: push_constants.1.clone(),
+                });
+                self.push_constants = Some(push_constants.clone());
+            }
+        }
+
+        if let Some((buffer, offset)) = item.vertex_buffer {
+            if self.vertex_buffer != item.vertex_buffer {
+                commands.push(VEBindCommand::VertexBuffer(buffer, offset));
+                self.vertex_buffer = item.vertex_buffer;
+            }
+        }
+
+        if let Some((buffer, offset, index_type)) = item.index_buffer {
+            if self.index_buffer != item.index_buffer {
+                commands.push(VEBindCommand::IndexBuffer(buffer, offset, index_type));
+                self.index_buffer = item.index_buffer;
+            }
+        }
+        commands
+    }
+}
+
+// records draws and replays them in order on flush, binds that match the
+// currently bound state are skipped
+pub struct VEDrawBatcher {
+    device: Arc<VEDevice>,
+    items: Vec<VEDrawItem>,
+}
+
+impl VEDrawBatcher {
+    pub fn new(device: Arc<VEDevice>) -> VEDrawBatcher {
+        VEDrawBatcher {
+            device,
+            items: vec![],
+        }
+    }
+
+    pub fn add(&mut self, item: VEDrawItem) {
+        self.items.push(item);
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // must be called inside the render pass, the batcher is empty afterwards
+    pub fn flush(&mut self, command_buffer: &VECommandBuffer) {
+        let mut bound = VEBoundState::default();
+        for item in self.items.drain(..) {
+            for command in bound.get_bind_commands(&item) {
+                record_bind_command(&self.device, command_buffer, &command);
+            }
+            record_draw(&self.device, command_buffer, &item.draw);
+        }
+    }
+}
+
+fn record_bind_command(
+    device: &VEDevice,
+    command_buffer: &VECommandBuffer,
+    command: &VEBindCommand,
+) {
+    let device = &device.device;
+    unsafe {
+        match command {
+            VEBindCommand::Pipeline(pipeline) => device.cmd_bind_pipeline(
+                command_buffer.handle,
+                vk::PipelineBindPoint::GRAPHICS,
+                *pipeline,
+            ),
+            VEBindCommand::DescriptorSets {
+                pipeline_layout,
+                first_set,
+                sets,
+            } => device.cmd_bind_descriptor_sets(
+                command_buffer.handle,
+                vk::PipelineBindPoint::GRAPHICS,
+                *pipeline_layout,
+                *first_set,
+                sets,
+                &[],
+            ),
+            VEBindCommand::PushConstants {
+                pipeline_layout,
+                stages,
+                data,
+            } => {
+                device.cmd_push_constants(command_buffer.handle, *pipeline_layout, *stages, 0, data)
+            }
+            VEBindCommand::VertexBuffer(buffer, offset) => {
+                device.cmd_bind_vertex_buffers(command_buffer.handle, 0, &[*buffer], &[*offset])
+            }
+            VEBindCommand::IndexBuffer(buffer, offset, index_type) => {
+                device.cmd_bind_index_buffer(command_buffer.handle, *buffer, *offset, *index_type)
+            }
+        }
+    }
+}
+
+fn record_draw(device: &VEDevice, command_buffer: &VECommandBuffer, draw: &VEDrawParams) {
+    let device = &device.device;
+    unsafe {
+        match *draw {
+            VEDrawParams::Draw {
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            } => device.cmd_draw(
+                command_buffer.handle,
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            ),
+            VEDrawParams::DrawIndexed {
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            } => device.cmd_draw_indexed(
+                command_buffer.handle,
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    fn item(pipeline: u64, sets: &[u64], vertex_buffer: u64) -> VEDrawItem {
+        VEDrawItem {
+            pipeline: vk::Pipeline::from_raw(pipeline),
+            pipeline_layout: vk::PipelineLayout::from_raw(pipeline),
+            descriptor_sets: sets
+                .iter()
+                .map(|x| vk::DescriptorSet::from_raw(*x))
+                .collect(),
+            vertex_buffer: Some((vk::Buffer::from_raw(vertex_buffer), 0)),
+            index_buffer: None,
+            push_constants: None,
+            draw: VEDrawParams::Draw {
+                vertex_count: 3,
+                instance_count: 1,
+                first_vertex: 0,
+                first_instance: 0,
+            },
+        }
+    }
+
+    // pipeline, descriptor set and vertex buffer binds over the whole batch
+    fn count_binds(items: &[VEDrawItem]) -> (usize, usize, usize) {
+        let mut bound = VEBoundState::default();
+        let mut counts = (0, 0, 0);
+        for item in items {
+            for command in bound.get_bind_commands(item) {
+                match command {
+                    VEBindCommand::Pipeline(_) => counts.0 += 1,
+                    VEBindCommand::DescriptorSets { .. } => counts.1 += 1,
+                    VEBindCommand::VertexBuffer(..) => counts.2 += 1,
+                    _ => {}
+                }
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn shared_pipeline_is_bound_once() {
+        let items = [
+            item(1, &[10], 100),
+            item(1, &[11], 101),
+            item(1, &[12], 102),
+        ];
+        assert_eq!(count_binds(&items), (1, 3, 3));
+    }
+
+    #[test]
+    fn shared_set_prefix_rebinds_only_the_rest() {
+        let mut bound = VEBoundState::default();
+        bound.get_bind_commands(&item(1, &[10, 20], 100));
+        let commands = bound.get_bind_commands(&item(1, &[10, 21], 100));
+        assert_eq!(
+            commands,
+            vec![VEBindCommand::DescriptorSets {
+                pipeline_layout: vk::PipelineLayout::from_raw(1),
+                first_set: 1,
+                sets: vec![vk::DescriptorSet::from_raw(21)],
+            }]
+        );
+    }
+
+    #[test]
+    fn shared_vertex_buffer_is_bound_once() {
+        let items = [
+            item(1, &[10], 100),
+            item(2, &[11], 100),
+            item(3, &[12], 100),
+        ];
+        assert_eq!(count_binds(&items), (3, 3, 1));
+    }
+
+    #[test]
+    fn pipeline_layout_change_rebinds_sets() {
+        // same set handle, but a different layout invalidates it
+        let items = [item(1, &[10], 100), item(2, &[10], 100)];
+        assert_eq!(count_binds(&items), (2, 2, 1));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::descriptor_set::VEDescriptorSet;
+use crate::core::descriptor_set_layout::VEDescriptorSetLayout;
+use crate::core::device::VEDevice;
+use crate::core::shader_module::{VEShaderModule, VEShaderModuleError, VEShaderModuleType};
+use crate::graphics::attachment::VEAttachment;
+use crate::graphics::render_stage::{
+    VECullMode, VEPrimitiveTopology, VERenderStage, VERenderStageError, VERenderStageOptions,
+};
+use ash::vk;
+use std::io::Cursor;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEFullscreenPassError {
+    #[error("shader module error")]
+    ShaderModuleError(#[from] VEShaderModuleError),
+
+    #[error("render stage error")]
+    RenderStageError(#[from] VERenderStageError),
+}
+
+// see shaders/fullscreen_triangle.vert, the fragment shader gets the uv at location 0
+static FULLSCREEN_TRIANGLE_VERTEX_SHADER: &[u8] =
+    include_bytes!("./shaders/fullscreen_triangle.spv");
+
+// vertexless pipeline drawing one triangle that covers the whole viewport, for post processing
+pub struct VEFullscreenPass {
+    device: Arc<VEDevice>,
+    stage: VERenderStage,
+}
+
+impl VEFullscreenPass {
+    pub fn new(
+        device: Arc<VEDevice>,
+        viewport_width: u32,
+        viewport_height: u32,
+        attachments: &[&VEAttachment],
+        set_layouts: &[&VEDescriptorSetLayout],
+        fragment_shader: &VEShaderModule,
+        options: &VERenderStageOptions,
+    ) -> Result<VEFullscreenPass, VEFullscreenPassError> {
+        let vertex_shader = VEShaderModule::from_stream(
+            device.clone(),
+            &mut Cursor::new(FULLSCREEN_TRIANGLE_VERTEX_SHADER),
+            VEShaderModuleType::Vertex,
+        )?;
+        let stage = VERenderStage::new_with_options(
+            device.clone(),
+            vk::Extent2D {
+                width: viewport_width,
+                height: viewport_height,
+            },
+            attachments,
+            set_layouts,
+            &vertex_shader,
+            fragment_shader,
+            &VERenderStageOptions {
+                vertex_attributes: vec![],
+                primitive_topology: VEPrimitiveTopology::TriangleList,
+                cull_mode: VECullMode::None,
+                ..options.clone()
+            },
+        )?;
+        Ok(VEFullscreenPass { device, stage })
+    }
+
+    pub fn get_stage(&self) -> &VERenderStage {
+        &self.stage
+    }
+
+    // begins and ends the render pass, the push constant range has to be in options
+    pub fn draw(
+        &self,
+        command_buffer: &VECommandBuffer,
+        descriptor_sets: &[&VEDescriptorSet],
+        push_constants: Option<(vk::ShaderStageFlags, &[u8])>,
+    ) {
+        self.stage.bind(command_buffer);
+        for (index, set) in descriptor_sets.iter().enumerate() {
+            self.stage
+                .set_descriptor_set(command_buffer, index as u32, set);
+        }
+        unsafe {
+            if let Some((stages, data)) = push_constants {
+                self.device.device.cmd_push_constants(
+                    command_buffer.handle,
+                    self.stage.get_pipeline().layout,
+                    stages,
+                    0,
+                    data,
+                );
+            }
+            self.device
+                .device
+                .cmd_draw(command_buffer.handle, 3, 1, 0, 0);
+        }
+        self.stage.end_render_pass(command_buffer);
+    }
+}
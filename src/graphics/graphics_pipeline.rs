@@ -2,12 +2,14 @@ use crate::core::descriptor_set_layout::VEDescriptorSetLayout;
 use crate::core::device::VEDevice;
 use crate::core::shader_module::VEShaderModule;
 use crate::graphics::attachment::{AttachmentBlending, VEAttachment};
+use crate::graphics::render_stage::{
+    get_cull_flags, get_polygon_mode, get_primitive_topology, VEPolygonMode, VERenderStageOptions,
+};
 use crate::graphics::renderpass::VERenderPass;
 use crate::graphics::vertex_attributes::{
-    create_vertex_input_state_descriptions, VEVertexAttributesError, VertexAttribFormat,
+    create_vertex_input_state_descriptions, VEVertexAttributesError,
 };
 use ash::vk;
-use ash::vk::ColorComponentFlags;
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -21,6 +23,67 @@ pub enum VEGraphicsPipelineError {
 
     #[error("vertex attributes error")]
     VertexAttributesError(#[from] VEVertexAttributesError),
+
+    #[error("extended dynamic state not supported")]
+    ExtendedDynamicStateNotSupported,
+
+    #[error("depth clamp not supported")]
+    DepthClampNotSupported,
+
+    #[error("depth clip enable not supported")]
+    DepthClipEnableNotSupported,
+
+    #[error("depth bounds test not supported")]
+    DepthBoundsNotSupported,
+
+    #[error("primitive restart requires a strip or fan topology")]
+    PrimitiveRestartRequiresStripTopology,
+
+    #[error("primitive restart cannot be validated against a dynamic topology")]
+    PrimitiveRestartWithDynamicTopology,
+
+    #[error("line and point polygon modes not supported, fillModeNonSolid is missing")]
+    FillModeNonSolidNotSupported,
+}
+
+// None for depth attachments, they take no part in color blending
+pub(crate) fn get_attachment_blend_state(
+    attachment: &VEAttachment,
+) -> Option<vk::PipelineColorBlendAttachmentState> {
+    if attachment.is_depth {
+        return None;
+    }
+    Some(
+        attachment
+            .blending
+            .unwrap_or(AttachmentBlending::Opaque)
+            .to_blend_state()
+            .color_write_mask(attachment.color_write_mask),
+    )
+}
+
+// with extended dynamic state the topology is set on the command buffer later, so the static
+// one says nothing about whether restart is valid
+fn validate_primitive_restart(
+    primitive_restart_enable: bool,
+    primitive_topology: vk::PrimitiveTopology,
+    dynamic_topology: bool,
+) -> Result<(), VEGraphicsPipelineError> {
+    if !primitive_restart_enable {
+        return Ok(());
+    }
+    if dynamic_topology {
+        return Err(VEGraphicsPipelineError::PrimitiveRestartWithDynamicTopology);
+    }
+    if !matches!(
+        primitive_topology,
+        vk::PrimitiveTopology::LINE_STRIP
+            | vk::PrimitiveTopology::TRIANGLE_STRIP
+            | vk::PrimitiveTopology::TRIANGLE_FAN
+    ) {
+        return Err(VEGraphicsPipelineError::PrimitiveRestartRequiresStripTopology);
+    }
+    Ok(())
 }
 
 pub struct VEGraphicsPipeline {
@@ -31,17 +94,41 @@ pub struct VEGraphicsPipeline {
 impl VEGraphicsPipeline {
     pub fn new(
         device: Arc<VEDevice>,
-        viewport_width: u32,
-        viewport_height: u32,
+        viewport: vk::Extent2D,
         set_layouts: &[&VEDescriptorSetLayout],
         vertex_shader: &VEShaderModule,
         fragment_shader: &VEShaderModule,
         render_pass: &VERenderPass,
         attachments: &[&VEAttachment],
-        vertex_attributes: &[VertexAttribFormat],
-        primitive_topology: vk::PrimitiveTopology,
-        cull_flags: vk::CullModeFlags,
+        options: &VERenderStageOptions,
     ) -> Result<VEGraphicsPipeline, VEGraphicsPipelineError> {
+        let vk::Extent2D {
+            width: viewport_width,
+            height: viewport_height,
+        } = viewport;
+        let primitive_topology = get_primitive_topology(options.primitive_topology.clone());
+        let cull_flags = get_cull_flags(options.cull_mode.clone());
+        if options.extended_dynamic_state && !device.supports_extended_dynamic_state() {
+            return Err(VEGraphicsPipelineError::ExtendedDynamicStateNotSupported);
+        }
+        if options.depth_clamp_enable == Some(true) && !device.supports_depth_clamp() {
+            return Err(VEGraphicsPipelineError::DepthClampNotSupported);
+        }
+        if options.depth_clip_enable.is_some() && !device.supports_depth_clip_enable() {
+            return Err(VEGraphicsPipelineError::DepthClipEnableNotSupported);
+        }
+        if options.depth_bounds_test_enable && !device.supports_depth_bounds() {
+            return Err(VEGraphicsPipelineError::DepthBoundsNotSupported);
+        }
+        if options.polygon_mode != VEPolygonMode::Fill && !device.supports_fill_mode_non_solid() {
+            return Err(VEGraphicsPipelineError::FillModeNonSolidNotSupported);
+        }
+        validate_primitive_restart(
+            options.primitive_restart_enable,
+            primitive_topology,
+            options.extended_dynamic_state,
+        )?;
+
         let vertex_shader_stage_info = vk::PipelineShaderStageCreateInfo::default()
             .stage(vk::ShaderStageFlags::VERTEX)
             .module(vertex_shader.handle)
@@ -56,7 +143,8 @@ impl VEGraphicsPipeline {
 
         let layouts: Vec<vk::DescriptorSetLayout> = set_layouts.iter().map(|x| x.layout).collect();
 
-        let vertex_attrib_descriptions = create_vertex_input_state_descriptions(vertex_attributes)?;
+        let vertex_attrib_descriptions =
+            create_vertex_input_state_descriptions(&options.vertex_attributes)?;
         let tmp_binds = [vertex_attrib_descriptions.0];
         let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
             .vertex_binding_descriptions(&tmp_binds)
@@ -64,7 +152,7 @@ impl VEGraphicsPipeline {
 
         let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
             .topology(primitive_topology)
-            .primitive_restart_enable(false);
+            .primitive_restart_enable(options.primitive_restart_enable);
 
         let viewport = vk::Viewport::default()
             .x(0.0)
@@ -96,14 +184,23 @@ impl VEGraphicsPipeline {
             .viewports(&tmp_viewports)
             .scissors(&tmp_scissors);
 
-        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
-            .depth_clamp_enable(true)
+        let mut depth_clip_state = vk::PipelineRasterizationDepthClipStateCreateInfoEXT::default()
+            .depth_clip_enable(options.depth_clip_enable.unwrap_or_default());
+        let mut rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .depth_clamp_enable(
+                options
+                    .depth_clamp_enable
+                    .unwrap_or_else(|| device.supports_depth_clamp()),
+            )
             .rasterizer_discard_enable(false)
-            .polygon_mode(vk::PolygonMode::FILL)
+            .polygon_mode(get_polygon_mode(options.polygon_mode))
             .line_width(1.0)
             .cull_mode(cull_flags)
             .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
             .depth_bias_enable(false);
+        if options.depth_clip_enable.is_some() {
+            rasterizer = rasterizer.push_next(&mut depth_clip_state);
+        }
 
         let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
             .sample_shading_enable(false)
@@ -116,48 +213,11 @@ impl VEGraphicsPipeline {
         //for att in render_pass.attachments {
         for i in 0..attachments.len() {
             let att = &attachments[i];
-            if !att.is_depth {
-                // not a depth buffer
-                let mut blend_state = vk::PipelineColorBlendAttachmentState::default()
-                    .color_write_mask(ColorComponentFlags::RGBA);
-                match &att.blending {
-                    None => {
-                        blend_state = blend_state
-                            .color_blend_op(vk::BlendOp::ADD)
-                            .src_color_blend_factor(vk::BlendFactor::ONE)
-                            .dst_color_blend_factor(vk::BlendFactor::ONE)
-                            .alpha_blend_op(vk::BlendOp::ADD)
-                            .src_alpha_blend_factor(vk::BlendFactor::ONE)
-                            .dst_alpha_blend_factor(vk::BlendFactor::ONE)
-                            .blend_enable(false);
-                    }
-                    Some(blending) => match blending {
-                        AttachmentBlending::Alpha => {
-                            blend_state = blend_state
-                                .color_blend_op(vk::BlendOp::ADD)
-                                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
-                                .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-                                .alpha_blend_op(vk::BlendOp::ADD)
-                                .src_alpha_blend_factor(vk::BlendFactor::SRC_ALPHA)
-                                .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-                                .blend_enable(true);
-                        }
-                        AttachmentBlending::Additive => {
-                            blend_state = blend_state
-                                .color_blend_op(vk::BlendOp::ADD)
-                                .src_color_blend_factor(vk::BlendFactor::ONE)
-                                .dst_color_blend_factor(vk::BlendFactor::ONE)
-                                .alpha_blend_op(vk::BlendOp::ADD)
-                                .src_alpha_blend_factor(vk::BlendFactor::ONE)
-                                .dst_alpha_blend_factor(vk::BlendFactor::ONE)
-                                .blend_enable(true);
-                        }
-                    },
-                }
+            if let Some(blend_state) = get_attachment_blend_state(att) {
                 attachment_blend_states.push(blend_state);
             } else {
-                // is a depth buffer, enable depth
-                enable_depth = true;
+                // is a depth buffer, enable depth unless only the stencil aspect is attached
+                enable_depth = att.aspect.contains(vk::ImageAspectFlags::DEPTH);
             }
         }
 
@@ -169,8 +229,12 @@ impl VEGraphicsPipeline {
             } else {
                 vk::CompareOp::ALWAYS
             })
+            .depth_bounds_test_enable(options.depth_bounds_test_enable)
             .min_depth_bounds(0.0)
-            .max_depth_bounds(1.0);
+            .max_depth_bounds(1.0)
+            .stencil_test_enable(options.stencil.is_some())
+            .front(options.stencil.unwrap_or_default())
+            .back(options.stencil.unwrap_or_default());
 
         let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
             .logic_op_enable(false)
@@ -178,7 +242,27 @@ impl VEGraphicsPipeline {
             .attachments(&attachment_blend_states)
             .blend_constants([1.0, 1.0, 1.0, 1.0]);
 
-        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts);
+        let mut dynamic_states = if options.extended_dynamic_state {
+            vec![
+                vk::DynamicState::CULL_MODE_EXT,
+                vk::DynamicState::FRONT_FACE_EXT,
+                vk::DynamicState::PRIMITIVE_TOPOLOGY_EXT,
+                vk::DynamicState::DEPTH_TEST_ENABLE_EXT,
+                vk::DynamicState::DEPTH_WRITE_ENABLE_EXT,
+                vk::DynamicState::DEPTH_COMPARE_OP_EXT,
+            ]
+        } else {
+            vec![]
+        };
+        if options.depth_bounds_test_enable {
+            dynamic_states.push(vk::DynamicState::DEPTH_BOUNDS);
+        }
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default()
+            .set_layouts(&layouts)
+            .push_constant_ranges(&options.push_constant_ranges);
         let pipeline_layout = unsafe {
             device
                 .device
@@ -195,6 +279,7 @@ impl VEGraphicsPipeline {
             .multisample_state(&multisampling)
             .depth_stencil_state(&depth_stencil)
             .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
             .layout(pipeline_layout)
             .render_pass(render_pass.handle)
             .subpass(0);
@@ -212,3 +297,84 @@ impl VEGraphicsPipeline {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color_attachment(
+        blending: Option<AttachmentBlending>,
+        color_write_mask: vk::ColorComponentFlags,
+    ) -> VEAttachment {
+        VEAttachment {
+            image_view: vk::ImageView::null(),
+            is_depth: false,
+            aspect: vk::ImageAspectFlags::COLOR,
+            description: vk::AttachmentDescription::default(),
+            blending,
+            color_write_mask,
+            clear: None,
+        }
+    }
+
+    #[test]
+    fn color_write_mask_reaches_blend_state() {
+        let motion_vectors = color_attachment(
+            None,
+            vk::ColorComponentFlags::R | vk::ColorComponentFlags::G,
+        );
+        let state = get_attachment_blend_state(&motion_vectors);
+        assert_eq!(
+            state.map(|s| s.color_write_mask),
+            Some(vk::ColorComponentFlags::R | vk::ColorComponentFlags::G)
+        );
+    }
+
+    #[test]
+    fn empty_mask_disables_color_writes_with_blending() {
+        let prepass = color_attachment(
+            Some(AttachmentBlending::Alpha),
+            vk::ColorComponentFlags::empty(),
+        );
+        let state = get_attachment_blend_state(&prepass);
+        assert_eq!(
+            state.map(|s| (s.color_write_mask, s.blend_enable)),
+            Some((vk::ColorComponentFlags::empty(), vk::TRUE))
+        );
+    }
+
+    #[test]
+    fn depth_attachment_has_no_blend_state() {
+        let depth = VEAttachment {
+            is_depth: true,
+            aspect: vk::ImageAspectFlags::DEPTH,
+            ..color_attachment(None, vk::ColorComponentFlags::RGBA)
+        };
+        assert!(get_attachment_blend_state(&depth).is_none());
+    }
+
+    #[test]
+    fn primitive_restart_needs_strip_topology() {
+        assert!(
+            validate_primitive_restart(true, vk::PrimitiveTopology::TRIANGLE_STRIP, false).is_ok()
+        );
+        assert!(matches!(
+            validate_primitive_restart(true, vk::PrimitiveTopology::TRIANGLE_LIST, false),
+            Err(VEGraphicsPipelineError::PrimitiveRestartRequiresStripTopology)
+        ));
+        assert!(
+            validate_primitive_restart(false, vk::PrimitiveTopology::TRIANGLE_LIST, false).is_ok()
+        );
+    }
+
+    #[test]
+    fn primitive_restart_rejects_dynamic_topology() {
+        assert!(matches!(
+            validate_primitive_restart(true, vk::PrimitiveTopology::TRIANGLE_STRIP, true),
+            Err(VEGraphicsPipelineError::PrimitiveRestartWithDynamicTopology)
+        ));
+        assert!(
+            validate_primitive_restart(false, vk::PrimitiveTopology::TRIANGLE_LIST, true).is_ok()
+        );
+    }
+}
@@ -1,4 +1,7 @@
 pub mod attachment;
+pub mod depth_target;
+pub mod draw_batcher;
+pub mod fullscreen_pass;
 pub mod render_stage;
 pub mod vertex_attributes;
 
@@ -1,7 +1,7 @@
-use crate::core::command_buffer::VECommandBuffer;
+use crate::core::command_buffer::{VECommandBuffer, VECommandBufferInheritance};
 use crate::core::descriptor_set::VEDescriptorSet;
 use crate::core::descriptor_set_layout::VEDescriptorSetLayout;
-use crate::core::device::VEDevice;
+use crate::core::device::{VEDevice, VEDeviceError};
 use crate::core::shader_module::VEShaderModule;
 use crate::graphics::attachment::VEAttachment;
 use crate::graphics::framebuffer::{VEFrameBuffer, VEFrameBufferError};
@@ -36,24 +36,26 @@ pub struct VERenderStage {
     clear_values: Vec<vk::ClearValue>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum VECullMode {
+    #[default]
     None,
     Front,
     Back,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum VEPrimitiveTopology {
     Points,
     LineList,
     LineStrip,
+    #[default]
     TriangleList,
     TriangleStrip,
     TriangleFan,
 }
 
-fn get_primitive_topology(topo: VEPrimitiveTopology) -> vk::PrimitiveTopology {
+pub(crate) fn get_primitive_topology(topo: VEPrimitiveTopology) -> vk::PrimitiveTopology {
     match topo {
         VEPrimitiveTopology::Points => vk::PrimitiveTopology::POINT_LIST,
         VEPrimitiveTopology::LineList => vk::PrimitiveTopology::LINE_LIST,
@@ -64,7 +66,24 @@ fn get_primitive_topology(topo: VEPrimitiveTopology) -> vk::PrimitiveTopology {
     }
 }
 
-fn get_cull_flags(mode: VECullMode) -> vk::CullModeFlags {
+// line and point need the fillModeNonSolid feature
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VEPolygonMode {
+    #[default]
+    Fill,
+    Line,
+    Point,
+}
+
+pub(crate) fn get_polygon_mode(mode: VEPolygonMode) -> vk::PolygonMode {
+    match mode {
+        VEPolygonMode::Fill => vk::PolygonMode::FILL,
+        VEPolygonMode::Line => vk::PolygonMode::LINE,
+        VEPolygonMode::Point => vk::PolygonMode::POINT,
+    }
+}
+
+pub(crate) fn get_cull_flags(mode: VECullMode) -> vk::CullModeFlags {
     match mode {
         VECullMode::None => vk::CullModeFlags::NONE,
         VECullMode::Front => vk::CullModeFlags::FRONT,
@@ -72,6 +91,49 @@ fn get_cull_flags(mode: VECullMode) -> vk::CullModeFlags {
     }
 }
 
+#[derive(Clone, Default)]
+pub struct VERenderStageOptions {
+    // empty for stages that generate their vertices in the shader, like a fullscreen triangle
+    pub vertex_attributes: Vec<VertexAttribFormat>,
+    pub primitive_topology: VEPrimitiveTopology,
+    pub cull_mode: VECullMode,
+    // cull mode, front face, topology and depth test state are set on the command buffer
+    pub extended_dynamic_state: bool,
+    // added to the pipeline layout, offsets are relative to the start of the block
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    // enables the stencil test with the same ops for front and back faces
+    pub stencil: Option<vk::StencilOpState>,
+    // color attachments also bound as input attachments for programmable blending,
+    // indices into the attachments in input_attachment_index order
+    pub input_attachments: Vec<u32>,
+    // clamps fragment depth to the viewport range instead of clipping at near and far,
+    // so shadow casters outside the light frustum still land in the shadow map,
+    // None clamps whenever the device supports it
+    pub depth_clamp_enable: Option<bool>,
+    // independent of clamping, needs VK_EXT_depth_clip_enable, None keeps the default
+    // of clipping only when clamping is off
+    pub depth_clip_enable: Option<bool>,
+    // strip and fan topologies only, affects indexed draws only, the restart index is
+    // 0xFFFF for VEIndexType::U16 and 0xFFFFFFFF for VEIndexType::U32 index buffers.
+    // not allowed together with extended_dynamic_state
+    pub primitive_restart_enable: bool,
+    // discards fragments whose stored depth is outside the bounds set with
+    // VECommandBuffer::set_depth_bounds, lets deferred lights skip pixels outside the
+    // light volume depth range. needs the depthBounds feature
+    pub depth_bounds_test_enable: bool,
+    pub polygon_mode: VEPolygonMode,
+}
+
+impl VERenderStageOptions {
+    // line polygon mode for debug views, everything else default
+    pub fn wireframe() -> VERenderStageOptions {
+        VERenderStageOptions {
+            polygon_mode: VEPolygonMode::Line,
+            ..Default::default()
+        }
+    }
+}
+
 impl VERenderStage {
     pub fn new(
         device: Arc<VEDevice>,
@@ -85,7 +147,41 @@ impl VERenderStage {
         primitive_topology: VEPrimitiveTopology,
         cull_mode: VECullMode,
     ) -> Result<VERenderStage, VERenderStageError> {
-        let render_pass = VERenderPass::new(device.clone(), attachments)?;
+        Self::new_with_options(
+            device,
+            vk::Extent2D {
+                width: viewport_width,
+                height: viewport_height,
+            },
+            attachments,
+            set_layouts,
+            vertex_shader,
+            fragment_shader,
+            &VERenderStageOptions {
+                vertex_attributes: vertex_attributes.to_vec(),
+                primitive_topology,
+                cull_mode,
+                ..VERenderStageOptions::default()
+            },
+        )
+    }
+
+    // vertex attributes, topology and cull mode come from options
+    pub fn new_with_options(
+        device: Arc<VEDevice>,
+        viewport: vk::Extent2D,
+        attachments: &[&VEAttachment],
+        set_layouts: &[&VEDescriptorSetLayout],
+        vertex_shader: &VEShaderModule,
+        fragment_shader: &VEShaderModule,
+        options: &VERenderStageOptions,
+    ) -> Result<VERenderStage, VERenderStageError> {
+        let vk::Extent2D {
+            width: viewport_width,
+            height: viewport_height,
+        } = viewport;
+        let render_pass =
+            VERenderPass::new(device.clone(), attachments, &options.input_attachments)?;
 
         let framebuffer = VEFrameBuffer::new(
             device.clone(),
@@ -97,16 +193,13 @@ impl VERenderStage {
 
         let pipeline = VEGraphicsPipeline::new(
             device.clone(),
-            viewport_width,
-            viewport_height,
+            viewport,
             set_layouts,
             vertex_shader,
             fragment_shader,
             &render_pass,
             attachments,
-            vertex_attributes,
-            get_primitive_topology(primitive_topology),
-            get_cull_flags(cull_mode),
+            options,
         )?;
 
         let clear_values = attachments
@@ -125,6 +218,14 @@ impl VERenderStage {
         })
     }
 
+    pub(crate) fn get_pipeline(&self) -> &VEGraphicsPipeline {
+        &self.pipeline
+    }
+
+    pub fn get_pipeline_layout(&self) -> vk::PipelineLayout {
+        self.pipeline.layout
+    }
+
     pub fn set_descriptor_set(
         &self,
         command_buffer: &VECommandBuffer,
@@ -144,6 +245,39 @@ impl VERenderStage {
     }
 
     pub fn bind(&self, command_buffer: &VECommandBuffer) {
+        self.begin_render_pass(command_buffer, vk::SubpassContents::INLINE);
+        self.bind_pipeline(command_buffer);
+    }
+
+    // begins the render pass on the primary, draws are then recorded into secondaries begun
+    // with get_inheritance and run with execute_commands
+    pub fn bind_for_secondaries(&self, command_buffer: &VECommandBuffer) {
+        self.begin_render_pass(
+            command_buffer,
+            vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
+        );
+    }
+
+    // pipeline state is not inherited, secondaries call this themselves
+    pub fn bind_pipeline(&self, command_buffer: &VECommandBuffer) {
+        unsafe {
+            self.device.device.cmd_bind_pipeline(
+                command_buffer.handle,
+                BIND_POINT,
+                self.pipeline.pipeline,
+            );
+        }
+    }
+
+    pub fn get_inheritance(&self) -> VECommandBufferInheritance {
+        VECommandBufferInheritance {
+            render_pass: self.render_pass.handle,
+            subpass: 0,
+            framebuffer: self.framebuffer.handle,
+        }
+    }
+
+    fn begin_render_pass(&self, command_buffer: &VECommandBuffer, contents: vk::SubpassContents) {
         let rect = vk::Rect2D::default()
             .offset(vk::Offset2D::default())
             .extent(
@@ -162,13 +296,7 @@ impl VERenderStage {
             self.device.device.cmd_begin_render_pass(
                 command_buffer.handle,
                 &render_pass_begin_info,
-                vk::SubpassContents::INLINE,
-            );
-
-            self.device.device.cmd_bind_pipeline(
-                command_buffer.handle,
-                BIND_POINT,
-                self.pipeline.pipeline,
+                contents,
             );
         }
     }
@@ -180,4 +308,12 @@ impl VERenderStage {
                 .cmd_end_render_pass(command_buffer.handle);
         }
     }
+
+    // names the pipeline, its layout, the render pass and the framebuffer
+    pub fn set_name(&self, name: &str) -> Result<(), VEDeviceError> {
+        self.device.set_debug_name(self.pipeline.pipeline, name)?;
+        self.device.set_debug_name(self.pipeline.layout, name)?;
+        self.device.set_debug_name(self.render_pass.handle, name)?;
+        self.device.set_debug_name(self.framebuffer.handle, name)
+    }
 }
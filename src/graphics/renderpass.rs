@@ -8,6 +8,9 @@ use thiserror::Error;
 pub enum VERenderPassError {
     #[error("creation failed")]
     CreationFailed(#[from] vk::Result),
+
+    #[error("input attachment is not a color attachment of the subpass")]
+    InputAttachmentNotColor(u32),
 }
 
 pub struct VERenderPass {
@@ -15,15 +18,41 @@ pub struct VERenderPass {
 }
 
 impl VERenderPass {
+    // input_attachments are indices into attachments that are also read as input attachments
+    // in the subpass, in input_attachment_index order. adds a by-region self-dependency so
+    // VECommandBuffer::subpass_self_barrier can be used between the draws
     pub fn new(
         device: Arc<VEDevice>,
         attachments: &[&VEAttachment],
+        input_attachments: &[u32],
     ) -> Result<VERenderPass, VERenderPassError> {
         let color_attas: Vec<&&VEAttachment> = attachments.iter().filter(|x| !x.is_depth).collect();
         let depth_atta = attachments.iter().filter(|x| x.is_depth).last();
 
+        for index in input_attachments {
+            if *index as usize >= color_attas.len() {
+                return Err(VERenderPassError::InputAttachmentNotColor(*index));
+            }
+        }
+
+        // an attachment used as color and input in the same subpass has to be in GENERAL
         let color_references: Vec<vk::AttachmentReference> = (0..color_attas.len())
-            .map(|i| Self::create_subpass_attachment_reference(i as i32, false))
+            .map(|i| {
+                let reference = Self::create_subpass_attachment_reference(i as i32, false);
+                if input_attachments.contains(&(i as u32)) {
+                    reference.layout(vk::ImageLayout::GENERAL)
+                } else {
+                    reference
+                }
+            })
+            .collect();
+        let input_references: Vec<vk::AttachmentReference> = input_attachments
+            .iter()
+            .map(|i| {
+                vk::AttachmentReference::default()
+                    .attachment(*i)
+                    .layout(vk::ImageLayout::GENERAL)
+            })
             .collect();
 
         let depth_reference_maybe =
@@ -33,15 +62,28 @@ impl VERenderPass {
             Some(_) => Some(&depth_reference_maybe), // depth last
         };
 
-        let subpass = Self::create_subpass(&color_references, depth_reference);
+        let subpass = Self::create_subpass(&color_references, depth_reference)
+            .input_attachments(&input_references);
         let subpasses = [subpass];
 
         let atta_descs: Vec<vk::AttachmentDescription> =
             attachments.iter().map(|a| a.description).collect();
 
-        let create_info = vk::RenderPassCreateInfo::default()
+        let self_dependency = [vk::SubpassDependency::default()
+            .src_subpass(0)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::INPUT_ATTACHMENT_READ)
+            .dependency_flags(vk::DependencyFlags::BY_REGION)];
+
+        let mut create_info = vk::RenderPassCreateInfo::default()
             .attachments(&atta_descs)
             .subpasses(&subpasses);
+        if !input_attachments.is_empty() {
+            create_info = create_info.dependencies(&self_dependency);
+        }
 
         let handle = unsafe { device.device.create_render_pass(&create_info, None)? };
 
@@ -65,6 +107,7 @@ impl VERenderPass {
         vk::AttachmentReference::default()
             .attachment(index as u32)
             .layout(if depth {
+                // also used for stencil-only attachments, the aspect is picked by the load and store ops
                 vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
             } else {
                 vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
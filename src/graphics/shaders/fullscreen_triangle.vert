@@ -0,0 +1,10 @@
+#version 450
+
+// vertexless fullscreen triangle, draw 3 vertices without a vertex buffer.
+// uv is (0, 0) at the top left and (1, 1) at the bottom right of the screen
+layout(location = 0) out vec2 uv;
+
+void main() {
+    uv = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2));
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -1,5 +1,6 @@
 use ash::vk;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum VEFiltering {
     Nearest,
     Linear,
@@ -1,10 +1,22 @@
 use crate::buffer::buffer::VEBufferError;
+use crate::compute::compute_stage::VEComputeStageError;
 use crate::core::command_buffer::{VECommandBuffer, VECommandBufferError};
-use crate::core::device::VEDevice;
+use crate::core::command_pool::VECommandPool;
+use crate::core::descriptor_set::VEDescriptorSetError;
+use crate::core::descriptor_set_layout::VEDescriptorSetLayoutError;
+use crate::core::device::{VEDevice, VEDeviceError};
+use crate::core::fence::{VEFence, VEFenceError};
 use crate::core::main_device_queue::{VEMainDeviceQueue, VEMainDeviceQueueError};
-use crate::image::transition_image_layout::transition_image_layout;
+use crate::core::memory_barrier::VEBarrierBatch;
+use crate::core::memory_properties::VEMemoryProperties;
+use crate::core::shader_module::VEShaderModuleError;
+use crate::core::sync_state::VESyncState;
+use crate::image::image_format::{to_linear_format, to_srgb_format};
+use crate::image::transition_image_layout::{
+    get_subresource_transition_barrier, get_transition_barrier, transition_image_layout,
+};
 use crate::memory::memory_chunk::{VEMemoryChunkError, VESingleAllocation};
-use crate::memory::memory_manager::VEMemoryManagerError;
+use crate::memory::memory_manager::{VEMemoryManager, VEMemoryManagerError};
 use ash::vk;
 use image::ImageError;
 use std::collections::HashMap;
@@ -13,14 +25,27 @@ use std::io;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
+#[path = "./image_clear.rs"]
+mod image_clear;
 #[path = "./image_from_data.rs"]
 mod image_from_data;
 #[path = "./image_from_file.rs"]
 mod image_from_file;
 #[path = "./image_from_full.rs"]
 mod image_from_full;
+#[cfg(any(feature = "hdr", feature = "exr"))]
+#[path = "./image_from_hdr.rs"]
+mod image_from_hdr;
 #[path = "./image_from_swapchain.rs"]
 mod image_from_swapchain;
+#[path = "./image_mipmaps.rs"]
+mod image_mipmaps;
+#[path = "./image_read_pixel.rs"]
+mod image_read_pixel;
+#[path = "./image_recreate.rs"]
+mod image_recreate;
+#[path = "./image_update_region.rs"]
+mod image_update_region;
 
 #[derive(Error, Debug)]
 pub enum VEImageError {
@@ -59,6 +84,93 @@ pub enum VEImageError {
 
     #[error("queue locking failed")]
     QueueLockingFailed,
+
+    #[error("image locking failed")]
+    ImageLockingFailed,
+
+    #[error("fence error")]
+    FenceError(#[from] VEFenceError),
+
+    #[error("none of the candidate formats is supported")]
+    NoSupportedFormatFound,
+
+    #[error("mipmaps are only supported for 2D images")]
+    MipmapsRequire2DImage,
+
+    #[error("no linear or sRGB counterpart for the image format")]
+    NoPairedFormat,
+
+    #[error("cube images need square faces and a multiple of 6 layers")]
+    InvalidCubeImage,
+
+    #[error("region does not fit into the mip level or layer")]
+    RegionOutOfBounds,
+
+    #[error("region data size does not match the region extent")]
+    RegionDataSizeMismatch,
+
+    #[error("format and usages do not support the requested tiling")]
+    TilingNotSupported,
+
+    #[error("image has no allocation of its own")]
+    NoOwnAllocation,
+
+    #[error("subresource layouts are only defined for linear images")]
+    SubresourceLayoutRequiresLinearTiling,
+
+    #[error("image was not created with the transfer destination usage")]
+    MissingTransferDestinationUsage,
+
+    #[error("image was not created with the transfer source usage")]
+    MissingTransferSourceUsage,
+
+    #[error("unknown texel size for the image format")]
+    UnknownTexelSize,
+
+    #[error("image was not created with a mutable format")]
+    MutableFormatNotSet,
+
+    #[error("no mipmap downsample shader for the image format")]
+    NoMipmapShaderForFormat,
+
+    #[error("compute clears are only supported for 2D images")]
+    ComputeClearRequires2DImage,
+
+    #[error("image was not created with the storage usage")]
+    MissingStorageUsage,
+
+    #[error("no clear shader for the image format")]
+    NoClearShaderForFormat,
+
+    #[error("color clears are only supported for color images")]
+    ColorClearRequiresColorImage,
+
+    #[error("depth stencil clears are only supported for depth or stencil images")]
+    DepthStencilClearRequiresDepthStencilImage,
+
+    #[error("usages or format do not support transfer clears")]
+    FixedFunctionClearNotSupported,
+
+    #[error("descriptor set layout error")]
+    DescriptorSetLayoutError(#[from] VEDescriptorSetLayoutError),
+
+    #[error("descriptor set error")]
+    DescriptorSetError(#[from] VEDescriptorSetError),
+
+    #[error("shader module error")]
+    ShaderModuleError(#[from] VEShaderModuleError),
+
+    #[error("compute stage error")]
+    ComputeStageError(#[from] VEComputeStageError),
+}
+
+// how from_data gets the texels to the device, see VEImage::get_upload_path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VEImageUploadPath {
+    // recorded inline with vkCmdUpdateBuffer into a device local buffer from the transient
+    // arena, no host visible memory
+    UpdateBuffer,
+    StagingBuffer,
 }
 
 #[derive(Debug, Clone)]
@@ -71,6 +183,57 @@ pub enum VEImageUsage {
     TransferSource,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VEImageTiling {
+    Optimal,
+    // host addressable texels, few formats and usages support it
+    Linear,
+}
+
+// settings past extent and format, see VEImage::new_with_options
+#[derive(Debug)]
+pub struct VEImageOptions<'a> {
+    pub usages: &'a [VEImageUsage],
+    // 1 for a single level, VEImage::get_full_mip_level_count for the full chain. with more than one level
+    // the usages generate_mipmaps needs are added depending on format support
+    pub mip_levels: u32,
+    pub array_layers: u32,
+    // allows cube views of 6 layer groups, needs a square image and a multiple of 6 layers
+    pub cube_compatible: bool,
+    // allows linear_view and srgb_view to reinterpret the image in its paired format
+    pub mutable_format: bool,
+    // shared between the given queue families without ownership transfers, note that
+    // drivers may skip some compression optimizations for concurrent images. needs at least
+    // two distinct families, otherwise the image stays exclusive
+    pub queue_families: &'a [u32],
+    // linear tiling for direct host access, memory_properties should then be host visible,
+    // mapped rows are get_row_pitch bytes apart
+    pub tiling: VEImageTiling,
+    pub memory_properties: VEMemoryProperties,
+}
+
+impl Default for VEImageOptions<'_> {
+    fn default() -> Self {
+        VEImageOptions {
+            usages: &[],
+            mip_levels: 1,
+            array_layers: 1,
+            cube_compatible: false,
+            mutable_format: false,
+            queue_families: &[],
+            tiling: VEImageTiling::Optimal,
+            memory_properties: VEMemoryProperties::DeviceLocal,
+        }
+    }
+}
+
+pub(crate) fn get_image_tiling(tiling: VEImageTiling) -> vk::ImageTiling {
+    match tiling {
+        VEImageTiling::Optimal => vk::ImageTiling::OPTIMAL,
+        VEImageTiling::Linear => vk::ImageTiling::LINEAR,
+    }
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub enum VEImageViewType {
     View1D,
@@ -89,6 +252,11 @@ pub struct VEImageViewCreateInfo {
     pub mipmap_count: u32,
     pub base_layer: u32,
     pub layer_count: u32,
+    // None uses the image format, anything else requires a MUTABLE_FORMAT image
+    pub format: Option<vk::Format>,
+    // None uses every aspect of the image, sampled views of depth stencil images must pick
+    // DEPTH or STENCIL alone
+    pub aspect: Option<vk::ImageAspectFlags>,
 }
 
 impl VEImageViewCreateInfo {
@@ -99,6 +267,16 @@ impl VEImageViewCreateInfo {
             layer_count: 1,
             base_mipmap: 0,
             mipmap_count: 1,
+            format: None,
+            aspect: None,
+        }
+    }
+
+    // single aspect 2d view for sampling the depth of a depth or depth stencil image
+    pub fn depth_only_2d() -> VEImageViewCreateInfo {
+        VEImageViewCreateInfo {
+            aspect: Some(vk::ImageAspectFlags::DEPTH),
+            ..Self::simple_2d()
         }
     }
 
@@ -109,6 +287,8 @@ impl VEImageViewCreateInfo {
             layer_count: 1,
             base_mipmap: 0,
             mipmap_count: 1,
+            format: None,
+            aspect: None,
         }
     }
 }
@@ -117,18 +297,28 @@ impl VEImageViewCreateInfo {
 pub struct VEImage {
     device: Arc<VEDevice>,
     queue: Arc<Mutex<VEMainDeviceQueue>>,
+    command_pool: Arc<VECommandPool>,
 
     pub width: u32,
     pub height: u32,
     pub depth: u32,
+    pub mip_levels: u32,
+    pub array_layers: u32,
 
     pub format: vk::Format,
+    pub usage: vk::ImageUsageFlags,
 
     aspect: vk::ImageAspectFlags,
+    sharing_mode: vk::SharingMode,
+    queue_family_indices: Vec<u32>,
+    create_flags: vk::ImageCreateFlags,
+    tiling: vk::ImageTiling,
 
     pub current_layout: vk::ImageLayout,
 
     allocation: Option<VESingleAllocation>,
+    last_use: Option<Arc<VEFence>>,
+    sync_state: VESyncState,
     pub handle: vk::Image,
     views: HashMap<VEImageViewCreateInfo, vk::ImageView>,
 }
@@ -141,7 +331,133 @@ impl Debug for VEImage {
 
 impl VEImage {
     pub fn is_depth(&self) -> bool {
-        self.format == vk::Format::D16_UNORM || self.format == vk::Format::D32_SFLOAT
+        self.aspect.contains(vk::ImageAspectFlags::DEPTH)
+    }
+
+    pub fn is_concurrent(&self) -> bool {
+        self.sharing_mode == vk::SharingMode::CONCURRENT
+    }
+
+    pub fn get_queue_family_indices(&self) -> &[u32] {
+        &self.queue_family_indices
+    }
+
+    pub fn get_aspect(&self) -> vk::ImageAspectFlags {
+        self.aspect
+    }
+
+    pub fn get_tiling(&self) -> vk::ImageTiling {
+        self.tiling
+    }
+
+    // offset, size and pitches of a mip and layer inside the allocation, linear images only
+    pub fn get_subresource_layout(
+        &self,
+        mip: u32,
+        layer: u32,
+    ) -> Result<vk::SubresourceLayout, VEImageError> {
+        if self.tiling != vk::ImageTiling::LINEAR {
+            return Err(VEImageError::SubresourceLayoutRequiresLinearTiling);
+        }
+        let subresource = vk::ImageSubresource::default()
+            .aspect_mask(self.aspect)
+            .mip_level(mip)
+            .array_layer(layer);
+        Ok(unsafe {
+            self.device
+                .device
+                .get_image_subresource_layout(self.handle, subresource)
+        })
+    }
+
+    pub fn get_row_pitch(&self) -> Result<u64, VEImageError> {
+        Ok(self.get_subresource_layout(0, 0)?.row_pitch)
+    }
+
+    // bytes of the first mip and layer as laid out in memory, rows are get_row_pitch apart
+    pub fn read_linear_to_cpu(
+        &self,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+    ) -> Result<Vec<u8>, VEImageError> {
+        let layout = self.get_subresource_layout(0, 0)?;
+        let allocation = self
+            .allocation
+            .as_ref()
+            .ok_or(VEImageError::NoOwnAllocation)?;
+        self.wait_until_idle()?;
+        let mut result = vec![0u8; layout.size as usize];
+        memory_manager
+            .lock()
+            .map_err(|_| VEImageError::MemoryManagerLockingFailed)?
+            .read_allocation(allocation, layout.offset, &mut result)?;
+        Ok(result)
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.aspect.contains(vk::ImageAspectFlags::STENCIL)
+    }
+
+    // one time command buffer with the image in GENERAL, for work that reads and writes
+    // different subresources of the same image, see finish_general_commands
+    pub(crate) fn begin_general_commands(&mut self) -> Result<VECommandBuffer, VEImageError> {
+        let command_buffer = VECommandBuffer::new(self.device.clone(), self.command_pool.clone())?;
+        command_buffer.begin()?;
+        if self.current_layout != vk::ImageLayout::GENERAL {
+            self.transition_layout(
+                &command_buffer,
+                self.current_layout,
+                vk::ImageLayout::GENERAL,
+            )?;
+        }
+        Ok(command_buffer)
+    }
+
+    // makes the writes visible to any later use of the whole image, then submits and waits
+    pub(crate) fn finish_general_commands(
+        &mut self,
+        command_buffer: VECommandBuffer,
+        src_access: vk::AccessFlags,
+        source_stage: vk::PipelineStageFlags,
+    ) -> Result<(), VEImageError> {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .old_layout(self.current_layout)
+            .new_layout(self.current_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.handle)
+            .subresource_range(
+                vk::ImageSubresourceRange::default()
+                    .aspect_mask(self.aspect)
+                    .base_mip_level(0)
+                    .level_count(vk::REMAINING_MIP_LEVELS)
+                    .base_array_layer(0)
+                    .layer_count(vk::REMAINING_ARRAY_LAYERS),
+            )
+            .src_access_mask(src_access)
+            .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE);
+        unsafe {
+            self.device.device.cmd_pipeline_barrier(
+                command_buffer.handle,
+                source_stage,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            );
+        }
+        self.sync_state.record_write(None, source_stage, src_access);
+        command_buffer.end()?;
+
+        let queue = self
+            .queue
+            .lock()
+            .map_err(|_| VEImageError::QueueLockingFailed)?;
+
+        command_buffer.submit(&queue, vec![], vec![])?;
+        queue.wait_idle()?;
+
+        Ok(())
     }
 
     pub fn transition_layout(
@@ -150,7 +466,7 @@ impl VEImage {
         from_layout: vk::ImageLayout,
         to_layout: vk::ImageLayout,
     ) -> Result<(), VEImageError> {
-        transition_image_layout(
+        let (stage, access) = transition_image_layout(
             self.device.clone(),
             command_buffer,
             self.handle,
@@ -159,11 +475,219 @@ impl VEImage {
             to_layout,
         )?;
 
+        // layout transitions count as writes
+        self.sync_state.record_write(None, stage, access);
         self.current_layout = to_layout;
 
         Ok(())
     }
 
+    // transitions the image to TRANSFER_SRC_OPTIMAL for the closure and back, the restore is
+    // recorded even when the closure fails, UNDEFINED and PREINITIALIZED images come back in
+    // GENERAL as they cannot be transitioned to. with a range only those mips and layers are
+    // transitioned, None or an image without a defined layout transitions the whole image
+    pub fn as_transfer_src<R>(
+        &mut self,
+        command_buffer: &VECommandBuffer,
+        range: Option<vk::ImageSubresourceRange>,
+        f: impl FnOnce(&mut VEImage) -> Result<R, VEImageError>,
+    ) -> Result<R, VEImageError> {
+        self.with_layout(
+            command_buffer,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            range,
+            f,
+        )
+    }
+
+    pub fn as_transfer_dst<R>(
+        &mut self,
+        command_buffer: &VECommandBuffer,
+        range: Option<vk::ImageSubresourceRange>,
+        f: impl FnOnce(&mut VEImage) -> Result<R, VEImageError>,
+    ) -> Result<R, VEImageError> {
+        self.with_layout(
+            command_buffer,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            range,
+            f,
+        )
+    }
+
+    // a single mip and layer in the image's aspect, for as_transfer_src and as_transfer_dst
+    pub fn get_subresource_range(&self, mip: u32, layer: u32) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(self.aspect)
+            .base_mip_level(mip)
+            .level_count(1)
+            .base_array_layer(layer)
+            .layer_count(1)
+    }
+
+    fn with_layout<R>(
+        &mut self,
+        command_buffer: &VECommandBuffer,
+        layout: vk::ImageLayout,
+        range: Option<vk::ImageSubresourceRange>,
+        f: impl FnOnce(&mut VEImage) -> Result<R, VEImageError>,
+    ) -> Result<R, VEImageError> {
+        let (original_layout, range) = match self.current_layout {
+            // the untouched subresources would stay undefined while the layout is tracked for
+            // the whole image, so the whole image is transitioned
+            vk::ImageLayout::UNDEFINED | vk::ImageLayout::PREINITIALIZED => {
+                (vk::ImageLayout::GENERAL, None)
+            }
+            current_layout => (current_layout, range),
+        };
+        let Some(range) = range else {
+            self.transition_layout(command_buffer, self.current_layout, layout)?;
+            let result = f(self);
+            let restored = self.transition_layout(command_buffer, layout, original_layout);
+            let value = result?;
+            restored?;
+            return Ok(value);
+        };
+
+        self.transition_subresource(command_buffer, range, original_layout, layout);
+        // the closure reads current_layout for the copy, it only touches the transitioned range
+        self.current_layout = layout;
+        let result = f(self);
+        self.transition_subresource(command_buffer, range, layout, original_layout);
+        self.current_layout = original_layout;
+        result
+    }
+
+    // leaves current_layout alone, the caller restores the range before it is read again
+    fn transition_subresource(
+        &mut self,
+        command_buffer: &VECommandBuffer,
+        range: vk::ImageSubresourceRange,
+        from_layout: vk::ImageLayout,
+        to_layout: vk::ImageLayout,
+    ) {
+        let (barrier, source_stage, destination_stage) =
+            get_subresource_transition_barrier(self.handle, range, from_layout, to_layout);
+        unsafe {
+            self.device.device.cmd_pipeline_barrier(
+                command_buffer.handle,
+                source_stage,
+                destination_stage,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            )
+        }
+        // layout transitions count as writes
+        self.sync_state
+            .record_write(None, destination_stage, barrier.dst_access_mask);
+    }
+
+    // same as transition_layout, but the barrier is recorded when the batch is flushed
+    pub fn queue_transition_layout(
+        &mut self,
+        batch: &mut VEBarrierBatch,
+        from_layout: vk::ImageLayout,
+        to_layout: vk::ImageLayout,
+    ) {
+        let (barrier, source_stage, destination_stage) =
+            get_transition_barrier(self.handle, self.aspect, from_layout, to_layout);
+        batch.add_image_barrier(source_stage, destination_stage, barrier);
+        self.sync_state
+            .record_write(None, destination_stage, barrier.dst_access_mask);
+        self.current_layout = to_layout;
+    }
+
+    // fence of the last submission that used this image, set by the caller after submit
+    pub fn set_last_use(&mut self, fence: Arc<VEFence>) {
+        self.last_use = Some(fence);
+    }
+
+    pub fn wait_until_idle(&self) -> Result<(), VEImageError> {
+        if let Some(fence) = &self.last_use {
+            fence.wait()?;
+        }
+        Ok(())
+    }
+
+    // debug builds only, layout is tracked for the whole image, not per subresource
+    pub fn assert_layout(&self, expected: vk::ImageLayout) {
+        debug_assert_eq!(
+            self.current_layout, expected,
+            "VEImage is in unexpected layout"
+        );
+    }
+
+    // views reinterpreting an sRGB image as linear or the other way around
+    pub fn linear_view(
+        &mut self,
+        info: VEImageViewCreateInfo,
+    ) -> Result<vk::ImageView, VEImageError> {
+        let format = to_linear_format(self.format).ok_or(VEImageError::NoPairedFormat)?;
+        self.get_paired_format_view(info, format)
+    }
+
+    pub fn srgb_view(
+        &mut self,
+        info: VEImageViewCreateInfo,
+    ) -> Result<vk::ImageView, VEImageError> {
+        let format = to_srgb_format(self.format).ok_or(VEImageError::NoPairedFormat)?;
+        self.get_paired_format_view(info, format)
+    }
+
+    fn get_paired_format_view(
+        &mut self,
+        info: VEImageViewCreateInfo,
+        format: vk::Format,
+    ) -> Result<vk::ImageView, VEImageError> {
+        if !self
+            .create_flags
+            .contains(vk::ImageCreateFlags::MUTABLE_FORMAT)
+        {
+            return Err(VEImageError::MutableFormatNotSet);
+        }
+        self.get_view(VEImageViewCreateInfo {
+            format: Some(format),
+            ..info
+        })
+    }
+
+    // 3D for depth > 1, cube for 6 layers of a cube compatible image and cube array for
+    // multiples of 6, 2D array for other layered images, 2D otherwise
+    pub fn default_view_type(&self) -> VEImageViewType {
+        let cube_compatible = self
+            .create_flags
+            .contains(vk::ImageCreateFlags::CUBE_COMPATIBLE);
+        if self.depth > 1 {
+            VEImageViewType::View3D
+        } else if cube_compatible && self.array_layers == 6 {
+            VEImageViewType::ViewCube
+        } else if cube_compatible && self.array_layers.is_multiple_of(6) {
+            VEImageViewType::ViewCubeArray
+        } else if self.array_layers > 1 {
+            VEImageViewType::View2DArray
+        } else {
+            VEImageViewType::View2D
+        }
+    }
+
+    // all mips and layers with the inferred type, change typ on the result to override it
+    pub fn default_view_info(&self) -> VEImageViewCreateInfo {
+        VEImageViewCreateInfo {
+            typ: self.default_view_type(),
+            base_mipmap: 0,
+            mipmap_count: self.mip_levels,
+            base_layer: 0,
+            layer_count: self.array_layers,
+            format: None,
+            aspect: None,
+        }
+    }
+
+    pub fn get_default_view(&mut self) -> Result<vk::ImageView, VEImageError> {
+        self.get_view(self.default_view_info())
+    }
+
     pub fn get_view(&mut self, info: VEImageViewCreateInfo) -> Result<vk::ImageView, VEImageError> {
         let existing = self.views.get(&info);
         match existing {
@@ -180,10 +704,10 @@ impl VEImage {
                         VEImageViewType::View2DArray => vk::ImageViewType::TYPE_2D_ARRAY,
                         VEImageViewType::ViewCubeArray => vk::ImageViewType::CUBE_ARRAY,
                     })
-                    .format(self.format)
+                    .format(info.format.unwrap_or(self.format))
                     .subresource_range(
                         vk::ImageSubresourceRange::default()
-                            .aspect_mask(self.aspect)
+                            .aspect_mask(info.aspect.unwrap_or(self.aspect))
                             .base_mip_level(info.base_mipmap)
                             .level_count(info.mipmap_count)
                             .base_array_layer(info.base_layer)
@@ -208,6 +732,24 @@ impl VEImage {
             }
         }
     }
+
+    // None for swapchain images
+    pub(crate) fn get_allocation(&self) -> Option<&VESingleAllocation> {
+        self.allocation.as_ref()
+    }
+
+    // read only, for debugging why a barrier was emitted
+    pub fn get_sync_state(&self) -> &VESyncState {
+        &self.sync_state
+    }
+
+    pub(crate) fn get_sync_state_mut(&mut self) -> &mut VESyncState {
+        &mut self.sync_state
+    }
+
+    pub fn set_name(&self, name: &str) -> Result<(), VEDeviceError> {
+        self.device.set_debug_name(self.handle, name)
+    }
 }
 
 impl Drop for VEImage {
@@ -0,0 +1,207 @@
+use super::image_from_full::get_mip_size;
+use crate::compute::compute_stage::VEComputeStage;
+use crate::core::descriptor_set_layout::{
+    VEDescriptorSetFieldStage, VEDescriptorSetFieldType, VEDescriptorSetLayout,
+    VEDescriptorSetLayoutField,
+};
+use crate::core::shader_module::{VEShaderModule, VEShaderModuleType};
+use crate::image::image::{VEImage, VEImageError, VEImageViewCreateInfo, VEImageViewType};
+use ash::vk;
+use std::io::Cursor;
+
+// see shaders/image_clear.comp, one variant per storage format
+fn get_image_clear_shader(format: vk::Format) -> Option<&'static [u8]> {
+    match format {
+        vk::Format::R8_SNORM => Some(include_bytes!("./shaders/image_clear_r8_snorm.spv")),
+        vk::Format::R8G8_SNORM => Some(include_bytes!("./shaders/image_clear_rg8_snorm.spv")),
+        vk::Format::R8G8B8A8_SNORM => Some(include_bytes!("./shaders/image_clear_rgba8_snorm.spv")),
+        vk::Format::R8_UNORM => Some(include_bytes!("./shaders/image_clear_r8.spv")),
+        vk::Format::R8G8_UNORM => Some(include_bytes!("./shaders/image_clear_rg8.spv")),
+        vk::Format::R8G8B8A8_UNORM => Some(include_bytes!("./shaders/image_clear_rgba8.spv")),
+        vk::Format::R16_SINT => Some(include_bytes!("./shaders/image_clear_r16i.spv")),
+        vk::Format::R16G16_SINT => Some(include_bytes!("./shaders/image_clear_rg16i.spv")),
+        vk::Format::R16G16B16A16_SINT => Some(include_bytes!("./shaders/image_clear_rgba16i.spv")),
+        vk::Format::R16_UINT => Some(include_bytes!("./shaders/image_clear_r16ui.spv")),
+        vk::Format::R16G16_UINT => Some(include_bytes!("./shaders/image_clear_rg16ui.spv")),
+        vk::Format::R16G16B16A16_UINT => Some(include_bytes!("./shaders/image_clear_rgba16ui.spv")),
+        vk::Format::R16_SFLOAT => Some(include_bytes!("./shaders/image_clear_r16f.spv")),
+        vk::Format::R16G16_SFLOAT => Some(include_bytes!("./shaders/image_clear_rg16f.spv")),
+        vk::Format::R16G16B16A16_SFLOAT => {
+            Some(include_bytes!("./shaders/image_clear_rgba16f.spv"))
+        }
+        vk::Format::R32_SINT => Some(include_bytes!("./shaders/image_clear_r32i.spv")),
+        vk::Format::R32G32_SINT => Some(include_bytes!("./shaders/image_clear_rg32i.spv")),
+        vk::Format::R32G32B32A32_SINT => Some(include_bytes!("./shaders/image_clear_rgba32i.spv")),
+        vk::Format::R32_UINT => Some(include_bytes!("./shaders/image_clear_r32ui.spv")),
+        vk::Format::R32G32_UINT => Some(include_bytes!("./shaders/image_clear_rg32ui.spv")),
+        vk::Format::R32G32B32A32_UINT => Some(include_bytes!("./shaders/image_clear_rgba32ui.spv")),
+        vk::Format::R32_SFLOAT => Some(include_bytes!("./shaders/image_clear_r32f.spv")),
+        vk::Format::R32G32_SFLOAT => Some(include_bytes!("./shaders/image_clear_rg32f.spv")),
+        vk::Format::R32G32B32A32_SFLOAT => {
+            Some(include_bytes!("./shaders/image_clear_rgba32f.spv"))
+        }
+        _ => None,
+    }
+}
+
+impl VEImage {
+    pub fn supports_fixed_function_clear(&self) -> bool {
+        self.usage.contains(vk::ImageUsageFlags::TRANSFER_DST)
+            && self
+                .device
+                .get_format_properties(self.format)
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::TRANSFER_DST)
+    }
+
+    // vkCmdClearColorImage where the format allows it, clear_compute otherwise,
+    // all mips and layers are cleared and the image is left in GENERAL, depth and stencil
+    // images use clear_depth_stencil
+    pub fn clear(&mut self, value: vk::ClearColorValue) -> Result<(), VEImageError> {
+        if !self.aspect.contains(vk::ImageAspectFlags::COLOR) {
+            return Err(VEImageError::ColorClearRequiresColorImage);
+        }
+        if !self.supports_fixed_function_clear() {
+            return self.clear_compute(value);
+        }
+        let command_buffer = self.begin_general_commands()?;
+        let range = vk::ImageSubresourceRange::default()
+            .aspect_mask(self.aspect)
+            .base_mip_level(0)
+            .level_count(vk::REMAINING_MIP_LEVELS)
+            .base_array_layer(0)
+            .layer_count(vk::REMAINING_ARRAY_LAYERS);
+        unsafe {
+            self.device.device.cmd_clear_color_image(
+                command_buffer.handle,
+                self.handle,
+                self.current_layout,
+                &value,
+                &[range],
+            );
+        }
+        self.finish_general_commands(
+            command_buffer,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::PipelineStageFlags::TRANSFER,
+        )
+    }
+
+    // writes the value from a compute shader, needs the storage usage on a 2D image,
+    // integer formats read the value as int32 or uint32, all others as float32
+    pub fn clear_compute(&mut self, value: vk::ClearColorValue) -> Result<(), VEImageError> {
+        if !self.aspect.contains(vk::ImageAspectFlags::COLOR) {
+            return Err(VEImageError::ColorClearRequiresColorImage);
+        }
+        if self.depth != 1 {
+            return Err(VEImageError::ComputeClearRequires2DImage);
+        }
+        if !self.usage.contains(vk::ImageUsageFlags::STORAGE) {
+            return Err(VEImageError::MissingStorageUsage);
+        }
+        let shader_code =
+            get_image_clear_shader(self.format).ok_or(VEImageError::NoClearShaderForFormat)?;
+
+        let shader = VEShaderModule::from_stream(
+            self.device.clone(),
+            &mut Cursor::new(shader_code),
+            VEShaderModuleType::Compute,
+        )?;
+        let mut set_layout = VEDescriptorSetLayout::new(
+            self.device.clone(),
+            &[VEDescriptorSetLayoutField {
+                binding: 0,
+                typ: VEDescriptorSetFieldType::StorageImage,
+                stage: VEDescriptorSetFieldStage::Compute,
+            }],
+        )?;
+        let compute_stage = VEComputeStage::new_with_push_constants(
+            self.device.clone(),
+            self.command_pool.clone(),
+            &[&set_layout],
+            &shader,
+            &[vk::PushConstantRange::default()
+                .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                .offset(0)
+                .size(size_of::<vk::ClearColorValue>() as u32)],
+        )?;
+        let value_bytes = unsafe { value.uint32 }
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<u8>>();
+
+        let command_buffer = self.begin_general_commands()?;
+        compute_stage.bind(&command_buffer);
+        compute_stage.set_push_constants(&command_buffer, 0, &value_bytes);
+
+        // sets have to outlive the submission
+        let mut sets = vec![];
+        for level in 0..self.mip_levels {
+            for layer in 0..self.array_layers {
+                let view = self.get_view(VEImageViewCreateInfo {
+                    typ: VEImageViewType::View2D,
+                    base_layer: layer,
+                    layer_count: 1,
+                    base_mipmap: level,
+                    mipmap_count: 1,
+                    format: None,
+                    aspect: None,
+                })?;
+                let set = set_layout.create_descriptor_set()?;
+                set.bind_image_storage(0, self, view)?;
+                compute_stage.set_descriptor_set(&command_buffer, 0, &set);
+                compute_stage.dispatch(
+                    &command_buffer,
+                    get_mip_size(self.width, level).div_ceil(8),
+                    get_mip_size(self.height, level).div_ceil(8),
+                    1,
+                );
+                sets.push(set);
+            }
+        }
+
+        self.finish_general_commands(
+            command_buffer,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+        )
+    }
+
+    // vkCmdClearDepthStencilImage on every aspect of the image, all mips and layers are
+    // cleared and the image is left in GENERAL, there is no compute fallback
+    pub fn clear_depth_stencil(
+        &mut self,
+        value: vk::ClearDepthStencilValue,
+    ) -> Result<(), VEImageError> {
+        if !self
+            .aspect
+            .intersects(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL)
+        {
+            return Err(VEImageError::DepthStencilClearRequiresDepthStencilImage);
+        }
+        if !self.supports_fixed_function_clear() {
+            return Err(VEImageError::FixedFunctionClearNotSupported);
+        }
+        let command_buffer = self.begin_general_commands()?;
+        let range = vk::ImageSubresourceRange::default()
+            .aspect_mask(self.aspect)
+            .base_mip_level(0)
+            .level_count(vk::REMAINING_MIP_LEVELS)
+            .base_array_layer(0)
+            .layer_count(vk::REMAINING_ARRAY_LAYERS);
+        unsafe {
+            self.device.device.cmd_clear_depth_stencil_image(
+                command_buffer.handle,
+                self.handle,
+                self.current_layout,
+                &value,
+                &[range],
+            );
+        }
+        self.finish_general_commands(
+            command_buffer,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::PipelineStageFlags::TRANSFER,
+        )
+    }
+}
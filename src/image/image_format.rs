@@ -35,6 +35,9 @@ pub enum VEImageFormat {
 
     Depth16u,
     Depth32f,
+
+    Depth24Stencil8u,
+    Depth32fStencil8u,
 }
 
 pub fn get_image_format(format: VEImageFormat) -> vk::Format {
@@ -73,5 +76,96 @@ pub fn get_image_format(format: VEImageFormat) -> vk::Format {
 
         VEImageFormat::Depth16u => vk::Format::D16_UNORM,
         VEImageFormat::Depth32f => vk::Format::D32_SFLOAT,
+
+        VEImageFormat::Depth24Stencil8u => vk::Format::D24_UNORM_S8_UINT,
+        VEImageFormat::Depth32fStencil8u => vk::Format::D32_SFLOAT_S8_UINT,
+    }
+}
+
+static SRGB_FORMAT_PAIRS: [(vk::Format, vk::Format); 15] = [
+    (vk::Format::R8_UNORM, vk::Format::R8_SRGB),
+    (vk::Format::R8G8_UNORM, vk::Format::R8G8_SRGB),
+    (vk::Format::R8G8B8_UNORM, vk::Format::R8G8B8_SRGB),
+    (vk::Format::B8G8R8_UNORM, vk::Format::B8G8R8_SRGB),
+    (vk::Format::R8G8B8A8_UNORM, vk::Format::R8G8B8A8_SRGB),
+    (vk::Format::B8G8R8A8_UNORM, vk::Format::B8G8R8A8_SRGB),
+    (
+        vk::Format::A8B8G8R8_UNORM_PACK32,
+        vk::Format::A8B8G8R8_SRGB_PACK32,
+    ),
+    (
+        vk::Format::BC1_RGB_UNORM_BLOCK,
+        vk::Format::BC1_RGB_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::BC1_RGBA_UNORM_BLOCK,
+        vk::Format::BC1_RGBA_SRGB_BLOCK,
+    ),
+    (vk::Format::BC2_UNORM_BLOCK, vk::Format::BC2_SRGB_BLOCK),
+    (vk::Format::BC3_UNORM_BLOCK, vk::Format::BC3_SRGB_BLOCK),
+    (vk::Format::BC7_UNORM_BLOCK, vk::Format::BC7_SRGB_BLOCK),
+    (
+        vk::Format::ETC2_R8G8B8_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+    ),
+];
+
+pub fn to_linear_format(format: vk::Format) -> Option<vk::Format> {
+    SRGB_FORMAT_PAIRS
+        .iter()
+        .find(|(_, srgb)| *srgb == format)
+        .map(|(linear, _)| *linear)
+}
+
+pub fn to_srgb_format(format: vk::Format) -> Option<vk::Format> {
+    SRGB_FORMAT_PAIRS
+        .iter()
+        .find(|(linear, _)| *linear == format)
+        .map(|(_, srgb)| *srgb)
+}
+
+// bytes per texel of uncompressed color and depth formats, None for block compressed ones
+pub fn get_format_texel_size(format: vk::Format) -> Option<u32> {
+    match format {
+        vk::Format::R8_SNORM | vk::Format::R8_UNORM | vk::Format::R8_SRGB => Some(1),
+        vk::Format::R8G8_SNORM
+        | vk::Format::R8G8_UNORM
+        | vk::Format::R8G8_SRGB
+        | vk::Format::R16_SINT
+        | vk::Format::R16_UINT
+        | vk::Format::R16_SFLOAT
+        | vk::Format::D16_UNORM => Some(2),
+        vk::Format::R8G8B8A8_SNORM
+        | vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::A8B8G8R8_UNORM_PACK32
+        | vk::Format::A8B8G8R8_SRGB_PACK32
+        | vk::Format::R16G16_SINT
+        | vk::Format::R16G16_UINT
+        | vk::Format::R16G16_SFLOAT
+        | vk::Format::R32_SINT
+        | vk::Format::R32_UINT
+        | vk::Format::R32_SFLOAT
+        | vk::Format::D32_SFLOAT => Some(4),
+        vk::Format::R16G16B16A16_SINT
+        | vk::Format::R16G16B16A16_UINT
+        | vk::Format::R16G16B16A16_SFLOAT
+        | vk::Format::R32G32_SINT
+        | vk::Format::R32G32_UINT
+        | vk::Format::R32G32_SFLOAT => Some(8),
+        vk::Format::R32G32B32A32_SINT
+        | vk::Format::R32G32B32A32_UINT
+        | vk::Format::R32G32B32A32_SFLOAT => Some(16),
+        _ => None,
     }
 }
@@ -4,7 +4,7 @@ use crate::core::command_pool::VECommandPool;
 use crate::core::device::VEDevice;
 use crate::core::main_device_queue::VEMainDeviceQueue;
 use crate::core::memory_properties::VEMemoryProperties;
-use crate::image::image::{VEImage, VEImageError, VEImageUsage};
+use crate::image::image::{VEImage, VEImageError, VEImageUploadPath, VEImageUsage};
 use crate::image::image_format::VEImageFormat;
 use crate::memory::memory_manager::VEMemoryManager;
 use ash::vk;
@@ -12,6 +12,7 @@ use ash::vk::CommandBufferUsageFlags;
 use std::sync::{Arc, Mutex};
 
 impl VEImage {
+    // see get_upload_path for how the texels get to the device
     pub fn from_data(
         device: Arc<VEDevice>,
         queue: Arc<Mutex<VEMainDeviceQueue>>,
@@ -42,31 +43,71 @@ impl VEImage {
             usages.as_slice(),
         )?;
 
-        let mut staging_buffer = VEBuffer::new(
-            device.clone(),
-            queue.clone(),
-            command_pool.clone(),
-            memory_manager.clone(),
-            &[VEBufferUsage::TransferSource],
-            data.len() as vk::DeviceSize,
-            Some(VEMemoryProperties::HostCoherent),
-        )?;
-
-        unsafe {
-            let mem = staging_buffer.map()? as *mut u8;
-            std::ptr::copy(data.as_ptr(), mem, data.len());
-            // staging_buffer.unmap()?;
+        let upload_path = get_upload_path(data.len());
+        let mut staging_buffer = match upload_path {
+            // dropped before returning, so the next reset_transient_arena can reclaim it
+            VEImageUploadPath::UpdateBuffer => VEBuffer::new_transient(
+                device.clone(),
+                queue.clone(),
+                command_pool.clone(),
+                memory_manager.clone(),
+                &[
+                    VEBufferUsage::TransferSource,
+                    VEBufferUsage::TransferDestination,
+                ],
+                data.len() as vk::DeviceSize,
+                Some(VEMemoryProperties::DeviceLocal),
+            )?,
+            VEImageUploadPath::StagingBuffer => VEBuffer::new(
+                device.clone(),
+                queue.clone(),
+                command_pool.clone(),
+                memory_manager.clone(),
+                &[VEBufferUsage::TransferSource],
+                data.len() as vk::DeviceSize,
+                Some(VEMemoryProperties::HostCoherent),
+            )?,
+        };
+
+        if upload_path == VEImageUploadPath::StagingBuffer {
+            unsafe {
+                let mem = staging_buffer.map()? as *mut u8;
+                std::ptr::copy(data.as_ptr(), mem, data.len());
+                // staging_buffer.unmap()?;
+            }
         }
 
         let command_buffer = VECommandBuffer::new(device.clone(), command_pool.clone())?;
         //command_buffer.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
         command_buffer.begin()?;
 
-        result.transition_layout(
-            &command_buffer,
-            result.current_layout,
-            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-        )?;
+        if upload_path == VEImageUploadPath::UpdateBuffer {
+            unsafe {
+                device.device.cmd_update_buffer(
+                    command_buffer.handle,
+                    staging_buffer.buffer,
+                    0,
+                    data,
+                );
+                let barrier = vk::BufferMemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .buffer(staging_buffer.buffer)
+                    .offset(0)
+                    .size(vk::WHOLE_SIZE);
+                device.device.cmd_pipeline_barrier(
+                    command_buffer.handle,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[barrier],
+                    &[],
+                );
+            }
+        }
 
         let region = vk::BufferImageCopy::default()
             .image_subresource(
@@ -83,21 +124,18 @@ impl VEImage {
                     .depth(depth),
             );
 
-        unsafe {
-            device.device.cmd_copy_buffer_to_image(
-                command_buffer.handle,
-                staging_buffer.buffer,
-                result.handle,
-                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                &[region],
-            );
-        }
-
-        result.transition_layout(
-            &command_buffer,
-            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-            vk::ImageLayout::GENERAL,
-        )?;
+        result.as_transfer_dst(&command_buffer, None, |image| {
+            unsafe {
+                device.device.cmd_copy_buffer_to_image(
+                    command_buffer.handle,
+                    staging_buffer.buffer,
+                    image.handle,
+                    image.current_layout,
+                    &[region],
+                );
+            }
+            Ok(())
+        })?;
 
         command_buffer.end()?;
 
@@ -108,4 +146,18 @@ impl VEImage {
 
         Ok(result)
     }
+
+    // the path from_data takes for data_len bytes of texel data
+    pub fn get_upload_path(data_len: usize) -> VEImageUploadPath {
+        get_upload_path(data_len)
+    }
+}
+
+// vkCmdUpdateBuffer takes at most 65536 bytes in multiples of 4
+fn get_upload_path(size: usize) -> VEImageUploadPath {
+    if size > 0 && size <= 65536 && size.is_multiple_of(4) {
+        VEImageUploadPath::UpdateBuffer
+    } else {
+        VEImageUploadPath::StagingBuffer
+    }
 }
@@ -2,9 +2,12 @@ use crate::core::command_buffer::VECommandBuffer;
 use crate::core::command_pool::VECommandPool;
 use crate::core::device::VEDevice;
 use crate::core::main_device_queue::VEMainDeviceQueue;
-use crate::core::memory_properties::{get_memory_properties_flags, VEMemoryProperties};
+use crate::core::memory_properties::get_memory_properties_flags;
+use crate::core::sync_state::VESyncState;
 use crate::image::aspect_from_format::aspect_from_format;
-use crate::image::image::{VEImage, VEImageError, VEImageUsage};
+use crate::image::image::{
+    get_image_tiling, VEImage, VEImageError, VEImageOptions, VEImageTiling, VEImageUsage,
+};
 use crate::image::image_format::{get_image_format, VEImageFormat};
 use crate::memory::memory_manager::VEMemoryManager;
 use ash::vk;
@@ -28,7 +31,77 @@ fn get_image_usage_flags(usages: &[VEImageUsage]) -> vk::ImageUsageFlags {
     flags
 }
 
+pub(crate) fn get_image_format_features(usages: &[VEImageUsage]) -> vk::FormatFeatureFlags {
+    let mut flags = vk::FormatFeatureFlags::empty();
+    for usage in usages {
+        match usage {
+            VEImageUsage::ColorAttachment => flags |= vk::FormatFeatureFlags::COLOR_ATTACHMENT,
+            VEImageUsage::DepthAttachment => {
+                flags |= vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+            }
+            VEImageUsage::Sampled => flags |= vk::FormatFeatureFlags::SAMPLED_IMAGE,
+            VEImageUsage::Storage => flags |= vk::FormatFeatureFlags::STORAGE_IMAGE,
+            VEImageUsage::TransferDestination => flags |= vk::FormatFeatureFlags::TRANSFER_DST,
+            VEImageUsage::TransferSource => flags |= vk::FormatFeatureFlags::TRANSFER_SRC,
+        }
+    }
+    flags
+}
+
+// depth only formats come first so sampled depth views need no aspect fiddling
+static DEPTH_FORMAT_CANDIDATES: [vk::Format; 4] = [
+    vk::Format::D32_SFLOAT,
+    vk::Format::D32_SFLOAT_S8_UINT,
+    vk::Format::D24_UNORM_S8_UINT,
+    vk::Format::D16_UNORM,
+];
+
+pub fn get_mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+pub(crate) fn get_mip_size(size: u32, level: u32) -> u32 {
+    (size >> level).max(1)
+}
+
+fn get_image_create_flags(options: &VEImageOptions) -> vk::ImageCreateFlags {
+    let mut flags = vk::ImageCreateFlags::empty();
+    if options.cube_compatible {
+        flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
+    }
+    if options.mutable_format {
+        flags |= vk::ImageCreateFlags::MUTABLE_FORMAT;
+    }
+    flags
+}
+
 impl VEImage {
+    pub fn find_supported_depth_format(
+        device: &VEDevice,
+        usages: &[VEImageUsage],
+    ) -> Option<vk::Format> {
+        Self::find_supported_format(device, &DEPTH_FORMAT_CANDIDATES, usages)
+    }
+
+    // the first candidate that supports all the usages with optimal tiling
+    pub fn find_supported_format(
+        device: &VEDevice,
+        candidates: &[vk::Format],
+        usages: &[VEImageUsage],
+    ) -> Option<vk::Format> {
+        let required_features = get_image_format_features(usages);
+        candidates.iter().cloned().find(|format| {
+            device
+                .get_format_properties(*format)
+                .optimal_tiling_features
+                .contains(required_features)
+        })
+    }
+
+    pub fn get_full_mip_level_count(width: u32, height: u32) -> u32 {
+        get_mip_level_count(width, height)
+    }
+
     pub fn from_full(
         device: Arc<VEDevice>,
         queue: Arc<Mutex<VEMainDeviceQueue>>,
@@ -43,12 +116,115 @@ impl VEImage {
 
         usages: &[VEImageUsage],
     ) -> Result<VEImage, VEImageError> {
-        let format = get_image_format(format);
+        Self::from_full_vk_format(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            vk::Extent3D {
+                width,
+                height,
+                depth,
+            },
+            get_image_format(format),
+            VEImageOptions {
+                usages,
+                ..VEImageOptions::default()
+            },
+        )
+    }
+
+    // depth 1 creates a 2D image, the format has to support the usages with the given tiling
+    pub fn new_with_options(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        extent: vk::Extent3D,
+        format: vk::Format,
+        options: VEImageOptions,
+    ) -> Result<VEImage, VEImageError> {
+        if options.cube_compatible
+            && (extent.width != extent.height
+                || extent.depth != 1
+                || options.array_layers == 0
+                || !options.array_layers.is_multiple_of(6))
+        {
+            return Err(VEImageError::InvalidCubeImage);
+        }
+        let format_properties = device.get_format_properties(format);
+        let supported_features = match options.tiling {
+            VEImageTiling::Optimal => format_properties.optimal_tiling_features,
+            VEImageTiling::Linear => format_properties.linear_tiling_features,
+        };
+        if !supported_features.contains(get_image_format_features(options.usages)) {
+            return Err(VEImageError::TilingNotSupported);
+        }
+
+        let mut usages = options.usages.to_vec();
+        if options.mip_levels > 1 {
+            if Self::format_supports_linear_blit(&device, format) {
+                usages.push(VEImageUsage::TransferSource);
+                usages.push(VEImageUsage::TransferDestination);
+            } else {
+                usages.push(VEImageUsage::Storage);
+            }
+        }
+        Self::from_full_vk_format(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            extent,
+            format,
+            VEImageOptions {
+                usages: &usages,
+                ..options
+            },
+        )
+    }
+
+    fn from_full_vk_format(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        extent: vk::Extent3D,
+        format: vk::Format,
+        options: VEImageOptions,
+    ) -> Result<VEImage, VEImageError> {
+        let create_flags = get_image_create_flags(&options);
+        let VEImageOptions {
+            usages,
+            mip_levels,
+            array_layers,
+            queue_families,
+            tiling,
+            memory_properties,
+            ..
+        } = options;
+        let vk::Extent3D {
+            width,
+            height,
+            depth,
+        } = extent;
+        let tiling = get_image_tiling(tiling);
         let aspect = aspect_from_format(format);
 
-        let queue_family_indices = [device.queue_family_index];
+        // concurrent sharing needs at least two distinct families, otherwise stay exclusive
+        let mut queue_family_indices = queue_families.to_vec();
+        queue_family_indices.sort_unstable();
+        queue_family_indices.dedup();
+        let sharing_mode = if queue_family_indices.len() > 1 {
+            vk::SharingMode::CONCURRENT
+        } else {
+            queue_family_indices = vec![device.queue_family_index];
+            vk::SharingMode::EXCLUSIVE
+        };
 
+        let usage = get_image_usage_flags(usages);
         let image_create_info = vk::ImageCreateInfo::default()
+            .flags(create_flags)
             .image_type(if depth == 1 {
                 vk::ImageType::TYPE_2D
             } else {
@@ -60,13 +236,13 @@ impl VEImage {
                     .height(height)
                     .depth(depth),
             )
-            .mip_levels(1)
-            .array_layers(1)
+            .mip_levels(mip_levels)
+            .array_layers(array_layers)
             .format(format)
-            .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(get_image_usage_flags(usages))
+            .tiling(tiling)
+            .usage(usage)
             .samples(vk::SampleCountFlags::TYPE_1)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .sharing_mode(sharing_mode)
             .queue_family_indices(&queue_family_indices)
             .initial_layout(vk::ImageLayout::PREINITIALIZED);
 
@@ -80,7 +256,7 @@ impl VEImage {
         let mem_reqs = unsafe { device.device.get_image_memory_requirements(image_handle) };
         let mem_index = device.find_memory_type(
             mem_reqs.memory_type_bits,
-            get_memory_properties_flags(Some(VEMemoryProperties::DeviceLocal)),
+            get_memory_properties_flags(Some(memory_properties)),
         );
 
         let allocation = match mem_index {
@@ -88,22 +264,32 @@ impl VEImage {
             Some(mem_index) => memory_manager
                 .lock()
                 .map_err(|_| VEImageError::MemoryManagerLockingFailed)?
-                .bind_image_memory(mem_index, image_handle, mem_reqs.size)?,
+                .bind_image_memory(mem_index, image_handle, mem_reqs.size, None)?,
         };
 
         let mut image = VEImage {
             device: device.clone(),
             queue: queue.clone(),
+            command_pool: command_pool.clone(),
 
             allocation: Some(allocation),
+            last_use: None,
+            sync_state: VESyncState::default(),
 
             width,
             height,
             depth,
+            mip_levels,
+            array_layers,
 
             format,
+            usage,
 
             aspect,
+            sharing_mode,
+            queue_family_indices,
+            create_flags,
+            tiling,
 
             handle: image_handle,
             views: HashMap::new(),
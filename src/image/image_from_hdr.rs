@@ -0,0 +1,125 @@
+use crate::core::command_pool::VECommandPool;
+use crate::core::device::VEDevice;
+use crate::core::main_device_queue::VEMainDeviceQueue;
+use crate::image::image::image_from_full::get_image_format_features;
+use crate::image::image::{VEImage, VEImageError, VEImageUsage};
+use crate::image::image_format::{get_image_format, VEImageFormat};
+use crate::memory::memory_manager::VEMemoryManager;
+use ash::vk;
+use half::f16;
+use image::{ImageFormat, ImageReader};
+use std::sync::{Arc, Mutex};
+
+// half float is preferred, it is enough for environment maps and uses half the memory
+fn pick_float_format(
+    device: &VEDevice,
+    usages: &[VEImageUsage],
+) -> Result<VEImageFormat, VEImageError> {
+    let required_features = get_image_format_features(usages)
+        | vk::FormatFeatureFlags::SAMPLED_IMAGE
+        | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+        | vk::FormatFeatureFlags::TRANSFER_DST;
+    let supports = |format: VEImageFormat| {
+        device
+            .get_format_properties(get_image_format(format))
+            .optimal_tiling_features
+            .contains(required_features)
+    };
+    if supports(VEImageFormat::RGBA16f) {
+        Ok(VEImageFormat::RGBA16f)
+    } else if supports(VEImageFormat::RGBA32f) {
+        Ok(VEImageFormat::RGBA32f)
+    } else {
+        Err(VEImageError::NoSupportedFormatFound)
+    }
+}
+
+impl VEImage {
+    // radiance rgbe is decoded to float, alpha is set to 1
+    #[cfg(feature = "hdr")]
+    pub fn from_hdr(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        path: &str,
+        usages: &[VEImageUsage],
+    ) -> Result<VEImage, VEImageError> {
+        Self::from_float_file(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            path,
+            ImageFormat::Hdr,
+            usages,
+        )
+    }
+
+    #[cfg(feature = "exr")]
+    pub fn from_exr(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        path: &str,
+        usages: &[VEImageUsage],
+    ) -> Result<VEImage, VEImageError> {
+        Self::from_float_file(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            path,
+            ImageFormat::OpenExr,
+            usages,
+        )
+    }
+
+    fn from_float_file(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        path: &str,
+        file_format: ImageFormat,
+        usages: &[VEImageUsage],
+    ) -> Result<VEImage, VEImageError> {
+        let mut reader = ImageReader::open(path).map_err(VEImageError::OpeningFileFailed)?;
+        reader.set_format(file_format);
+        let img = reader
+            .decode()
+            .map_err(VEImageError::ImageDecodingFailed)?
+            .to_rgba32f();
+
+        let mut usages = usages.to_vec();
+        if !usages
+            .iter()
+            .any(|usage| matches!(usage, VEImageUsage::Sampled))
+        {
+            usages.push(VEImageUsage::Sampled);
+        }
+        let format = pick_float_format(&device, &usages)?;
+        let data: Vec<u8> = match format {
+            VEImageFormat::RGBA16f => img
+                .as_raw()
+                .iter()
+                .flat_map(|v| f16::from_f32(*v).to_ne_bytes())
+                .collect(),
+            _ => img.as_raw().iter().flat_map(|v| v.to_ne_bytes()).collect(),
+        };
+
+        VEImage::from_data(
+            device,
+            queue,
+            command_pool,
+            memory_manager,
+            &data,
+            img.width(),
+            img.height(),
+            1,
+            format,
+            &usages,
+        )
+    }
+}
@@ -2,6 +2,7 @@ use crate::core::command_buffer::VECommandBuffer;
 use crate::core::command_pool::VECommandPool;
 use crate::core::device::VEDevice;
 use crate::core::main_device_queue::VEMainDeviceQueue;
+use crate::core::sync_state::VESyncState;
 use crate::image::image::{VEImage, VEImageError};
 use ash::vk;
 use std::collections::HashMap;
@@ -19,24 +20,15 @@ impl VEImage {
         format: vk::Format,
         image_handle: vk::Image,
     ) -> Result<VEImage, VEImageError> {
-        let mut image = VEImage {
-            device: device.clone(),
-            queue: queue.clone(),
-
-            allocation: None,
-
+        let mut image = Self::wrap_swapchain_image(
+            device.clone(),
+            queue.clone(),
+            command_pool.clone(),
             width,
             height,
-            depth: 1,
-
             format,
-
-            aspect: vk::ImageAspectFlags::COLOR,
-
-            handle: image_handle,
-            views: HashMap::new(),
-            current_layout: vk::ImageLayout::UNDEFINED,
-        };
+            image_handle,
+        );
 
         let command_buffer = VECommandBuffer::new(device, command_pool)?;
         //command_buffer.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
@@ -54,4 +46,47 @@ impl VEImage {
         queue.wait_idle()?;
         Ok(image)
     }
+
+    // left in UNDEFINED, the caller is responsible for the initial transition
+    pub(crate) fn wrap_swapchain_image(
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+
+        width: u32,
+        height: u32,
+
+        format: vk::Format,
+        image_handle: vk::Image,
+    ) -> VEImage {
+        VEImage {
+            device: device.clone(),
+            queue,
+            command_pool,
+
+            allocation: None,
+            last_use: None,
+            sync_state: VESyncState::default(),
+
+            width,
+            height,
+            depth: 1,
+            mip_levels: 1,
+            array_layers: 1,
+
+            format,
+            // must match the image_usage the swapchain is created with
+            usage: vk::ImageUsageFlags::TRANSFER_DST,
+
+            aspect: vk::ImageAspectFlags::COLOR,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_indices: vec![device.queue_family_index],
+            tiling: vk::ImageTiling::OPTIMAL,
+            create_flags: vk::ImageCreateFlags::empty(),
+
+            handle: image_handle,
+            views: HashMap::new(),
+            current_layout: vk::ImageLayout::UNDEFINED,
+        }
+    }
 }
@@ -0,0 +1,275 @@
+use super::image_from_full::get_mip_size;
+use crate::compute::compute_stage::VEComputeStage;
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::descriptor_set_layout::{
+    VEDescriptorSetFieldStage, VEDescriptorSetFieldType, VEDescriptorSetLayout,
+    VEDescriptorSetLayoutField,
+};
+use crate::core::device::VEDevice;
+use crate::core::shader_module::{VEShaderModule, VEShaderModuleType};
+use crate::image::image::{VEImage, VEImageError, VEImageViewCreateInfo, VEImageViewType};
+use ash::vk;
+use std::io::Cursor;
+
+// see shaders/mipmap_downsample.comp, one variant per storage format
+fn get_mipmap_downsample_shader(format: vk::Format) -> Option<&'static [u8]> {
+    match format {
+        vk::Format::R8_SNORM => Some(include_bytes!("./shaders/mipmap_downsample_r8_snorm.spv")),
+        vk::Format::R8G8_SNORM => Some(include_bytes!("./shaders/mipmap_downsample_rg8_snorm.spv")),
+        vk::Format::R8G8B8A8_SNORM => Some(include_bytes!(
+            "./shaders/mipmap_downsample_rgba8_snorm.spv"
+        )),
+        vk::Format::R8_UNORM => Some(include_bytes!("./shaders/mipmap_downsample_r8.spv")),
+        vk::Format::R8G8_UNORM => Some(include_bytes!("./shaders/mipmap_downsample_rg8.spv")),
+        vk::Format::R8G8B8A8_UNORM => Some(include_bytes!("./shaders/mipmap_downsample_rgba8.spv")),
+        vk::Format::R16_SINT => Some(include_bytes!("./shaders/mipmap_downsample_r16i.spv")),
+        vk::Format::R16G16_SINT => Some(include_bytes!("./shaders/mipmap_downsample_rg16i.spv")),
+        vk::Format::R16G16B16A16_SINT => {
+            Some(include_bytes!("./shaders/mipmap_downsample_rgba16i.spv"))
+        }
+        vk::Format::R16_UINT => Some(include_bytes!("./shaders/mipmap_downsample_r16ui.spv")),
+        vk::Format::R16G16_UINT => Some(include_bytes!("./shaders/mipmap_downsample_rg16ui.spv")),
+        vk::Format::R16G16B16A16_UINT => {
+            Some(include_bytes!("./shaders/mipmap_downsample_rgba16ui.spv"))
+        }
+        vk::Format::R16_SFLOAT => Some(include_bytes!("./shaders/mipmap_downsample_r16f.spv")),
+        vk::Format::R16G16_SFLOAT => Some(include_bytes!("./shaders/mipmap_downsample_rg16f.spv")),
+        vk::Format::R16G16B16A16_SFLOAT => {
+            Some(include_bytes!("./shaders/mipmap_downsample_rgba16f.spv"))
+        }
+        vk::Format::R32_SINT => Some(include_bytes!("./shaders/mipmap_downsample_r32i.spv")),
+        vk::Format::R32G32_SINT => Some(include_bytes!("./shaders/mipmap_downsample_rg32i.spv")),
+        vk::Format::R32G32B32A32_SINT => {
+            Some(include_bytes!("./shaders/mipmap_downsample_rgba32i.spv"))
+        }
+        vk::Format::R32_UINT => Some(include_bytes!("./shaders/mipmap_downsample_r32ui.spv")),
+        vk::Format::R32G32_UINT => Some(include_bytes!("./shaders/mipmap_downsample_rg32ui.spv")),
+        vk::Format::R32G32B32A32_UINT => {
+            Some(include_bytes!("./shaders/mipmap_downsample_rgba32ui.spv"))
+        }
+        vk::Format::R32_SFLOAT => Some(include_bytes!("./shaders/mipmap_downsample_r32f.spv")),
+        vk::Format::R32G32_SFLOAT => Some(include_bytes!("./shaders/mipmap_downsample_rg32f.spv")),
+        vk::Format::R32G32B32A32_SFLOAT => {
+            Some(include_bytes!("./shaders/mipmap_downsample_rgba32f.spv"))
+        }
+        _ => None,
+    }
+}
+
+fn get_mip_view(level: u32, layer: u32) -> VEImageViewCreateInfo {
+    VEImageViewCreateInfo {
+        typ: VEImageViewType::View2D,
+        base_layer: layer,
+        layer_count: 1,
+        base_mipmap: level,
+        mipmap_count: 1,
+        format: None,
+        aspect: None,
+    }
+}
+
+impl VEImage {
+    pub(crate) fn format_supports_linear_blit(device: &VEDevice, format: vk::Format) -> bool {
+        device
+            .get_format_properties(format)
+            .optimal_tiling_features
+            .contains(
+                vk::FormatFeatureFlags::BLIT_SRC
+                    | vk::FormatFeatureFlags::BLIT_DST
+                    | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+            )
+    }
+
+    pub fn supports_linear_blit(&self) -> bool {
+        Self::format_supports_linear_blit(&self.device, self.format)
+    }
+
+    // blits each level from the previous one, falls back to compute if the format cannot blit
+    pub fn generate_mipmaps(&mut self) -> Result<(), VEImageError> {
+        if !self.supports_linear_blit() {
+            return self.generate_mipmaps_compute();
+        }
+        if self.depth != 1 {
+            return Err(VEImageError::MipmapsRequire2DImage);
+        }
+
+        let command_buffer = self.begin_general_commands()?;
+
+        for level in 1..self.mip_levels {
+            self.mip_barrier(
+                &command_buffer,
+                self.get_mip_range(level - 1, 1),
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::TRANSFER_READ,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+            );
+
+            let region = vk::ImageBlit::default()
+                .src_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(self.aspect)
+                        .mip_level(level - 1)
+                        .base_array_layer(0)
+                        .layer_count(self.array_layers),
+                )
+                .src_offsets([
+                    vk::Offset3D::default(),
+                    vk::Offset3D::default()
+                        .x(get_mip_size(self.width, level - 1) as i32)
+                        .y(get_mip_size(self.height, level - 1) as i32)
+                        .z(1),
+                ])
+                .dst_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(self.aspect)
+                        .mip_level(level)
+                        .base_array_layer(0)
+                        .layer_count(self.array_layers),
+                )
+                .dst_offsets([
+                    vk::Offset3D::default(),
+                    vk::Offset3D::default()
+                        .x(get_mip_size(self.width, level) as i32)
+                        .y(get_mip_size(self.height, level) as i32)
+                        .z(1),
+                ]);
+
+            unsafe {
+                self.device.device.cmd_blit_image(
+                    command_buffer.handle,
+                    self.handle,
+                    self.current_layout,
+                    self.handle,
+                    self.current_layout,
+                    &[region],
+                    vk::Filter::LINEAR,
+                );
+            }
+        }
+
+        self.finish_general_commands(
+            command_buffer,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::PipelineStageFlags::TRANSFER,
+        )
+    }
+
+    // 2x2 box filter, each level bound as a storage image, works for integer formats too
+    pub fn generate_mipmaps_compute(&mut self) -> Result<(), VEImageError> {
+        if self.depth != 1 {
+            return Err(VEImageError::MipmapsRequire2DImage);
+        }
+        let shader_code = get_mipmap_downsample_shader(self.format)
+            .ok_or(VEImageError::NoMipmapShaderForFormat)?;
+
+        let shader = VEShaderModule::from_stream(
+            self.device.clone(),
+            &mut Cursor::new(shader_code),
+            VEShaderModuleType::Compute,
+        )?;
+        let mut set_layout = VEDescriptorSetLayout::new(
+            self.device.clone(),
+            &[
+                VEDescriptorSetLayoutField {
+                    binding: 0,
+                    typ: VEDescriptorSetFieldType::StorageImage,
+                    stage: VEDescriptorSetFieldStage::Compute,
+                },
+                VEDescriptorSetLayoutField {
+                    binding: 1,
+                    typ: VEDescriptorSetFieldType::StorageImage,
+                    stage: VEDescriptorSetFieldStage::Compute,
+                },
+            ],
+        )?;
+        let compute_stage = VEComputeStage::new(
+            self.device.clone(),
+            self.command_pool.clone(),
+            &[&set_layout],
+            &shader,
+        )?;
+
+        let command_buffer = self.begin_general_commands()?;
+
+        // sets have to outlive the submission
+        let mut sets = vec![];
+        for level in 1..self.mip_levels {
+            self.mip_barrier(
+                &command_buffer,
+                self.get_mip_range(level - 1, 1),
+                vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+            );
+
+            compute_stage.bind(&command_buffer);
+            // the shader works on 2d views, one dispatch per layer
+            for layer in 0..self.array_layers {
+                let source_view = self.get_view(get_mip_view(level - 1, layer))?;
+                let destination_view = self.get_view(get_mip_view(level, layer))?;
+                let set = set_layout.create_descriptor_set()?;
+                set.bind_image_storage(0, self, source_view)?;
+                set.bind_image_storage(1, self, destination_view)?;
+
+                compute_stage.set_descriptor_set(&command_buffer, 0, &set);
+                compute_stage.dispatch(
+                    &command_buffer,
+                    get_mip_size(self.width, level).div_ceil(8),
+                    get_mip_size(self.height, level).div_ceil(8),
+                    1,
+                );
+                sets.push(set);
+            }
+        }
+
+        self.finish_general_commands(
+            command_buffer,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+        )
+    }
+
+    // all array layers of the given levels
+    fn get_mip_range(&self, base_level: u32, level_count: u32) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(self.aspect)
+            .base_mip_level(base_level)
+            .level_count(level_count)
+            .base_array_layer(0)
+            .layer_count(self.array_layers)
+    }
+
+    fn mip_barrier(
+        &self,
+        command_buffer: &VECommandBuffer,
+        range: vk::ImageSubresourceRange,
+        src_access: vk::AccessFlags,
+        dst_access: vk::AccessFlags,
+        source_stage: vk::PipelineStageFlags,
+        destination_stage: vk::PipelineStageFlags,
+    ) {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .old_layout(self.current_layout)
+            .new_layout(self.current_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.handle)
+            .subresource_range(range)
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access);
+
+        unsafe {
+            self.device.device.cmd_pipeline_barrier(
+                command_buffer.handle,
+                source_stage,
+                destination_stage,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            )
+        }
+    }
+}
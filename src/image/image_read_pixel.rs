@@ -0,0 +1,111 @@
+use super::image_from_full::get_mip_size;
+use crate::buffer::buffer::{VEBuffer, VEBufferUsage};
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::memory_properties::VEMemoryProperties;
+use crate::image::image::{VEImage, VEImageError};
+use crate::image::image_format::get_format_texel_size;
+use crate::memory::memory_manager::VEMemoryManager;
+use ash::vk;
+use std::sync::{Arc, Mutex};
+
+impl VEImage {
+    // copies one texel into a tiny staging buffer, for picking from an id target, the bytes are
+    // the raw texel of the format, for example u32::from_ne_bytes for R32_UINT
+    pub fn read_pixel(
+        &mut self,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        x: u32,
+        y: u32,
+        mip: u32,
+        layer: u32,
+    ) -> Result<Vec<u8>, VEImageError> {
+        if !self.usage.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+            return Err(VEImageError::MissingTransferSourceUsage);
+        }
+        if mip >= self.mip_levels
+            || layer >= self.array_layers
+            || x >= get_mip_size(self.width, mip)
+            || y >= get_mip_size(self.height, mip)
+        {
+            return Err(VEImageError::RegionOutOfBounds);
+        }
+        // combined depth stencil formats have no single texel size and are rejected here
+        let texel_size =
+            get_format_texel_size(self.format).ok_or(VEImageError::UnknownTexelSize)?;
+
+        let mut staging_buffer = VEBuffer::new(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            memory_manager,
+            &[VEBufferUsage::TransferDestination],
+            texel_size as vk::DeviceSize,
+            Some(VEMemoryProperties::HostCoherent),
+        )?;
+
+        let command_buffer = VECommandBuffer::new(self.device.clone(), self.command_pool.clone())?;
+        command_buffer.begin()?;
+
+        let region = vk::BufferImageCopy::default()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(
+                vk::ImageSubresourceLayers::default()
+                    .aspect_mask(self.aspect)
+                    .mip_level(mip)
+                    .base_array_layer(layer)
+                    .layer_count(1),
+            )
+            .image_offset(vk::Offset3D {
+                x: x as i32,
+                y: y as i32,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            });
+
+        let range = self.get_subresource_range(mip, layer);
+        self.as_transfer_src(&command_buffer, Some(range), |image| {
+            unsafe {
+                image.device.device.cmd_copy_image_to_buffer(
+                    command_buffer.handle,
+                    image.handle,
+                    image.current_layout,
+                    staging_buffer.buffer,
+                    &[region],
+                );
+                // makes the copy visible to the host read below
+                image.device.device.cmd_pipeline_barrier(
+                    command_buffer.handle,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::HOST,
+                    vk::DependencyFlags::empty(),
+                    &[vk::MemoryBarrier::default()
+                        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .dst_access_mask(vk::AccessFlags::HOST_READ)],
+                    &[],
+                    &[],
+                );
+            }
+            Ok(())
+        })?;
+
+        command_buffer.end()?;
+
+        {
+            let queue = self
+                .queue
+                .lock()
+                .map_err(|_| VEImageError::QueueLockingFailed)?;
+
+            command_buffer.submit(&queue, vec![], vec![])?;
+            queue.wait_idle()?;
+        }
+
+        Ok(staging_buffer.read_to_cpu()?)
+    }
+}
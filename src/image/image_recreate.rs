@@ -0,0 +1,139 @@
+use super::image_from_full::get_mip_level_count;
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::sync_state::VESyncState;
+use crate::image::image::{VEImage, VEImageError};
+use crate::memory::memory_manager::VEMemoryManager;
+use ash::vk;
+use std::sync::{Arc, Mutex};
+
+impl VEImage {
+    // replaces the image, its views and its allocation with new ones of the given size in the
+    // same memory type, format, usage and layer count, the contents are lost. framebuffers and
+    // descriptor sets built from the old views must still be rebuilt by the caller. this needs
+    // &mut, so an image shared between holders must be shared as Arc<Mutex<VEImage>>, see
+    // VEToolkit::recreate_shared_image, a plain Arc<VEImage> cannot be recreated
+    pub fn recreate(
+        &mut self,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), VEImageError> {
+        let old_allocation = self
+            .allocation
+            .clone()
+            .ok_or(VEImageError::NoOwnAllocation)?;
+        // a full mip chain stays a full chain at the new size
+        let mip_levels = if self.mip_levels == get_mip_level_count(self.width, self.height) {
+            get_mip_level_count(width, height)
+        } else {
+            self.mip_levels.min(get_mip_level_count(width, height))
+        };
+
+        let image_create_info = vk::ImageCreateInfo::default()
+            .flags(self.create_flags)
+            .image_type(if self.depth == 1 {
+                vk::ImageType::TYPE_2D
+            } else {
+                vk::ImageType::TYPE_3D
+            })
+            .extent(
+                vk::Extent3D::default()
+                    .width(width)
+                    .height(height)
+                    .depth(self.depth),
+            )
+            .mip_levels(mip_levels)
+            .array_layers(self.array_layers)
+            .format(self.format)
+            .tiling(self.tiling)
+            .usage(self.usage)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .sharing_mode(self.sharing_mode)
+            .queue_family_indices(&self.queue_family_indices)
+            .initial_layout(vk::ImageLayout::PREINITIALIZED);
+
+        // there is no deletion queue and last_use is only set by callers, so the whole queue
+        // has to be idle before the old handles are destroyed
+        self.queue
+            .lock()
+            .map_err(|_| VEImageError::QueueLockingFailed)?
+            .wait_idle()?;
+
+        let image_handle = unsafe {
+            self.device
+                .device
+                .create_image(&image_create_info, None)
+                .map_err(VEImageError::ImageCreationFailed)?
+        };
+        let mem_reqs = unsafe {
+            self.device
+                .device
+                .get_image_memory_requirements(image_handle)
+        };
+
+        {
+            let mut memory_manager = memory_manager
+                .lock()
+                .map_err(|_| VEImageError::MemoryManagerLockingFailed)?;
+            let info = memory_manager.allocation_info(&old_allocation);
+            let allocation = match info {
+                Some(info) if mem_reqs.memory_type_bits & (1 << info.memory_type_index) != 0 => {
+                    memory_manager.bind_image_memory(
+                        info.memory_type_index,
+                        image_handle,
+                        mem_reqs.size,
+                        Some(info.category),
+                    )
+                }
+                _ => {
+                    unsafe { self.device.device.destroy_image(image_handle, None) };
+                    return Err(VEImageError::NoSuitableMemoryTypeFound);
+                }
+            };
+            let allocation = match allocation {
+                Ok(allocation) => allocation,
+                Err(e) => {
+                    unsafe { self.device.device.destroy_image(image_handle, None) };
+                    return Err(e.into());
+                }
+            };
+            memory_manager.free_allocation(&old_allocation)?;
+            self.allocation = Some(allocation);
+        }
+
+        unsafe {
+            for view in self.views.values() {
+                self.device.device.destroy_image_view(*view, None);
+            }
+            self.device.device.destroy_image(self.handle, None);
+        }
+        self.views.clear();
+
+        self.handle = image_handle;
+        self.width = width;
+        self.height = height;
+        self.mip_levels = mip_levels;
+        self.current_layout = vk::ImageLayout::PREINITIALIZED;
+        self.last_use = None;
+        self.sync_state = VESyncState::default();
+
+        let command_buffer = VECommandBuffer::new(self.device.clone(), self.command_pool.clone())?;
+        command_buffer.begin()?;
+        self.transition_layout(
+            &command_buffer,
+            self.current_layout,
+            vk::ImageLayout::GENERAL,
+        )?;
+        command_buffer.end()?;
+
+        let queue = self
+            .queue
+            .lock()
+            .map_err(|_| VEImageError::QueueLockingFailed)?;
+
+        command_buffer.submit(&queue, vec![], vec![])?;
+        queue.wait_idle()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use super::image_from_full::get_mip_size;
+use crate::buffer::buffer::{VEBuffer, VEBufferUsage};
+use crate::core::command_buffer::VECommandBuffer;
+use crate::core::memory_properties::VEMemoryProperties;
+use crate::image::image::{VEImage, VEImageError};
+use crate::image::image_format::get_format_texel_size;
+use crate::memory::memory_manager::VEMemoryManager;
+use ash::vk;
+use std::sync::{Arc, Mutex};
+
+fn region_fits(offset: i32, extent: u32, size: u32) -> bool {
+    offset >= 0 && extent > 0 && offset as u64 + extent as u64 <= size as u64
+}
+
+impl VEImage {
+    // data is tightly packed for the region, the image is back in its current layout afterwards
+    pub fn update_region(
+        &mut self,
+        memory_manager: Arc<Mutex<VEMemoryManager>>,
+        offset: vk::Offset3D,
+        extent: vk::Extent3D,
+        mip: u32,
+        layer: u32,
+        data: &[u8],
+    ) -> Result<(), VEImageError> {
+        if !self.usage.contains(vk::ImageUsageFlags::TRANSFER_DST) {
+            return Err(VEImageError::MissingTransferDestinationUsage);
+        }
+        if mip >= self.mip_levels
+            || layer >= self.array_layers
+            || !region_fits(offset.x, extent.width, get_mip_size(self.width, mip))
+            || !region_fits(offset.y, extent.height, get_mip_size(self.height, mip))
+            || !region_fits(offset.z, extent.depth, get_mip_size(self.depth, mip))
+        {
+            return Err(VEImageError::RegionOutOfBounds);
+        }
+        let texel_size =
+            get_format_texel_size(self.format).ok_or(VEImageError::UnknownTexelSize)?;
+        let expected_size =
+            extent.width as u64 * extent.height as u64 * extent.depth as u64 * texel_size as u64;
+        if data.len() as u64 != expected_size {
+            return Err(VEImageError::RegionDataSizeMismatch);
+        }
+
+        let mut staging_buffer = VEBuffer::new(
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_pool.clone(),
+            memory_manager,
+            &[VEBufferUsage::TransferSource],
+            data.len() as vk::DeviceSize,
+            Some(VEMemoryProperties::HostCoherent),
+        )?;
+        staging_buffer.write(0, data)?;
+
+        let command_buffer = VECommandBuffer::new(self.device.clone(), self.command_pool.clone())?;
+        command_buffer.begin()?;
+
+        let region = vk::BufferImageCopy::default()
+            .buffer_offset(0)
+            .buffer_row_length(extent.width)
+            .buffer_image_height(extent.height)
+            .image_subresource(
+                vk::ImageSubresourceLayers::default()
+                    .aspect_mask(self.aspect)
+                    .mip_level(mip)
+                    .base_array_layer(layer)
+                    .layer_count(1),
+            )
+            .image_offset(offset)
+            .image_extent(extent);
+
+        let range = self.get_subresource_range(mip, layer);
+        self.as_transfer_dst(&command_buffer, Some(range), |image| {
+            unsafe {
+                image.device.device.cmd_copy_buffer_to_image(
+                    command_buffer.handle,
+                    staging_buffer.buffer,
+                    image.handle,
+                    image.current_layout,
+                    &[region],
+                );
+            }
+            Ok(())
+        })?;
+
+        command_buffer.end()?;
+
+        let queue = self
+            .queue
+            .lock()
+            .map_err(|_| VEImageError::QueueLockingFailed)?;
+
+        command_buffer.submit(&queue, vec![], vec![])?;
+        queue.wait_idle()?;
+
+        Ok(())
+    }
+}
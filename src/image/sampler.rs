@@ -8,8 +8,18 @@ use thiserror::Error;
 pub enum VESamplerError {
     #[error("creation failed")]
     CreationFailed(#[source] vk::Result),
+
+    #[error("mip lod bias exceeds maxSamplerLodBias")]
+    LodBiasOutOfRange,
+
+    #[error("min lod is greater than max lod")]
+    InvalidLodRange,
+
+    #[error("custom border colors need VK_EXT_custom_border_color")]
+    CustomBorderColorNotSupported,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum VESamplerAddressMode {
     Repeat,
     MirroredRepeat,
@@ -26,6 +36,73 @@ fn get_sampler_address_mode(mode: VESamplerAddressMode) -> vk::SamplerAddressMod
     }
 }
 
+// only used with ClampToBorder, float colors are for float and normalized formats,
+// int colors for integer formats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VESamplerBorderColor {
+    TransparentBlack,
+    OpaqueBlack,
+    OpaqueWhite,
+    IntTransparentBlack,
+    IntOpaqueBlack,
+    IntOpaqueWhite,
+    // need VK_EXT_custom_border_color
+    CustomFloat([f32; 4]),
+    CustomInt([i32; 4]),
+}
+
+fn get_border_color(color: VESamplerBorderColor) -> vk::BorderColor {
+    match color {
+        VESamplerBorderColor::TransparentBlack => vk::BorderColor::FLOAT_TRANSPARENT_BLACK,
+        VESamplerBorderColor::OpaqueBlack => vk::BorderColor::FLOAT_OPAQUE_BLACK,
+        VESamplerBorderColor::OpaqueWhite => vk::BorderColor::FLOAT_OPAQUE_WHITE,
+        VESamplerBorderColor::IntTransparentBlack => vk::BorderColor::INT_TRANSPARENT_BLACK,
+        VESamplerBorderColor::IntOpaqueBlack => vk::BorderColor::INT_OPAQUE_BLACK,
+        VESamplerBorderColor::IntOpaqueWhite => vk::BorderColor::INT_OPAQUE_WHITE,
+        VESamplerBorderColor::CustomFloat(_) => vk::BorderColor::FLOAT_CUSTOM_EXT,
+        VESamplerBorderColor::CustomInt(_) => vk::BorderColor::INT_CUSTOM_EXT,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VESamplerDesc {
+    pub address_mode: VESamplerAddressMode,
+    pub min_filter: VEFiltering,
+    pub mag_filter: VEFiltering,
+    pub anisotropy: bool,
+    pub mip_lod_bias: f32,
+    // vk::LOD_CLAMP_NONE as max_lod samples every mip level
+    pub min_lod: f32,
+    pub max_lod: f32,
+    pub border_color: VESamplerBorderColor,
+}
+
+impl Default for VESamplerDesc {
+    fn default() -> Self {
+        VESamplerDesc {
+            address_mode: VESamplerAddressMode::Repeat,
+            min_filter: VEFiltering::Linear,
+            mag_filter: VEFiltering::Linear,
+            anisotropy: false,
+            mip_lod_bias: 0.0,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            border_color: VESamplerBorderColor::IntOpaqueBlack,
+        }
+    }
+}
+
+impl VESamplerDesc {
+    // reads outside a shadow map count as lit instead of smearing the edge texels
+    pub fn shadow_clamp_white() -> VESamplerDesc {
+        VESamplerDesc {
+            address_mode: VESamplerAddressMode::ClampToBorder,
+            border_color: VESamplerBorderColor::OpaqueWhite,
+            ..Default::default()
+        }
+    }
+}
+
 pub struct VESampler {
     device: Arc<VEDevice>,
     pub handle: vk::Sampler,
@@ -41,6 +118,30 @@ impl VESampler {
 
         anisotropy: bool,
     ) -> Result<VESampler, VESamplerError> {
+        Self::new_with_lod_bias(
+            device,
+            sampler_address_mode,
+            min_filter,
+            mag_filter,
+            anisotropy,
+            0.0,
+        )
+    }
+
+    // the bias is added to the computed lod, negative values pick sharper mips
+    pub fn new_with_lod_bias(
+        device: Arc<VEDevice>,
+        sampler_address_mode: VESamplerAddressMode,
+
+        min_filter: VEFiltering,
+        mag_filter: VEFiltering,
+
+        anisotropy: bool,
+
+        mip_lod_bias: f32,
+    ) -> Result<VESampler, VESamplerError> {
+        // silently off without the device feature, see VEDevice::get_feature_report
+        let anisotropy = anisotropy && device.supports_sampler_anisotropy();
         let sampler_address_mode = get_sampler_address_mode(sampler_address_mode);
         let min_filter = get_filtering(min_filter);
         let mag_filter = get_filtering(mag_filter);
@@ -57,12 +158,85 @@ impl VESampler {
             .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
             .min_lod(0.0)
             .max_lod(1.0)
-            .mip_lod_bias(0.0);
+            .mip_lod_bias(mip_lod_bias);
+
+        Self::from_create_info(device, &create_info)
+    }
+
+    // for temporal upscaling, render_scale is render resolution / output resolution,
+    // the bias is log2(render_scale), for example -1.0 when rendering at half resolution
+    pub fn upscaling(
+        device: Arc<VEDevice>,
+        render_scale: f32,
+    ) -> Result<VESampler, VESamplerError> {
+        let create_info = vk::SamplerCreateInfo::default()
+            .min_filter(vk::Filter::LINEAR)
+            .mag_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::REPEAT)
+            .address_mode_w(vk::SamplerAddressMode::REPEAT)
+            .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .min_lod(0.0)
+            .max_lod(vk::LOD_CLAMP_NONE)
+            .mip_lod_bias(render_scale.log2());
+
+        Self::from_create_info(device, &create_info)
+    }
+
+    pub fn from_desc(
+        device: Arc<VEDevice>,
+        desc: &VESamplerDesc,
+    ) -> Result<VESampler, VESamplerError> {
+        if desc.min_lod > desc.max_lod {
+            return Err(VESamplerError::InvalidLodRange);
+        }
+        let custom_color = match desc.border_color {
+            VESamplerBorderColor::CustomFloat(float32) => Some(vk::ClearColorValue { float32 }),
+            VESamplerBorderColor::CustomInt(int32) => Some(vk::ClearColorValue { int32 }),
+            _ => None,
+        };
+        if custom_color.is_some() && !device.supports_custom_border_color() {
+            return Err(VESamplerError::CustomBorderColorNotSupported);
+        }
+
+        let anisotropy = desc.anisotropy && device.supports_sampler_anisotropy();
+        let address_mode = get_sampler_address_mode(desc.address_mode);
+        let mut custom_border_color = vk::SamplerCustomBorderColorCreateInfoEXT::default()
+            .custom_border_color(custom_color.unwrap_or_default())
+            .format(vk::Format::UNDEFINED);
+        let mut create_info = vk::SamplerCreateInfo::default()
+            .min_filter(get_filtering(desc.min_filter))
+            .mag_filter(get_filtering(desc.mag_filter))
+            .address_mode_u(address_mode)
+            .address_mode_v(address_mode)
+            .address_mode_w(address_mode)
+            .anisotropy_enable(anisotropy)
+            .max_anisotropy(if anisotropy { 16.0 } else { 0.0 })
+            .border_color(get_border_color(desc.border_color))
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .min_lod(desc.min_lod)
+            .max_lod(desc.max_lod)
+            .mip_lod_bias(desc.mip_lod_bias);
+        if custom_color.is_some() {
+            create_info = create_info.push_next(&mut custom_border_color);
+        }
+
+        Self::from_create_info(device, &create_info)
+    }
+
+    fn from_create_info(
+        device: Arc<VEDevice>,
+        create_info: &vk::SamplerCreateInfo,
+    ) -> Result<VESampler, VESamplerError> {
+        if create_info.mip_lod_bias.abs() > device.get_max_sampler_lod_bias() {
+            return Err(VESamplerError::LodBiasOutOfRange);
+        }
 
         let handle = unsafe {
             device
                 .device
-                .create_sampler(&create_info, None)
+                .create_sampler(create_info, None)
                 .map_err(VESamplerError::CreationFailed)?
         };
 
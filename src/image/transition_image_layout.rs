@@ -14,7 +14,60 @@ pub fn transition_image_layout(
     aspect: vk::ImageAspectFlags,
     current_layout: vk::ImageLayout,
     new_layout: vk::ImageLayout,
-) -> Result<(), VEImageError> {
+) -> Result<(vk::PipelineStageFlags, vk::AccessFlags), VEImageError> {
+    let (barrier, source_stage, destination_stage) =
+        get_transition_barrier(image_handle, aspect, current_layout, new_layout);
+
+    unsafe {
+        device.device.cmd_pipeline_barrier(
+            command_buffer.handle,
+            source_stage,
+            destination_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        )
+    }
+
+    Ok((destination_stage, barrier.dst_access_mask))
+}
+
+// barrier and stages for a whole-image layout transition, without recording it
+pub(crate) fn get_transition_barrier(
+    image_handle: vk::Image,
+    aspect: vk::ImageAspectFlags,
+    current_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) -> (
+    vk::ImageMemoryBarrier<'static>,
+    vk::PipelineStageFlags,
+    vk::PipelineStageFlags,
+) {
+    get_subresource_transition_barrier(
+        image_handle,
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(aspect)
+            .base_mip_level(0)
+            .level_count(vk::REMAINING_MIP_LEVELS)
+            .base_array_layer(0)
+            .layer_count(vk::REMAINING_ARRAY_LAYERS),
+        current_layout,
+        new_layout,
+    )
+}
+
+// same as get_transition_barrier, limited to the given mips and layers
+pub(crate) fn get_subresource_transition_barrier(
+    image_handle: vk::Image,
+    range: vk::ImageSubresourceRange,
+    current_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) -> (
+    vk::ImageMemoryBarrier<'static>,
+    vk::PipelineStageFlags,
+    vk::PipelineStageFlags,
+) {
     let mut src_access = vk::AccessFlags::empty();
     let mut dst_access = vk::AccessFlags::empty();
     let source_stage;
@@ -83,60 +136,15 @@ pub fn transition_image_layout(
         }
     }
 
-    image_memory_barrier(
-        device,
-        &command_buffer,
-        image_handle,
-        aspect,
-        current_layout,
-        new_layout,
-        src_access,
-        dst_access,
-        source_stage,
-        destination_stage,
-    );
-
-    Ok(())
-}
-
-fn image_memory_barrier(
-    device: Arc<VEDevice>,
-    command_buffer: &VECommandBuffer,
-    image_handle: vk::Image,
-    aspect: vk::ImageAspectFlags,
-    current_layout: vk::ImageLayout,
-    new_layout: vk::ImageLayout,
-    src_access: vk::AccessFlags,
-    dst_access: vk::AccessFlags,
-    source_stage: vk::PipelineStageFlags,
-    destination_stage: vk::PipelineStageFlags,
-) {
     let barrier = vk::ImageMemoryBarrier::default()
         .old_layout(current_layout)
         .new_layout(new_layout)
         .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
         .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
         .image(image_handle)
-        .subresource_range(
-            vk::ImageSubresourceRange::default()
-                .aspect_mask(aspect)
-                .base_mip_level(0)
-                .level_count(1) // TODO mip mapping
-                .base_array_layer(0)
-                .layer_count(1),
-        )
+        .subresource_range(range)
         .src_access_mask(src_access)
         .dst_access_mask(dst_access);
 
-    unsafe {
-        device.device.cmd_pipeline_barrier(
-            command_buffer.handle,
-            source_stage,
-            destination_stage,
-            vk::DependencyFlags::empty(),
-            &[],
-            &[],
-            &[barrier],
-        )
-    }
+    (barrier, source_stage, destination_stage)
 }
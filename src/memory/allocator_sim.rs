@@ -0,0 +1,295 @@
+use crate::memory::memory_chunk::{
+    find_free_offset, get_free_regions, VESingleAllocation, CHUNK_SIZE,
+};
+use crate::memory::memory_manager::get_padded_allocation_size;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VEAllocatorSimError {
+    #[error("allocation identifier already in use")]
+    DuplicateAllocation(u64),
+
+    #[error("no allocation found to free")]
+    NoAllocationFoundToFree(u64),
+
+    #[error("allocation larger than the chunk size")]
+    AllocationLargerThanChunk(u64),
+}
+
+#[derive(Clone, Debug)]
+pub enum VEAllocatorSimEvent {
+    Alloc {
+        identifier: u64,
+        size: u64,
+        alignment: u64,
+        memory_type_index: u32,
+    },
+    Free {
+        identifier: u64,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VEAllocatorSimReport {
+    pub peak_reserved_bytes: u64,
+    pub peak_used_bytes: u64,
+    pub chunk_count: usize,
+    // 1 - largest free region / total free space, worst value seen during the trace
+    pub peak_fragmentation: f64,
+    // the real allocator places on 0x1000 boundaries and ignores larger alignments
+    pub misaligned_allocations: usize,
+}
+
+struct VESimChunk {
+    chunk_identifier: u64,
+    allocations: Vec<VESingleAllocation>,
+}
+
+// replays alloc/free traces through the same placement code as VEMemoryManager, no device needed
+pub struct VEAllocatorSim {
+    chunk_size: u64,
+    chunks: HashMap<u32, Vec<VESimChunk>>,
+    live: HashMap<u64, (u32, u64)>,
+    identifier_counter: u64,
+    report: VEAllocatorSimReport,
+}
+
+impl VEAllocatorSim {
+    pub fn new() -> VEAllocatorSim {
+        VEAllocatorSim::with_chunk_size(CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(chunk_size: u64) -> VEAllocatorSim {
+        VEAllocatorSim {
+            chunk_size,
+            chunks: HashMap::new(),
+            live: HashMap::new(),
+            identifier_counter: 0,
+            report: VEAllocatorSimReport::default(),
+        }
+    }
+
+    pub fn run(
+        &mut self,
+        events: &[VEAllocatorSimEvent],
+    ) -> Result<VEAllocatorSimReport, VEAllocatorSimError> {
+        for event in events {
+            self.apply(event)?;
+        }
+        Ok(self.get_report())
+    }
+
+    pub fn apply(&mut self, event: &VEAllocatorSimEvent) -> Result<(), VEAllocatorSimError> {
+        match event {
+            VEAllocatorSimEvent::Alloc {
+                identifier,
+                size,
+                alignment,
+                memory_type_index,
+            } => self.alloc(*identifier, *size, *alignment, *memory_type_index)?,
+            VEAllocatorSimEvent::Free { identifier } => self.free(*identifier)?,
+        }
+        self.update_report();
+        Ok(())
+    }
+
+    pub fn get_report(&self) -> VEAllocatorSimReport {
+        self.report.clone()
+    }
+
+    fn alloc(
+        &mut self,
+        identifier: u64,
+        size: u64,
+        alignment: u64,
+        memory_type_index: u32,
+    ) -> Result<(), VEAllocatorSimError> {
+        if self.live.contains_key(&identifier) {
+            return Err(VEAllocatorSimError::DuplicateAllocation(identifier));
+        }
+        let padded_size = get_padded_allocation_size(size);
+        if padded_size > self.chunk_size {
+            return Err(VEAllocatorSimError::AllocationLargerThanChunk(identifier));
+        }
+
+        let chunk_size = self.chunk_size;
+        let chunks_for_type = self.chunks.entry(memory_type_index).or_default();
+        let found = chunks_for_type.iter().enumerate().find_map(|(i, chunk)| {
+            find_free_offset(&chunk.allocations, padded_size, chunk_size).map(|offset| (i, offset))
+        });
+        let (index, offset) = match found {
+            Some(found) => found,
+            None => {
+                self.identifier_counter += 1;
+                chunks_for_type.push(VESimChunk {
+                    chunk_identifier: self.identifier_counter,
+                    allocations: vec![],
+                });
+                (chunks_for_type.len() - 1, 0)
+            }
+        };
+
+        if alignment > 1 && !offset.is_multiple_of(alignment) {
+            self.report.misaligned_allocations += 1;
+        }
+        let chunk = &mut chunks_for_type[index];
+        chunk.allocations.push(VESingleAllocation {
+            alloc_identifier: identifier,
+            chunk_identifier: chunk.chunk_identifier,
+            size,
+            padded_size,
+            offset,
+        });
+        self.live
+            .insert(identifier, (memory_type_index, chunk.chunk_identifier));
+        Ok(())
+    }
+
+    fn free(&mut self, identifier: u64) -> Result<(), VEAllocatorSimError> {
+        let (memory_type_index, chunk_identifier) = self
+            .live
+            .remove(&identifier)
+            .ok_or(VEAllocatorSimError::NoAllocationFoundToFree(identifier))?;
+        if let Some(chunk) = self.chunks.get_mut(&memory_type_index).and_then(|chunks| {
+            chunks
+                .iter_mut()
+                .find(|chunk| chunk.chunk_identifier == chunk_identifier)
+        }) {
+            chunk
+                .allocations
+                .retain(|a| a.alloc_identifier != identifier);
+        }
+        Ok(())
+    }
+
+    fn update_report(&mut self) {
+        // empty chunks are kept, like the manager keeps them outside free_all, so reserved
+        // only grows
+        let chunk_count: usize = self.chunks.values().map(|chunks| chunks.len()).sum();
+        let used: u64 = self
+            .chunks
+            .values()
+            .flatten()
+            .flat_map(|chunk| chunk.allocations.iter())
+            .map(|a| a.padded_size)
+            .sum();
+
+        let free_regions: Vec<u64> = self
+            .chunks
+            .values()
+            .flatten()
+            .flat_map(|chunk| get_free_regions(&chunk.allocations, self.chunk_size))
+            .map(|(_, size)| size)
+            .collect();
+        let total_free: u64 = free_regions.iter().sum();
+        let largest_free = free_regions.iter().copied().max().unwrap_or(0);
+        let fragmentation = if total_free == 0 {
+            0.0
+        } else {
+            1.0 - largest_free as f64 / total_free as f64
+        };
+
+        self.report.chunk_count = chunk_count;
+        self.report.peak_reserved_bytes = self
+            .report
+            .peak_reserved_bytes
+            .max(chunk_count as u64 * self.chunk_size);
+        self.report.peak_used_bytes = self.report.peak_used_bytes.max(used);
+        self.report.peak_fragmentation = self.report.peak_fragmentation.max(fragmentation);
+    }
+}
+
+impl Default for VEAllocatorSim {
+    fn default() -> Self {
+        VEAllocatorSim::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: u64 = 0x1000;
+
+    fn alloc(
+        identifier: u64,
+        size: u64,
+        alignment: u64,
+        memory_type_index: u32,
+    ) -> VEAllocatorSimEvent {
+        VEAllocatorSimEvent::Alloc {
+            identifier,
+            size,
+            alignment,
+            memory_type_index,
+        }
+    }
+
+    fn free(identifier: u64) -> VEAllocatorSimEvent {
+        VEAllocatorSimEvent::Free { identifier }
+    }
+
+    // 16 page chunks, sizes are padded by one page and placement leaves a page gap
+    fn replay(events: &[VEAllocatorSimEvent]) -> VEAllocatorSimReport {
+        let mut sim = VEAllocatorSim::with_chunk_size(16 * PAGE);
+        match sim.run(events) {
+            Ok(report) => report,
+            Err(error) => panic!("replay failed: {error}"),
+        }
+    }
+
+    #[test]
+    fn replay_counts_chunks_and_peaks() {
+        let report = replay(&[
+            // pages 0..2
+            alloc(1, PAGE, 1, 0),
+            // pages 3..5, not on its 4 page alignment
+            alloc(2, PAGE, 4 * PAGE, 0),
+            // pages 6..15, leaves three single free pages in the chunk
+            alloc(3, 8 * PAGE, PAGE, 0),
+            // other memory type, own chunk
+            alloc(4, 0x100, 1, 1),
+            free(2),
+            // the freed hole is too small, a third chunk is created
+            alloc(5, 9 * PAGE, 1, 0),
+        ]);
+
+        assert_eq!(report.chunk_count, 3);
+        assert_eq!(report.peak_reserved_bytes, 3 * 16 * PAGE);
+        assert_eq!(report.peak_used_bytes, (2 + 9 + 1 + 10) * PAGE);
+        assert_eq!(report.misaligned_allocations, 1);
+        // after the third allocation, 3 free pages of which the largest region is 1
+        assert!((report.peak_fragmentation - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn freed_chunks_stay_reserved() {
+        let report = replay(&[alloc(1, PAGE, 1, 0), alloc(2, PAGE, 1, 1), free(1), free(2)]);
+
+        assert_eq!(report.chunk_count, 2);
+        assert_eq!(report.peak_reserved_bytes, 2 * 16 * PAGE);
+        assert_eq!(report.peak_used_bytes, 4 * PAGE);
+        assert_eq!(report.misaligned_allocations, 0);
+        // free space is measured over all chunks, two equally used chunks count as half
+        // fragmented
+        assert!((report.peak_fragmentation - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_invalid_traces() {
+        let mut sim = VEAllocatorSim::with_chunk_size(16 * PAGE);
+        assert!(matches!(
+            sim.run(&[alloc(1, PAGE, 1, 0), alloc(1, PAGE, 1, 0)]),
+            Err(VEAllocatorSimError::DuplicateAllocation(1))
+        ));
+        assert!(matches!(
+            sim.run(&[free(7)]),
+            Err(VEAllocatorSimError::NoAllocationFoundToFree(7))
+        ));
+        assert!(matches!(
+            sim.run(&[alloc(8, 16 * PAGE, 1, 0)]),
+            Err(VEAllocatorSimError::AllocationLargerThanChunk(8))
+        ));
+    }
+}
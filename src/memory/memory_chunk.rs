@@ -5,7 +5,7 @@ use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use thiserror::Error;
 
-static CHUNK_SIZE: u64 = 256 * 1024 * 1024;
+pub static CHUNK_SIZE: u64 = 256 * 1024 * 1024;
 
 #[derive(Error, Debug)]
 pub enum VEMemoryChunkError {
@@ -19,23 +19,43 @@ pub enum VEMemoryChunkError {
     MappingFailed(#[source] vk::Result),
     #[error("pointer not found")]
     PointerNotFound,
+    #[error("flushing failed")]
+    FlushingFailed(#[source] vk::Result),
+    #[error("invalidating failed")]
+    InvalidatingFailed(#[source] vk::Result),
+    #[error("memory is not mapped")]
+    NotMapped,
 }
 
+// linear chunks bump allocate from a cursor and only get space back on reset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VEMemoryChunkMode {
+    FreeList,
+    Linear,
+}
+
+// size is what the caller requested, padded_size is what is actually reserved in the chunk,
+// which covers the driver's memory requirements plus padding. size used to hold the driver's
+// requirement, for images the two are the same, for buffers size can now be smaller
 #[derive(Clone, Debug)]
 pub struct VESingleAllocation {
     pub alloc_identifier: u64,
     pub chunk_identifier: u64,
     pub size: u64,
+    pub padded_size: u64,
     pub offset: u64,
 }
 
 pub struct VEMemoryChunk {
     pub chunk_identifier: u64,
+    pub memory_type_index: u32,
+    pub mode: VEMemoryChunkMode,
     device: Arc<VEDevice>,
     pub allocations: Vec<VESingleAllocation>,
     pub handle: DeviceMemory,
     identifier_counter: u64,
     ptr: Option<*mut core::ffi::c_void>,
+    linear_cursor: u64,
 }
 
 unsafe impl Send for VEMemoryChunk {}
@@ -52,6 +72,20 @@ impl VEMemoryChunk {
         device: Arc<VEDevice>,
         chunk_identifier: u64,
         memory_type_index: u32,
+    ) -> Result<VEMemoryChunk, VEMemoryChunkError> {
+        Self::new_with_mode(
+            device,
+            chunk_identifier,
+            memory_type_index,
+            VEMemoryChunkMode::FreeList,
+        )
+    }
+
+    pub fn new_with_mode(
+        device: Arc<VEDevice>,
+        chunk_identifier: u64,
+        memory_type_index: u32,
+        mode: VEMemoryChunkMode,
     ) -> Result<VEMemoryChunk, VEMemoryChunkError> {
         let handle = unsafe {
             device
@@ -68,13 +102,23 @@ impl VEMemoryChunk {
         Ok(VEMemoryChunk {
             device,
             chunk_identifier,
+            memory_type_index,
+            mode,
             allocations: vec![],
             handle,
             identifier_counter: 0,
             ptr: None,
+            linear_cursor: 0,
         })
     }
 
+    // makes the whole chunk free again and keeps the device memory, only safe once the gpu
+    // is done with every resource bound to it, usually after waiting for the frame fence
+    pub fn reset(&mut self) {
+        self.allocations.clear();
+        self.linear_cursor = 0;
+    }
+
     pub fn free_allocation(&mut self, alloc_identifier: u64) {
         for i in 0..self.allocations.len() {
             if self.allocations[i].alloc_identifier == alloc_identifier {
@@ -88,6 +132,7 @@ impl VEMemoryChunk {
         &mut self,
         buffer: Buffer,
         size: u64,
+        padded_size: u64,
         offset: u64,
     ) -> Result<VESingleAllocation, VEMemoryChunkError> {
         {
@@ -103,9 +148,10 @@ impl VEMemoryChunk {
             chunk_identifier: self.chunk_identifier,
             alloc_identifier: self.identifier_counter,
             size,
+            padded_size,
             offset,
         };
-        self.allocations.push(allocation.clone());
+        self.push_allocation(allocation.clone());
         Ok(allocation)
     }
 
@@ -113,6 +159,7 @@ impl VEMemoryChunk {
         &mut self,
         image: Image,
         size: u64,
+        padded_size: u64,
         offset: u64,
     ) -> Result<VESingleAllocation, VEMemoryChunkError> {
         {
@@ -128,40 +175,72 @@ impl VEMemoryChunk {
             chunk_identifier: self.chunk_identifier,
             alloc_identifier: self.identifier_counter,
             size,
+            padded_size,
             offset,
         };
-        self.allocations.push(allocation.clone());
+        self.push_allocation(allocation.clone());
         Ok(allocation)
     }
 
-    pub fn find_free_memory_offset(&self, size: u64) -> Option<u64> {
-        if self.is_free_space(0, size) {
-            return Some(0);
+    // binds at an offset that is already recorded in allocations, used after compaction
+    pub fn rebind_buffer_memory(
+        &self,
+        buffer: Buffer,
+        offset: u64,
+    ) -> Result<(), VEMemoryChunkError> {
+        unsafe {
+            self.device
+                .device
+                .bind_buffer_memory(buffer, self.handle, offset as DeviceSize)
+                .map_err(VEMemoryChunkError::BindingBufferMemoryFailed)
         }
-        for a in &self.allocations {
-            if self.is_free_space(a.offset + a.size + 0x1000, size) {
-                return Some(a.offset + a.size + 0x1000);
-            }
+    }
+
+    pub fn rebind_image_memory(&self, image: Image, offset: u64) -> Result<(), VEMemoryChunkError> {
+        unsafe {
+            self.device
+                .device
+                .bind_image_memory(image, self.handle, offset as DeviceSize)
+                .map_err(VEMemoryChunkError::BindingImageMemoryFailed)
+        }
+    }
+
+    fn push_allocation(&mut self, allocation: VESingleAllocation) {
+        if self.mode == VEMemoryChunkMode::Linear {
+            self.linear_cursor = self
+                .linear_cursor
+                .max(allocation.offset + allocation.padded_size);
         }
-        None
-    }
-
-    fn is_free_space(&self, offset: u64, size: u64) -> bool {
-        // Check for overflow and bounds
-        match offset.checked_add(size) {
-            None => false,                          // Integer overflow
-            Some(end) if end > CHUNK_SIZE => false, // Out of bounds
-            Some(end) => {
-                // Check for overlap with any existing allocation
-                // Two ranges overlap if the start of one range is before the end of the other
-                !self.allocations.iter().any(|alloc| {
-                    let alloc_end = alloc.offset + alloc.size;
-                    offset < alloc_end && alloc.offset < end
-                })
+        self.allocations.push(allocation);
+    }
+
+    pub fn find_free_memory_offset(&self, padded_size: u64) -> Option<u64> {
+        match self.mode {
+            VEMemoryChunkMode::FreeList => {
+                find_free_offset(&self.allocations, padded_size, CHUNK_SIZE)
             }
+            // sizes are padded to 0x1000, so the cursor stays aligned
+            VEMemoryChunkMode::Linear => match self.linear_cursor.checked_add(padded_size) {
+                Some(end) if end <= CHUNK_SIZE => Some(self.linear_cursor),
+                _ => None,
+            },
         }
     }
 
+    // sorted (offset, size) gaps between allocations, for debugging fragmentation
+    pub fn free_regions(&self) -> Vec<(u64, u64)> {
+        get_free_regions(&self.allocations, CHUNK_SIZE)
+    }
+
+    // reserved bytes, padding included
+    pub fn used_size(&self) -> u64 {
+        self.allocations.iter().map(|a| a.padded_size).sum()
+    }
+
+    pub fn requested_size(&self) -> u64 {
+        self.allocations.iter().map(|a| a.size).sum()
+    }
+
     pub fn map(&mut self, offset: u64) -> Result<*mut core::ffi::c_void, VEMemoryChunkError> {
         if self.ptr.is_none() {
             // once mapped, stays mapped
@@ -183,6 +262,51 @@ impl VEMemoryChunk {
         })
     }
 
+    pub fn flush(&self, offset: u64, size: u64) -> Result<(), VEMemoryChunkError> {
+        let range = self.get_mapped_range(offset, size)?;
+        unsafe {
+            self.device
+                .device
+                .flush_mapped_memory_ranges(&[range])
+                .map_err(VEMemoryChunkError::FlushingFailed)
+        }
+    }
+
+    // makes device writes visible to the host, needed before reading non coherent memory
+    pub fn invalidate(&self, offset: u64, size: u64) -> Result<(), VEMemoryChunkError> {
+        let range = self.get_mapped_range(offset, size)?;
+        unsafe {
+            self.device
+                .device
+                .invalidate_mapped_memory_ranges(&[range])
+                .map_err(VEMemoryChunkError::InvalidatingFailed)
+        }
+    }
+
+    fn get_mapped_range(
+        &self,
+        offset: u64,
+        size: u64,
+    ) -> Result<vk::MappedMemoryRange<'static>, VEMemoryChunkError> {
+        if self.ptr.is_none() {
+            return Err(VEMemoryChunkError::NotMapped);
+        }
+        let (start, size) = get_atom_aligned_range(
+            offset,
+            size,
+            self.device.get_non_coherent_atom_size(),
+            CHUNK_SIZE,
+        );
+        Ok(vk::MappedMemoryRange::default()
+            .memory(self.handle)
+            .offset(start)
+            .size(size))
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        self.ptr.is_some()
+    }
+
     pub fn unmap(&mut self) {
         self.ptr = None;
         unsafe {
@@ -191,8 +315,130 @@ impl VEMemoryChunk {
     }
 }
 
+// (offset, size) grown outwards to nonCoherentAtomSize for flushes and invalidates, the chunk
+// size always is a multiple of it so the end is clamped there
+pub(crate) fn get_atom_aligned_range(
+    offset: u64,
+    size: u64,
+    atom_size: u64,
+    chunk_size: u64,
+) -> (u64, u64) {
+    let atom_size = atom_size.max(1);
+    let start = offset - offset % atom_size;
+    let end = ((offset + size).div_ceil(atom_size) * atom_size).min(chunk_size);
+    (start, end - start)
+}
+
+// host reads of memory without HOST_COHERENT see stale data unless the range is invalidated
+pub(crate) fn host_read_needs_invalidate(flags: Option<vk::MemoryPropertyFlags>) -> bool {
+    !flags.is_some_and(|flags| flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT))
+}
+
+// placement logic is kept free of any device state so the allocator simulator runs the same code
+pub(crate) fn find_free_offset(
+    allocations: &[VESingleAllocation],
+    size: u64,
+    chunk_size: u64,
+) -> Option<u64> {
+    if is_free_space(allocations, 0, size, chunk_size) {
+        return Some(0);
+    }
+    for a in allocations {
+        if is_free_space(
+            allocations,
+            a.offset + a.padded_size + 0x1000,
+            size,
+            chunk_size,
+        ) {
+            return Some(a.offset + a.padded_size + 0x1000);
+        }
+    }
+    None
+}
+
+pub(crate) fn get_free_regions(
+    allocations: &[VESingleAllocation],
+    chunk_size: u64,
+) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = allocations
+        .iter()
+        .map(|a| (a.offset, a.offset + a.padded_size))
+        .collect();
+    ranges.sort_unstable();
+
+    let mut regions = vec![];
+    let mut cursor = 0;
+    for (start, end) in ranges {
+        if start > cursor {
+            regions.push((cursor, start - cursor));
+        }
+        cursor = cursor.max(end);
+    }
+    if cursor < chunk_size {
+        regions.push((cursor, chunk_size - cursor));
+    }
+    regions
+}
+
+fn is_free_space(
+    allocations: &[VESingleAllocation],
+    offset: u64,
+    size: u64,
+    chunk_size: u64,
+) -> bool {
+    // Check for overflow and bounds
+    match offset.checked_add(size) {
+        None => false,                          // Integer overflow
+        Some(end) if end > chunk_size => false, // Out of bounds
+        Some(end) => {
+            // Check for overlap with any existing allocation
+            // Two ranges overlap if the start of one range is before the end of the other
+            !allocations.iter().any(|alloc| {
+                let alloc_end = alloc.offset + alloc.padded_size;
+                offset < alloc_end && alloc.offset < end
+            })
+        }
+    }
+}
+
 impl Drop for VEMemoryChunk {
     fn drop(&mut self) {
         unsafe { self.device.device.free_memory(self.handle, None) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_coherent_reads_invalidate() {
+        assert!(host_read_needs_invalidate(Some(
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED
+        )));
+        assert!(!host_read_needs_invalidate(Some(
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+        )));
+        // unknown memory types are treated as non coherent
+        assert!(host_read_needs_invalidate(None));
+    }
+
+    #[test]
+    fn invalidated_range_covers_the_read() {
+        // a 10 byte read at 70 with 64 byte atoms stays inside the atom at 64, a 60 byte
+        // read at 70 ends at 130 and also touches the atom at 128
+        assert_eq!(get_atom_aligned_range(70, 10, 64, CHUNK_SIZE), (64, 64));
+        assert_eq!(get_atom_aligned_range(70, 60, 64, CHUNK_SIZE), (64, 128));
+        assert_eq!(get_atom_aligned_range(128, 64, 64, CHUNK_SIZE), (128, 64));
+    }
+
+    #[test]
+    fn invalidated_range_stays_inside_the_chunk() {
+        assert_eq!(get_atom_aligned_range(1000, 20, 256, 1024), (768, 256));
+    }
+
+    #[test]
+    fn zero_atom_size_is_treated_as_one() {
+        assert_eq!(get_atom_aligned_range(3, 5, 0, CHUNK_SIZE), (3, 5));
+    }
+}
@@ -1,5 +1,9 @@
 use crate::core::device::VEDevice;
-use crate::memory::memory_chunk::{VEMemoryChunk, VEMemoryChunkError, VESingleAllocation};
+use crate::memory::memory_chunk::{
+    find_free_offset, host_read_needs_invalidate, VEMemoryChunk, VEMemoryChunkError,
+    VEMemoryChunkMode, VESingleAllocation, CHUNK_SIZE,
+};
+use ash::vk;
 use ash::vk::{Buffer, Image};
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
@@ -19,17 +23,186 @@ pub enum VEMemoryManagerError {
 
     #[error("mapping failed")]
     MappingFailed(#[from] VEMemoryChunkError),
+
+    #[error("cannot free allocations that are still mapped")]
+    AllocationStillMapped,
+
+    #[error("{count} transient allocations are still alive, drop them before resetting")]
+    TransientAllocationsStillAlive { count: usize },
+
+    #[error("no allocation found to write")]
+    NoAllocationFoundToWrite,
+
+    #[error("no allocation found to flush")]
+    NoAllocationFoundToFlush,
+
+    #[error("write out of allocation bounds")]
+    WriteOutOfBounds,
+
+    #[error("flush out of allocation bounds")]
+    FlushOutOfBounds,
+
+    #[error("no allocation found to read")]
+    NoAllocationFoundToRead,
+
+    #[error("read out of allocation bounds")]
+    ReadOutOfBounds,
+
+    #[error("invalidating failed")]
+    InvalidatingFailed(#[source] VEMemoryChunkError),
+
+    #[error("flushing failed")]
+    FlushingFailed(#[source] VEMemoryChunkError),
+
+    #[error("allocation failed")]
+    AllocationFailed(#[source] VEMemoryChunkError),
+
+    #[error("out of memory, compacting would not free enough contiguous space")]
+    OutOfMemory,
+
+    #[error("no allocation found to set the priority of")]
+    NoAllocationFoundToPrioritize,
+
+    #[error("priority {0} is not within 0..=1")]
+    InvalidPriority(f32),
+
+    #[error("compaction plan is stale, the chunk changed since it was planned")]
+    StaleCompactionPlan,
+
+    #[error("no live allocation found at that offset to rebind")]
+    NoAllocationFoundToRebind,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VEMemoryChunkStats {
+    pub chunk_identifier: u64,
+    pub memory_type_index: u32,
+    pub allocation_count: usize,
+    // used includes the padding, requested is the sum of the sizes asked for
+    pub used_size: u64,
+    pub requested_size: u64,
+    pub free_regions: Option<Vec<(u64, u64)>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VEAllocationInfo {
+    pub chunk_identifier: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub padded_size: u64,
+    pub memory_type_index: u32,
+    pub category: &'static str,
+}
+
+// reserved is the padded size taken from the chunks, used is the size that was requested
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VEMemoryCategoryStats {
+    pub category: &'static str,
+    pub allocation_count: usize,
+    pub reserved_size: u64,
+    pub used_size: u64,
+}
+
+// what get_stats reports, per chunk and per category
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VEMemoryStats {
+    pub chunks: Vec<VEMemoryChunkStats>,
+    pub categories: Vec<VEMemoryCategoryStats>,
+}
+
+pub static DEFAULT_CATEGORY: &str = "uncategorized";
+
+// an allocation whose offset changes when a compaction plan is committed, the resource bound
+// to `old` has to be recreated, bound at `new` and have its contents restored before it is used
+#[derive(Clone, Debug)]
+pub struct VERelocation {
+    pub old: VESingleAllocation,
+    pub new: VESingleAllocation,
+}
+
+// relocations are in ascending offset order
+#[derive(Clone, Debug)]
+pub struct VECompactionPlan {
+    pub chunk_identifier: u64,
+    pub relocations: Vec<VERelocation>,
+    packed: Vec<VESingleAllocation>,
+}
+
+#[derive(Clone, Debug)]
+pub enum VEBindOrCompact {
+    Bound(VESingleAllocation),
+    // nothing was bound, commit the plan and bind again
+    Compact(VECompactionPlan),
+}
+
+pub(crate) fn get_padded_allocation_size(size: u64) -> u64 {
+    size + (0x1000 - (size % 0x1000))
+}
+
+// offset and size relative to the allocation, against the size that was asked for
+fn is_within_allocation(allocation: &VESingleAllocation, offset: u64, size: u64) -> bool {
+    offset
+        .checked_add(size)
+        .is_some_and(|end| end <= allocation.size)
+}
+
+// VK_EXT_pageable_device_local_memory only defines priorities from 0 to 1, NaN is rejected too
+fn validate_priority(priority: f32) -> Result<(), VEMemoryManagerError> {
+    if !(0.0..=1.0).contains(&priority) {
+        return Err(VEMemoryManagerError::InvalidPriority(priority));
+    }
+    Ok(())
+}
+
+// chunk relative range read_allocation invalidates before copying, None on coherent memory
+fn get_read_invalidate_range(
+    flags: Option<vk::MemoryPropertyFlags>,
+    allocation: &VESingleAllocation,
+    offset: u64,
+    size: u64,
+) -> Option<(u64, u64)> {
+    host_read_needs_invalidate(flags).then_some((allocation.offset + offset, size))
+}
+
+// fields only, nothing is formatted unless a subscriber has TRACE enabled
+fn trace_allocation(
+    kind: &'static str,
+    memory_type_index: u32,
+    requested_size: u64,
+    alignment: u64,
+    allocation: &VESingleAllocation,
+    new_chunk: bool,
+) {
+    tracing::trace!(
+        kind,
+        memory_type_index,
+        requested_size,
+        padded_size = allocation.padded_size,
+        alignment,
+        chunk_identifier = allocation.chunk_identifier,
+        offset = allocation.offset,
+        new_chunk,
+        "memory allocation"
+    );
 }
 
 pub struct VEMemoryManager {
     device: Arc<VEDevice>,
     chunks: HashMap<u32, Vec<VEMemoryChunk>>,
     identifier_counter: u64,
+    // (chunk identifier, alloc identifier) to category
+    categories: HashMap<(u64, u64), &'static str>,
 }
 
 impl Debug for VEMemoryManager {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("VEMemoryManager")
+        f.debug_struct("VEMemoryManager")
+            .field("chunk_count", &self.chunks.values().flatten().count())
+            .field("categories", &self.get_category_stats())
+            .finish()
     }
 }
 
@@ -39,6 +212,7 @@ impl VEMemoryManager {
             device,
             chunks: HashMap::new(),
             identifier_counter: 0,
+            categories: HashMap::new(),
         }
     }
 
@@ -47,10 +221,73 @@ impl VEMemoryManager {
         memory_type_index: u32,
         buffer: Buffer,
         size: u64,
+        category: Option<&'static str>,
+    ) -> Result<VESingleAllocation, VEMemoryChunkError> {
+        self.bind_buffer_memory_with_mode(
+            memory_type_index,
+            buffer,
+            size,
+            category,
+            VEMemoryChunkMode::FreeList,
+        )
+    }
+
+    // bump allocated from the transient arena, dropping the buffer does not give the space
+    // back, only reset_transient_arena does
+    pub fn bind_transient_buffer_memory(
+        &mut self,
+        memory_type_index: u32,
+        buffer: Buffer,
+        size: u64,
+        category: Option<&'static str>,
+    ) -> Result<VESingleAllocation, VEMemoryChunkError> {
+        self.bind_buffer_memory_with_mode(
+            memory_type_index,
+            buffer,
+            size,
+            category,
+            VEMemoryChunkMode::Linear,
+        )
+    }
+
+    // size is what the caller asked for, the driver may require more than that, in which
+    // case the larger size is reserved
+    fn get_buffer_reserved_size(&self, buffer: Buffer, size: u64) -> u64 {
+        let required = unsafe { self.device.device.get_buffer_memory_requirements(buffer) }.size;
+        size.max(required)
+    }
+
+    fn bind_buffer_memory_with_mode(
+        &mut self,
+        memory_type_index: u32,
+        buffer: Buffer,
+        size: u64,
+        category: Option<&'static str>,
+        mode: VEMemoryChunkMode,
     ) -> Result<VESingleAllocation, VEMemoryChunkError> {
-        let size = size + (0x1000 - (size % 0x1000));
-        let free = self.find_free(memory_type_index, size)?;
-        free.0.bind_buffer_memory(buffer, size, free.1)
+        let padded_size = get_padded_allocation_size(self.get_buffer_reserved_size(buffer, size));
+        let free = self.find_free(memory_type_index, padded_size, mode)?;
+        let new_chunk = free.2;
+        let allocation = free
+            .0
+            .bind_buffer_memory(buffer, size, padded_size, free.1)?;
+        self.categories.insert(
+            (allocation.chunk_identifier, allocation.alloc_identifier),
+            category.unwrap_or(DEFAULT_CATEGORY),
+        );
+        if tracing::enabled!(tracing::Level::TRACE) {
+            let alignment =
+                unsafe { self.device.device.get_buffer_memory_requirements(buffer) }.alignment;
+            trace_allocation(
+                "buffer",
+                memory_type_index,
+                size,
+                alignment,
+                &allocation,
+                new_chunk,
+            );
+        }
+        Ok(allocation)
     }
 
     pub fn bind_image_memory(
@@ -58,36 +295,255 @@ impl VEMemoryManager {
         memory_type_index: u32,
         image: Image,
         size: u64,
+        category: Option<&'static str>,
+    ) -> Result<VESingleAllocation, VEMemoryChunkError> {
+        self.bind_image_memory_with_mode(
+            memory_type_index,
+            image,
+            size,
+            category,
+            VEMemoryChunkMode::FreeList,
+        )
+    }
+
+    pub fn bind_transient_image_memory(
+        &mut self,
+        memory_type_index: u32,
+        image: Image,
+        size: u64,
+        category: Option<&'static str>,
     ) -> Result<VESingleAllocation, VEMemoryChunkError> {
-        let size = size + (0x1000 - (size % 0x1000));
-        let free = self.find_free(memory_type_index, size)?;
-        free.0.bind_image_memory(image, size, free.1)
+        self.bind_image_memory_with_mode(
+            memory_type_index,
+            image,
+            size,
+            category,
+            VEMemoryChunkMode::Linear,
+        )
+    }
+
+    fn bind_image_memory_with_mode(
+        &mut self,
+        memory_type_index: u32,
+        image: Image,
+        size: u64,
+        category: Option<&'static str>,
+        mode: VEMemoryChunkMode,
+    ) -> Result<VESingleAllocation, VEMemoryChunkError> {
+        let padded_size = get_padded_allocation_size(size);
+        let free = self.find_free(memory_type_index, padded_size, mode)?;
+        let new_chunk = free.2;
+        let allocation = free.0.bind_image_memory(image, size, padded_size, free.1)?;
+        self.categories.insert(
+            (allocation.chunk_identifier, allocation.alloc_identifier),
+            category.unwrap_or(DEFAULT_CATEGORY),
+        );
+        if tracing::enabled!(tracing::Level::TRACE) {
+            let alignment =
+                unsafe { self.device.device.get_image_memory_requirements(image) }.alignment;
+            trace_allocation(
+                "image",
+                memory_type_index,
+                size,
+                alignment,
+                &allocation,
+                new_chunk,
+            );
+        }
+        Ok(allocation)
+    }
+
+    // like bind_buffer_memory, but when a new chunk cannot be allocated and an existing
+    // chunk would fit the allocation once compacted, nothing is bound and the compaction
+    // plan is returned instead, see commit_compaction for how to apply it
+    pub fn bind_buffer_memory_or_defrag(
+        &mut self,
+        memory_type_index: u32,
+        buffer: Buffer,
+        size: u64,
+        category: Option<&'static str>,
+    ) -> Result<VEBindOrCompact, VEMemoryManagerError> {
+        let reserved_size = self.get_buffer_reserved_size(buffer, size);
+        self.bind_or_plan_compaction(memory_type_index, reserved_size, |manager| {
+            manager.bind_buffer_memory(memory_type_index, buffer, size, category)
+        })
+    }
+
+    pub fn bind_image_memory_or_defrag(
+        &mut self,
+        memory_type_index: u32,
+        image: Image,
+        size: u64,
+        category: Option<&'static str>,
+    ) -> Result<VEBindOrCompact, VEMemoryManagerError> {
+        self.bind_or_plan_compaction(memory_type_index, size, |manager| {
+            manager.bind_image_memory(memory_type_index, image, size, category)
+        })
+    }
+
+    fn bind_or_plan_compaction<F>(
+        &mut self,
+        memory_type_index: u32,
+        size: u64,
+        bind: F,
+    ) -> Result<VEBindOrCompact, VEMemoryManagerError>
+    where
+        F: Fn(&mut VEMemoryManager) -> Result<VESingleAllocation, VEMemoryChunkError>,
+    {
+        match bind(self) {
+            Ok(allocation) => Ok(VEBindOrCompact::Bound(allocation)),
+            // only running out of memory for a new chunk can be helped by compacting
+            Err(VEMemoryChunkError::AllocationFailed(
+                vk::Result::ERROR_OUT_OF_DEVICE_MEMORY | vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+            )) => self
+                .plan_compaction(memory_type_index, size)
+                .map(VEBindOrCompact::Compact)
+                .ok_or(VEMemoryManagerError::OutOfMemory),
+            Err(e) => Err(VEMemoryManagerError::AllocationFailed(e)),
+        }
+    }
+
+    // packs the allocations of the first unmapped free list chunk that fits `size` afterwards,
+    // the bookkeeping is left untouched until the plan is committed
+    pub fn plan_compaction(&self, memory_type_index: u32, size: u64) -> Option<VECompactionPlan> {
+        let size = get_padded_allocation_size(size);
+        let chunks_for_type = self.chunks.get(&memory_type_index)?;
+        for chunk in chunks_for_type
+            .iter()
+            .filter(|chunk| chunk.mode == VEMemoryChunkMode::FreeList && !chunk.is_mapped())
+        {
+            let mut packed = chunk.allocations.clone();
+            packed.sort_unstable_by_key(|a| a.offset);
+            let mut cursor = 0;
+            for a in packed.iter_mut() {
+                a.offset = cursor;
+                cursor += a.padded_size + 0x1000;
+            }
+            if find_free_offset(&packed, size, CHUNK_SIZE).is_none() {
+                continue;
+            }
+
+            let mut relocations = vec![];
+            for new in packed.iter() {
+                if let Some(old) = chunk
+                    .allocations
+                    .iter()
+                    .find(|a| a.alloc_identifier == new.alloc_identifier)
+                {
+                    if old.offset != new.offset {
+                        relocations.push(VERelocation {
+                            old: old.clone(),
+                            new: new.clone(),
+                        });
+                    }
+                }
+            }
+            return Some(VECompactionPlan {
+                chunk_identifier: chunk.chunk_identifier,
+                relocations,
+                packed,
+            });
+        }
+        None
+    }
+
+    // applies the plan to the bookkeeping, nothing is copied or rebound here. before calling,
+    // wait for the gpu to be idle and save the contents of every relocated resource, then
+    // destroy those resources. afterwards create them again, bind them with rebind_buffer_memory
+    // or rebind_image_memory at `new`, restore the contents and replace every held `old`
+    pub fn commit_compaction(
+        &mut self,
+        plan: VECompactionPlan,
+    ) -> Result<(), VEMemoryManagerError> {
+        let chunk = self
+            .find_chunk_mut(plan.chunk_identifier)
+            .ok_or(VEMemoryManagerError::StaleCompactionPlan)?;
+        if chunk.is_mapped() {
+            return Err(VEMemoryManagerError::AllocationStillMapped);
+        }
+        // any allocation or free since planning invalidates the plan
+        let unchanged = chunk.allocations.len() == plan.packed.len()
+            && chunk.allocations.iter().all(|a| {
+                plan.packed
+                    .iter()
+                    .any(|p| p.alloc_identifier == a.alloc_identifier)
+                    && plan
+                        .relocations
+                        .iter()
+                        .find(|r| r.old.alloc_identifier == a.alloc_identifier)
+                        .is_none_or(|r| r.old.offset == a.offset)
+            });
+        if !unchanged {
+            return Err(VEMemoryManagerError::StaleCompactionPlan);
+        }
+        chunk.allocations = plan.packed;
+        Ok(())
+    }
+
+    // binds a resource at an allocation that is already recorded, for relocated allocations
+    pub fn rebind_buffer_memory(
+        &mut self,
+        allocation: &VESingleAllocation,
+        buffer: Buffer,
+    ) -> Result<(), VEMemoryManagerError> {
+        self.find_live_chunk(allocation)?
+            .rebind_buffer_memory(buffer, allocation.offset)
+            .map_err(VEMemoryManagerError::AllocationFailed)
+    }
+
+    pub fn rebind_image_memory(
+        &mut self,
+        allocation: &VESingleAllocation,
+        image: Image,
+    ) -> Result<(), VEMemoryManagerError> {
+        self.find_live_chunk(allocation)?
+            .rebind_image_memory(image, allocation.offset)
+            .map_err(VEMemoryManagerError::AllocationFailed)
+    }
+
+    fn find_live_chunk(
+        &mut self,
+        allocation: &VESingleAllocation,
+    ) -> Result<&mut VEMemoryChunk, VEMemoryManagerError> {
+        self.find_chunk_mut(allocation.chunk_identifier)
+            .filter(|chunk| {
+                chunk.allocations.iter().any(|a| {
+                    a.alloc_identifier == allocation.alloc_identifier
+                        && a.offset == allocation.offset
+                })
+            })
+            .ok_or(VEMemoryManagerError::NoAllocationFoundToRebind)
     }
 
     fn find_free(
         &mut self,
         memory_type_index: u32,
         size: u64,
-    ) -> Result<(&mut VEMemoryChunk, u64), VEMemoryChunkError> {
+        mode: VEMemoryChunkMode,
+    ) -> Result<(&mut VEMemoryChunk, u64, bool), VEMemoryChunkError> {
         let chunks_for_type = self.chunks.entry(memory_type_index).or_default();
 
         for i in 0..chunks_for_type.len() {
+            if chunks_for_type[i].mode != mode {
+                continue;
+            }
             if let Some(offset) = chunks_for_type[i].find_free_memory_offset(size) {
-                return Ok((&mut chunks_for_type[i], offset));
+                return Ok((&mut chunks_for_type[i], offset, false));
             }
         }
 
         // no suitable chunk found, allocate
         self.identifier_counter += 1;
-        let chunk = VEMemoryChunk::new(
+        let chunk = VEMemoryChunk::new_with_mode(
             self.device.clone(),
             self.identifier_counter,
             memory_type_index,
+            mode,
         )?;
         chunks_for_type.push(chunk);
         let last_index = chunks_for_type.len() - 1;
         // honestly, i dont know why rust allows this
-        Ok((&mut chunks_for_type[last_index], 0))
+        Ok((&mut chunks_for_type[last_index], 0, true))
     }
 
     pub fn map(
@@ -126,10 +582,399 @@ impl VEMemoryManager {
             for i in 0..chunks_for_type.len() {
                 if chunks_for_type[i].chunk_identifier == allocation.chunk_identifier {
                     chunks_for_type[i].free_allocation(allocation.alloc_identifier);
+                    self.categories
+                        .remove(&(allocation.chunk_identifier, allocation.alloc_identifier));
                     return Ok(());
                 }
             }
         }
         Err(VEMemoryManagerError::NoAllocationFoundToFree)
     }
+
+    // None if the allocation was already freed
+    pub fn allocation_info(&self, allocation: &VESingleAllocation) -> Option<VEAllocationInfo> {
+        let chunk = self
+            .chunks
+            .values()
+            .flatten()
+            .find(|chunk| chunk.chunk_identifier == allocation.chunk_identifier)?;
+        chunk
+            .allocations
+            .iter()
+            .find(|a| a.alloc_identifier == allocation.alloc_identifier)
+            .map(|a| VEAllocationInfo {
+                chunk_identifier: chunk.chunk_identifier,
+                offset: a.offset,
+                size: a.size,
+                padded_size: a.padded_size,
+                memory_type_index: chunk.memory_type_index,
+                category: self.get_category(chunk.chunk_identifier, a.alloc_identifier),
+            })
+    }
+
+    // residency hint, 1 for render targets that should stay resident, 0 for caches that may be
+    // paged out first. chunks are the unit the driver pages, so this applies to every allocation
+    // sharing the chunk, the last call wins. does nothing without VK_EXT_pageable_device_local_memory,
+    // but the priority and the allocation are checked either way
+    pub fn set_priority(
+        &mut self,
+        allocation: &VESingleAllocation,
+        priority: f32,
+    ) -> Result<(), VEMemoryManagerError> {
+        validate_priority(priority)?;
+        let device = self.device.clone();
+        let chunk = self
+            .find_chunk_mut(allocation.chunk_identifier)
+            .filter(|chunk| {
+                chunk
+                    .allocations
+                    .iter()
+                    .any(|a| a.alloc_identifier == allocation.alloc_identifier)
+            })
+            .ok_or(VEMemoryManagerError::NoAllocationFoundToPrioritize)?;
+        let Some(pageable) = &device.pageable_device_local_memory else {
+            return Ok(());
+        };
+        // ash has no wrapper for this one
+        unsafe {
+            (pageable.fp().set_device_memory_priority_ext)(
+                pageable.device(),
+                chunk.handle,
+                priority,
+            );
+        }
+        Ok(())
+    }
+
+    // retags a live allocation, returns false if it was already freed
+    pub fn set_allocation_category(
+        &mut self,
+        allocation: &VESingleAllocation,
+        category: &'static str,
+    ) -> bool {
+        match self
+            .categories
+            .get_mut(&(allocation.chunk_identifier, allocation.alloc_identifier))
+        {
+            Some(entry) => {
+                *entry = category;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // sorted by category name
+    pub fn get_category_stats(&self) -> Vec<VEMemoryCategoryStats> {
+        let mut by_category: HashMap<&'static str, VEMemoryCategoryStats> = HashMap::new();
+        for allocation in self
+            .chunks
+            .values()
+            .flatten()
+            .flat_map(|c| c.allocations.iter())
+        {
+            let category =
+                self.get_category(allocation.chunk_identifier, allocation.alloc_identifier);
+            let stats = by_category
+                .entry(category)
+                .or_insert(VEMemoryCategoryStats {
+                    category,
+                    allocation_count: 0,
+                    reserved_size: 0,
+                    used_size: 0,
+                });
+            stats.allocation_count += 1;
+            stats.reserved_size += allocation.padded_size;
+            stats.used_size += allocation.size;
+        }
+        let mut stats: Vec<VEMemoryCategoryStats> = by_category.into_values().collect();
+        stats.sort_by_key(|s| s.category);
+        stats
+    }
+
+    fn get_category(&self, chunk_identifier: u64, alloc_identifier: u64) -> &'static str {
+        self.categories
+            .get(&(chunk_identifier, alloc_identifier))
+            .copied()
+            .unwrap_or(DEFAULT_CATEGORY)
+    }
+
+    // (used, total) in bytes, used counts whole reserved chunks of device local types
+    pub fn device_local_usage(&self) -> (u64, u64) {
+        let used = self
+            .chunks
+            .iter()
+            .filter(|(memory_type_index, _)| {
+                self.device
+                    .memory_type_properties(**memory_type_index)
+                    .is_some_and(|flags| flags.contains(vk::MemoryPropertyFlags::DEVICE_LOCAL))
+            })
+            .map(|(_, chunks)| chunks.len() as u64 * CHUNK_SIZE)
+            .sum();
+        (used, self.device.get_device_local_heap_size())
+    }
+
+    // every live sub-allocation across all chunks and memory types
+    pub fn iter_allocations(&self) -> impl Iterator<Item = VEAllocationInfo> + '_ {
+        self.chunks.values().flatten().flat_map(|chunk| {
+            chunk.allocations.iter().map(|a| VEAllocationInfo {
+                chunk_identifier: chunk.chunk_identifier,
+                offset: a.offset,
+                size: a.size,
+                padded_size: a.padded_size,
+                memory_type_index: chunk.memory_type_index,
+                category: self.get_category(chunk.chunk_identifier, a.alloc_identifier),
+            })
+        })
+    }
+
+    // maps, copies and flushes if the memory type is not host coherent
+    pub fn write_allocation(
+        &mut self,
+        allocation: &VESingleAllocation,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), VEMemoryManagerError> {
+        self.write_allocation_no_flush(allocation, offset, data)?;
+        if !self.is_host_coherent(allocation) {
+            self.flush_allocation(allocation, offset, data.len() as u64)?;
+        }
+        Ok(())
+    }
+
+    /// Copies `data` into the mapped allocation without flushing. On memory that is not
+    /// `HOST_COHERENT` the written range must be passed to `flush_allocation` before the GPU
+    /// accesses it, otherwise the device may read stale data.
+    pub fn write_allocation_no_flush(
+        &mut self,
+        allocation: &VESingleAllocation,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), VEMemoryManagerError> {
+        if !is_within_allocation(allocation, offset, data.len() as u64) {
+            return Err(VEMemoryManagerError::WriteOutOfBounds);
+        }
+        let chunk = self
+            .find_chunk_mut(allocation.chunk_identifier)
+            .ok_or(VEMemoryManagerError::NoAllocationFoundToWrite)?;
+        let ptr = chunk.map(allocation.offset + offset)? as *mut u8;
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        }
+        Ok(())
+    }
+
+    pub fn flush_allocation(
+        &mut self,
+        allocation: &VESingleAllocation,
+        offset: u64,
+        size: u64,
+    ) -> Result<(), VEMemoryManagerError> {
+        // the chunk would accept it, but it may reach into a neighbouring allocation
+        if !is_within_allocation(allocation, offset, size) {
+            return Err(VEMemoryManagerError::FlushOutOfBounds);
+        }
+        self.find_chunk_mut(allocation.chunk_identifier)
+            .ok_or(VEMemoryManagerError::NoAllocationFoundToFlush)?
+            .flush(allocation.offset + offset, size)
+            .map_err(VEMemoryManagerError::FlushingFailed)
+    }
+
+    // invalidates first if the memory type is not host coherent, so device writes are seen
+    pub fn read_allocation(
+        &mut self,
+        allocation: &VESingleAllocation,
+        offset: u64,
+        data: &mut [u8],
+    ) -> Result<(), VEMemoryManagerError> {
+        if !is_within_allocation(allocation, offset, data.len() as u64) {
+            return Err(VEMemoryManagerError::ReadOutOfBounds);
+        }
+        let invalidate_range = get_read_invalidate_range(
+            self.memory_flags(allocation),
+            allocation,
+            offset,
+            data.len() as u64,
+        );
+        let chunk = self
+            .find_chunk_mut(allocation.chunk_identifier)
+            .ok_or(VEMemoryManagerError::NoAllocationFoundToRead)?;
+        let ptr = chunk.map(allocation.offset + offset)? as *const u8;
+        // before the copy, otherwise the host may read stale data
+        if let Some((start, size)) = invalidate_range {
+            chunk
+                .invalidate(start, size)
+                .map_err(VEMemoryManagerError::InvalidatingFailed)?;
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(ptr, data.as_mut_ptr(), data.len());
+        }
+        Ok(())
+    }
+
+    // for callers reading through their own mapped pointer, does nothing on coherent memory
+    pub fn invalidate_allocation(
+        &mut self,
+        allocation: &VESingleAllocation,
+        offset: u64,
+        size: u64,
+    ) -> Result<(), VEMemoryManagerError> {
+        if self.is_host_coherent(allocation) {
+            return Ok(());
+        }
+        self.find_chunk_mut(allocation.chunk_identifier)
+            .ok_or(VEMemoryManagerError::NoAllocationFoundToRead)?
+            .invalidate(allocation.offset + offset, size)
+            .map_err(VEMemoryManagerError::InvalidatingFailed)
+    }
+
+    fn is_host_coherent(&self, allocation: &VESingleAllocation) -> bool {
+        !host_read_needs_invalidate(self.memory_flags(allocation))
+    }
+
+    fn memory_flags(&self, allocation: &VESingleAllocation) -> Option<vk::MemoryPropertyFlags> {
+        self.chunks
+            .values()
+            .flatten()
+            .find(|chunk| chunk.chunk_identifier == allocation.chunk_identifier)
+            .and_then(|chunk| self.device.memory_type_properties(chunk.memory_type_index))
+    }
+
+    fn find_chunk_mut(&mut self, chunk_identifier: u64) -> Option<&mut VEMemoryChunk> {
+        self.chunks
+            .values_mut()
+            .flatten()
+            .find(|chunk| chunk.chunk_identifier == chunk_identifier)
+    }
+
+    // empties every transient chunk without freeing device memory. only safe once the gpu
+    // finished the frame that used them, every transient buffer has to be dropped before, if
+    // some are still alive nothing is reset as their memory would be handed out again
+    pub fn reset_transient_arena(&mut self) -> Result<(), VEMemoryManagerError> {
+        let live: usize = self
+            .chunks
+            .values()
+            .flatten()
+            .filter(|chunk| chunk.mode == VEMemoryChunkMode::Linear)
+            .map(|chunk| chunk.allocations.len())
+            .sum();
+        if live > 0 {
+            return Err(VEMemoryManagerError::TransientAllocationsStillAlive { count: live });
+        }
+        for chunk in self
+            .chunks
+            .values_mut()
+            .flatten()
+            .filter(|chunk| chunk.mode == VEMemoryChunkMode::Linear)
+        {
+            chunk.reset();
+        }
+        Ok(())
+    }
+
+    // frees every allocation of the type and releases its chunks, returns the number freed.
+    // mapped chunks are unmapped first. buffers and images bound to them may still exist,
+    // they must not be used afterwards and only be dropped. chunk identifiers are never
+    // reused, so their later free_allocation finds no chunk and does nothing
+    pub fn free_all(&mut self, memory_type_index: u32) -> usize {
+        let Some(mut chunks) = self.chunks.remove(&memory_type_index) else {
+            return 0;
+        };
+        for chunk in chunks.iter_mut() {
+            if chunk.is_mapped() {
+                chunk.unmap();
+            }
+            self.categories
+                .retain(|(chunk_identifier, _), _| *chunk_identifier != chunk.chunk_identifier);
+        }
+        // dropping the chunks releases the device memory
+        chunks.iter().map(|c| c.allocations.len()).sum()
+    }
+
+    pub fn get_stats(&self, verbose: bool) -> VEMemoryStats {
+        let mut stats = vec![];
+        for chunks_for_type in self.chunks.values() {
+            for chunk in chunks_for_type {
+                stats.push(VEMemoryChunkStats {
+                    chunk_identifier: chunk.chunk_identifier,
+                    memory_type_index: chunk.memory_type_index,
+                    allocation_count: chunk.allocations.len(),
+                    used_size: chunk.used_size(),
+                    requested_size: chunk.requested_size(),
+                    // free list walk sorts the allocations, only do it when asked
+                    free_regions: if verbose {
+                        Some(chunk.free_regions())
+                    } else {
+                        None
+                    },
+                });
+            }
+        }
+        stats.sort_by_key(|s| s.chunk_identifier);
+        VEMemoryStats {
+            chunks: stats,
+            categories: self.get_category_stats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocation() -> VESingleAllocation {
+        VESingleAllocation {
+            alloc_identifier: 1,
+            chunk_identifier: 1,
+            size: 256,
+            padded_size: 0x1000,
+            offset: 0x2000,
+        }
+    }
+
+    #[test]
+    fn coherent_reads_skip_invalidate() {
+        let flags = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        assert_eq!(
+            get_read_invalidate_range(Some(flags), &allocation(), 16, 32),
+            None
+        );
+    }
+
+    #[test]
+    fn non_coherent_reads_invalidate_the_read_range() {
+        let flags = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED;
+        assert_eq!(
+            get_read_invalidate_range(Some(flags), &allocation(), 16, 32),
+            Some((0x2000 + 16, 32))
+        );
+        // unknown memory types are treated as non coherent
+        assert_eq!(
+            get_read_invalidate_range(None, &allocation(), 0, 256),
+            Some((0x2000, 256))
+        );
+    }
+
+    #[test]
+    fn priority_must_be_within_zero_and_one() {
+        assert!(validate_priority(0.0).is_ok());
+        assert!(validate_priority(0.5).is_ok());
+        assert!(validate_priority(1.0).is_ok());
+        for priority in [-0.1, 1.1, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                validate_priority(priority),
+                Err(VEMemoryManagerError::InvalidPriority(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn ranges_must_stay_inside_the_allocation() {
+        assert!(is_within_allocation(&allocation(), 0, 256));
+        assert!(is_within_allocation(&allocation(), 200, 56));
+        assert!(!is_within_allocation(&allocation(), 200, 57));
+        // the padding belongs to the chunk, not to the allocation
+        assert!(!is_within_allocation(&allocation(), 0, 0x1000));
+        assert!(!is_within_allocation(&allocation(), u64::MAX, 1));
+    }
 }
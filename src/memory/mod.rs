@@ -1,2 +1,3 @@
+pub mod allocator_sim;
 pub mod memory_chunk;
 pub mod memory_manager;
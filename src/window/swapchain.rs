@@ -2,6 +2,7 @@ use crate::core::command_buffer::{VECommandBuffer, VECommandBufferError};
 use crate::core::command_pool::VECommandPool;
 use crate::core::device::VEDevice;
 use crate::core::main_device_queue::VEMainDeviceQueue;
+use crate::core::memory_barrier::VEBarrierBatch;
 use crate::core::semaphore::{SemaphoreState, VESemaphore, VESemaphoreError};
 use crate::image::image::{VEImage, VEImageError};
 use crate::window::window::VEWindow;
@@ -13,6 +14,16 @@ use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use winit::dpi::PhysicalSize;
 
+pub fn get_present_mode_name(present_mode: vk::PresentModeKHR) -> &'static str {
+    match present_mode {
+        vk::PresentModeKHR::FIFO => "VSync (FIFO)",
+        vk::PresentModeKHR::FIFO_RELAXED => "Adaptive VSync (FIFO Relaxed)",
+        vk::PresentModeKHR::MAILBOX => "Triple Buffered (Mailbox)",
+        vk::PresentModeKHR::IMMEDIATE => "No VSync (Immediate)",
+        _ => "Unknown",
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum VESwapchainError {
     #[error("no winit window found")]
@@ -62,6 +73,9 @@ pub enum VESwapchainError {
 
     #[error("cannot get swapchain images")]
     CannotGetSwapchainImages(#[source] vk::Result),
+
+    #[error("no suitable present mode found")]
+    NoSuitablePresentMode,
 }
 
 pub struct VESwapchain {
@@ -74,6 +88,8 @@ pub struct VESwapchain {
     pub present_images: Vec<VEImage>,
     pub width: u32,
     pub height: u32,
+    present_mode: vk::PresentModeKHR,
+    desired_image_count: Option<u32>,
 
     acquire_ready_semaphore: Arc<Mutex<VESemaphore>>,
     pub blit_done_semaphore: Arc<Mutex<VESemaphore>>,
@@ -92,6 +108,21 @@ impl VESwapchain {
         device: Arc<VEDevice>,
         queue: Arc<Mutex<VEMainDeviceQueue>>,
         command_pool: Arc<VECommandPool>,
+    ) -> Result<VESwapchain, VESwapchainError> {
+        Self::new_with_image_count(window, device, queue, command_pool, None)
+    }
+
+    // the count is clamped to the surface limits and the driver may create more, check
+    // image_count. None means min + 1. with FIFO every extra image adds up to a frame of
+    // latency while the queue is full, with MAILBOX extra images only give the renderer
+    // more room to run ahead since queued frames get replaced. 3 is triple buffering.
+    // per-frame resources such as VEPerFrameDescriptorSet can be sized with image_count
+    pub fn new_with_image_count(
+        window: &VEWindow,
+        device: Arc<VEDevice>,
+        queue: Arc<Mutex<VEMainDeviceQueue>>,
+        command_pool: Arc<VECommandPool>,
+        desired_image_count: Option<u32>,
     ) -> Result<VESwapchain, VESwapchainError> {
         let winit_window = window
             .window
@@ -100,11 +131,20 @@ impl VESwapchain {
             .lock()
             .map_err(|_| VESwapchainError::WindowLockingFailed)?;
 
+        let present_mode = Self::get_present_modes(&device)?
+            .iter()
+            .cloned()
+            .find(|&mode| mode == vk::PresentModeKHR::MAILBOX)
+            .unwrap_or(vk::PresentModeKHR::FIFO);
+
         let (swapchain, swapchain_loader, present_images) = Self::create_swapchain_images(
             device.clone(),
             queue.clone(),
             command_pool.clone(),
             winit_window.inner_size(),
+            present_mode,
+            desired_image_count,
+            SwapchainKHR::null(),
         )?;
 
         let acquire_ready_semaphore = VESemaphore::new(device.clone())?;
@@ -121,6 +161,8 @@ impl VESwapchain {
 
             width: winit_window.inner_size().width,
             height: winit_window.inner_size().height,
+            present_mode,
+            desired_image_count,
 
             acquire_ready_semaphore: Arc::new(Mutex::from(acquire_ready_semaphore)),
             blit_done_semaphore: Arc::new(Mutex::from(blit_done_semaphore)),
@@ -133,6 +175,9 @@ impl VESwapchain {
         main_device_queue: Arc<Mutex<VEMainDeviceQueue>>,
         command_pool: Arc<VECommandPool>,
         new_size: PhysicalSize<u32>,
+        present_mode: vk::PresentModeKHR,
+        desired_image_count: Option<u32>,
+        old_swapchain: SwapchainKHR,
     ) -> Result<(SwapchainKHR, swapchain::Device, Vec<VEImage>), VESwapchainError> {
         let swapchain_loader = swapchain::Device::new(&device.instance, &device.device);
 
@@ -149,7 +194,10 @@ impl VESwapchain {
                 .get_physical_device_surface_capabilities(device.physical_device, device.surface)
                 .map_err(VESwapchainError::CannotGetPhysicalDeviceSurfaceCapabilities)?
         };
-        let mut desired_image_count = surface_capabilities.min_image_count + 1;
+        let mut desired_image_count = desired_image_count
+            .unwrap_or(surface_capabilities.min_image_count + 1)
+            .max(surface_capabilities.min_image_count);
+        // max of 0 means no limit
         if surface_capabilities.max_image_count > 0
             && desired_image_count > surface_capabilities.max_image_count
         {
@@ -170,18 +218,6 @@ impl VESwapchain {
         } else {
             surface_capabilities.current_transform
         };
-        let present_modes = unsafe {
-            device
-                .surface_loader
-                .get_physical_device_surface_present_modes(device.physical_device, device.surface)
-                .map_err(VESwapchainError::CannotGetPhysicalDeviceSurfacePresentModes)?
-        };
-        let present_mode = present_modes
-            .iter()
-            .cloned()
-            .find(|&mode| mode == vk::PresentModeKHR::MAILBOX)
-            .unwrap_or(vk::PresentModeKHR::FIFO);
-
         let swapchain_create_info = vk::SwapchainCreateInfoKHR::default()
             .surface(device.surface)
             .min_image_count(desired_image_count)
@@ -194,7 +230,8 @@ impl VESwapchain {
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
             .present_mode(present_mode)
             .clipped(true)
-            .image_array_layers(1);
+            .image_array_layers(1)
+            .old_swapchain(old_swapchain);
 
         let swapchain = unsafe {
             swapchain_loader
@@ -208,42 +245,87 @@ impl VESwapchain {
                 .map_err(VESwapchainError::CannotGetSwapchainImages)?
         };
 
-        let mut present_images = vec![];
-        for i in 0..present_images_raw.len() {
-            present_images.push(VEImage::from_swapchain_present_image(
-                device.clone(),
-                main_device_queue.clone(),
-                command_pool.clone(),
-                surface_resolution.width,
-                surface_resolution.height,
-                surface_format.format,
-                present_images_raw[i],
-            )?);
+        let mut present_images: Vec<VEImage> = present_images_raw
+            .iter()
+            .map(|handle| {
+                VEImage::wrap_swapchain_image(
+                    device.clone(),
+                    main_device_queue.clone(),
+                    command_pool.clone(),
+                    surface_resolution.width,
+                    surface_resolution.height,
+                    surface_format.format,
+                    *handle,
+                )
+            })
+            .collect();
+
+        // every image goes to PRESENT_SRC in one submission so the first present is valid,
+        // the transitions share a stage pair and get recorded as a single barrier call
+        let command_buffer = VECommandBuffer::new(device.clone(), command_pool.clone())?;
+        command_buffer.begin()?;
+        let mut batch = VEBarrierBatch::new();
+        for image in present_images.iter_mut() {
+            let current_layout = image.current_layout;
+            image.queue_transition_layout(
+                &mut batch,
+                current_layout,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+            );
         }
+        batch.flush(&device, &command_buffer);
+        command_buffer.end()?;
+        {
+            let queue = main_device_queue
+                .lock()
+                .map_err(|_| VESwapchainError::QueueLockingFailed)?;
+            command_buffer.submit(&queue, vec![], vec![])?;
+            queue
+                .wait_idle()
+                .map_err(|_| VESwapchainError::QueueWaitIdleFailed)?;
+        }
+
         Ok((swapchain, swapchain_loader, present_images))
     }
 
     pub fn recreate(&mut self, new_size: PhysicalSize<u32>) -> Result<(), VESwapchainError> {
+        self.recreate_with(new_size, self.present_mode, self.desired_image_count)
+    }
+
+    // present_mode and desired_image_count are only stored once the new swapchain exists,
+    // a failed creation leaves the previous settings and swapchain in place
+    fn recreate_with(
+        &mut self,
+        new_size: PhysicalSize<u32>,
+        present_mode: vk::PresentModeKHR,
+        desired_image_count: Option<u32>,
+    ) -> Result<(), VESwapchainError> {
         self.queue
             .lock()
             .map_err(|_| VEImageError::QueueLockingFailed)?
             .wait_idle()
             .map_err(|_| VESwapchainError::QueueWaitIdleFailed)?;
 
-        self.present_images.clear();
-
-        unsafe {
-            self.swapchain_loader
-                .destroy_swapchain(self.swapchain, None);
-        }
-
         let (swapchain, swapchain_loader, present_images) = Self::create_swapchain_images(
             self.device.clone(),
             self.queue.clone(),
             self.command_pool.clone(),
             new_size,
+            present_mode,
+            desired_image_count,
+            self.swapchain,
         )?;
 
+        self.present_mode = present_mode;
+        self.desired_image_count = desired_image_count;
+        self.present_images.clear();
+
+        // old swapchain is retired by the creation above, safe to destroy now
+        unsafe {
+            self.swapchain_loader
+                .destroy_swapchain(self.swapchain, None);
+        }
+
         self.present_images = present_images;
 
         self.swapchain_loader = swapchain_loader;
@@ -269,6 +351,84 @@ impl VESwapchain {
         Ok(())
     }
 
+    pub fn set_vsync(&mut self, enabled: bool) -> Result<(), VESwapchainError> {
+        let present_modes = Self::get_present_modes(&self.device)?;
+        let preferred: &[vk::PresentModeKHR] = if enabled {
+            &[vk::PresentModeKHR::FIFO]
+        } else {
+            &[vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::IMMEDIATE]
+        };
+        let present_mode = preferred
+            .iter()
+            .cloned()
+            .find(|mode| present_modes.contains(mode))
+            .ok_or(VESwapchainError::NoSuitablePresentMode)?;
+
+        self.recreate_with(
+            PhysicalSize::new(self.width, self.height),
+            present_mode,
+            self.desired_image_count,
+        )
+    }
+
+    // recreates the swapchain, see new_with_image_count
+    pub fn set_image_count(
+        &mut self,
+        desired_image_count: Option<u32>,
+    ) -> Result<(), VESwapchainError> {
+        self.recreate_with(
+            PhysicalSize::new(self.width, self.height),
+            self.present_mode,
+            desired_image_count,
+        )
+    }
+
+    // number of images the driver actually created
+    pub fn image_count(&self) -> u32 {
+        self.present_images.len() as u32
+    }
+
+    pub fn get_image(&self, index: u32) -> Option<&VEImage> {
+        self.present_images.get(index as usize)
+    }
+
+    // modes the surface supports, for a settings ui, see get_present_mode_name
+    pub fn present_modes(&self) -> Result<Vec<vk::PresentModeKHR>, VESwapchainError> {
+        Self::get_present_modes(&self.device)
+    }
+
+    pub fn get_present_mode(&self) -> vk::PresentModeKHR {
+        self.present_mode
+    }
+
+    // recreates the swapchain, the mode has to be one of present_modes
+    pub fn set_present_mode(
+        &mut self,
+        present_mode: vk::PresentModeKHR,
+    ) -> Result<(), VESwapchainError> {
+        if !Self::get_present_modes(&self.device)?.contains(&present_mode) {
+            return Err(VESwapchainError::NoSuitablePresentMode);
+        }
+        self.recreate_with(
+            PhysicalSize::new(self.width, self.height),
+            present_mode,
+            self.desired_image_count,
+        )
+    }
+
+    pub fn is_vsync(&self) -> bool {
+        self.present_mode == vk::PresentModeKHR::FIFO
+    }
+
+    fn get_present_modes(device: &VEDevice) -> Result<Vec<vk::PresentModeKHR>, VESwapchainError> {
+        unsafe {
+            device
+                .surface_loader
+                .get_physical_device_surface_present_modes(device.physical_device, device.surface)
+                .map_err(VESwapchainError::CannotGetPhysicalDeviceSurfacePresentModes)
+        }
+    }
+
     pub fn blit(
         &mut self,
         source: &VEImage,